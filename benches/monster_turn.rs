@@ -0,0 +1,86 @@
+//! Times one monster turn (perception, deciding, combat and movement) with
+//! thousands of monsters wandering an open map, those that spot the player
+//! hunting it. The map grows with the crowd so density stays the same, and with
+//! the spatial index the time per monster should stay roughly flat as the crowd
+//! grows. Every iteration starts from a freshly built world, so each one times
+//! the same first turn.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use ferros_rts::prelude::*;
+use ferros_rts::systems;
+
+/// Open tiles per monster
+const TILES_PER_MONSTER: usize = 20;
+
+fn build_world(monsters: usize) -> (World, Resources) {
+    let mut ecs = World::default();
+    let mut resources = Resources::default();
+    let size = ((monsters * TILES_PER_MONSTER) as f64).sqrt() as i32;
+    let map = Map::new(size, size);
+    let mut rng = RandomNumberGenerator::seeded(monsters as u64);
+
+    ecs.push((
+        Player,
+        Point::new(size / 2, size / 2),
+        Health::new(i32::MAX / 2),
+        CombatStats::default(),
+        StatusEffects::default(),
+    ));
+    for _ in 0..monsters {
+        let pos = Point::new(rng.range(0, size), rng.range(0, size));
+        ecs.push((
+            Enemy,
+            Behaviour::brute(),
+            Awareness::default(),
+            pos,
+            Health::new(1),
+            CombatStats::default(),
+            StatusEffects::default(),
+        ));
+    }
+
+    resources.insert(SpatialIndex::build(&map, &ecs));
+    resources.insert(map);
+    resources.insert(Camera::new(Point::zero()));
+    resources.insert(MovementRules { diagonals: true });
+    resources.insert(EventChannel::default());
+    resources.insert(Noises::default());
+    resources.insert(GameRng::seeded(monsters as u64));
+    (ecs, resources)
+}
+
+fn monster_turn() -> Schedule {
+    Schedule::builder()
+        .add_system(systems::perception_system())
+        .add_system(systems::clear_events_system())
+        .add_system(systems::monster_ai_system())
+        .flush()
+        .add_system(systems::characters::combat_system())
+        .flush()
+        .add_system(systems::characters::movement_system())
+        .flush()
+        .build()
+}
+
+fn bench_monster_turn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("monster_turn");
+    group.sample_size(20);
+    for monsters in [1000, 2000, 4000, 8000] {
+        let mut schedule = monster_turn();
+        group.throughput(Throughput::Elements(monsters as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(monsters), &monsters, |b, _| {
+            b.iter_batched(
+                || build_world(monsters),
+                |(mut ecs, mut resources)| {
+                    schedule.execute(&mut ecs, &mut resources);
+                    (ecs, resources)
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_monster_turn);
+criterion_main!(benches);
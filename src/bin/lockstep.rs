@@ -0,0 +1,156 @@
+//! Plays a headless skirmish between computer players over lockstep TCP and
+//! checks that every player's world ends up the same.
+//!
+//! ```text
+//! lockstep [--players N] [--workers N] [--soldiers N] [--turns N] [--seed N] [--recipe PATH]
+//!          [--difficulty easy|normal|hard] [--desync TURN]
+//! lockstep --host ADDRESS [--players N] [--workers N] [--soldiers N] [--turns N] [--seed N]
+//!          [--recipe PATH] [--difficulty easy|normal|hard]
+//! lockstep --join ADDRESS [--turns N] [--difficulty easy|normal|hard] [--desync TURN]
+//! ```
+//!
+//! Without `--host` or `--join` every player runs on its own thread in this
+//! process, connected over localhost. `--desync` knocks a hit point off a unit
+//! in each joining player's world before that turn, to show the host catching it.
+
+use std::net::TcpListener;
+
+use ferros_rts::prelude::*;
+
+enum Role {
+    Local,
+    Host(String),
+    Join(String),
+}
+
+struct Options {
+    role: Role,
+    settings: SkirmishSettings,
+    turns: u32,
+    difficulty: Difficulty,
+    desync: Option<u32>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut role = Role::Local;
+    let mut settings = SkirmishSettings::default();
+    let mut seed = None;
+    let mut turns = 1000;
+    let mut difficulty = Difficulty::default();
+    let mut desync = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} expects a value", arg));
+        match arg.as_str() {
+            "--host" => role = Role::Host(value()?),
+            "--join" => role = Role::Join(value()?),
+            "--players" => {
+                settings.factions = value()?.parse().map_err(|_| "--players expects a number")?
+            }
+            "--workers" => {
+                settings.workers_per_faction =
+                    value()?.parse().map_err(|_| "--workers expects a number")?
+            }
+            "--soldiers" => {
+                settings.soldiers_per_faction = value()?
+                    .parse()
+                    .map_err(|_| "--soldiers expects a number")?
+            }
+            "--difficulty" => {
+                let name = value()?;
+                difficulty =
+                    Difficulty::parse(&name).ok_or(format!("unknown difficulty {}", name))?
+            }
+            "--turns" => turns = value()?.parse().map_err(|_| "--turns expects a number")?,
+            "--seed" => seed = Some(value()?.parse().map_err(|_| "--seed expects a number")?),
+            "--recipe" => {
+                settings.recipe = GeneratorRecipe::load(value()?).map_err(|e| e.to_string())?
+            }
+            "--desync" => desync = Some(value()?.parse().map_err(|_| "--desync expects a turn")?),
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+    if settings.factions < 2 {
+        return Err("--players must be at least 2".to_string());
+    }
+
+    settings.seed = seed.or(settings.recipe.seed).unwrap_or(0);
+    Ok(Options {
+        role,
+        settings,
+        turns,
+        difficulty,
+        desync,
+    })
+}
+
+fn report(outcome: &MatchOutcome) {
+    let result = match outcome.winner {
+        Some(winner) => format!("faction {} won", winner.0),
+        None => "no winner".to_string(),
+    };
+    println!(
+        "faction {}: {} turns, {}, checksum {:016x}",
+        outcome.faction.0, outcome.turns, result, outcome.checksum
+    );
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("lockstep: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let outcomes = match &options.role {
+        Role::Local => play_local(
+            &options.settings,
+            options.turns,
+            options.difficulty,
+            options.desync,
+        ),
+        Role::Host(address) => TcpListener::bind(address)
+            .map_err(LockstepError::from)
+            .and_then(|listener| Lockstep::host(&listener, &options.settings))
+            .and_then(|session| {
+                play(
+                    session,
+                    &options.settings,
+                    options.turns,
+                    options.difficulty,
+                    None,
+                )
+            })
+            .map(|outcome| vec![outcome]),
+        Role::Join(address) => Lockstep::join(address)
+            .and_then(|(session, settings)| {
+                play(
+                    session,
+                    &settings,
+                    options.turns,
+                    options.difficulty,
+                    options.desync,
+                )
+            })
+            .map(|outcome| vec![outcome]),
+    };
+    let outcomes = match outcomes {
+        Ok(outcomes) => outcomes,
+        Err(error) => {
+            eprintln!("lockstep: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    outcomes.iter().for_each(report);
+    if outcomes
+        .windows(2)
+        .any(|pair| pair[0].checksum != pair[1].checksum)
+    {
+        eprintln!("lockstep: the players' worlds differ");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,283 @@
+//! Runs the world generator headlessly and exports the result.
+//!
+//! ```text
+//! mapgen [--recipe PATH] [--seed N] [--out PREFIX] [--format ascii,png,json]
+//! mapgen [--recipe PATH] [--seed N] --batch COUNT
+//! ```
+
+use std::fs::File;
+use std::io::BufWriter;
+
+use ferros_rts::prelude::*;
+use serde::Serialize;
+
+const PNG_SCALE: u32 = 4;
+
+struct Options {
+    recipe: GeneratorRecipe,
+    seed: u64,
+    out: String,
+    ascii: bool,
+    png: bool,
+    json: bool,
+    batch: Option<u64>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut recipe = None;
+    let mut seed = None;
+    let mut out = None;
+    let mut formats = String::from("ascii,png,json");
+    let mut batch = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} expects a value", arg));
+        match arg.as_str() {
+            "--recipe" => {
+                recipe = Some(GeneratorRecipe::load(value()?).map_err(|e| e.to_string())?)
+            }
+            "--seed" => seed = Some(value()?.parse().map_err(|_| "--seed expects a number")?),
+            "--out" => out = Some(value()?),
+            "--format" => formats = value()?,
+            "--batch" => batch = Some(value()?.parse().map_err(|_| "--batch expects a number")?),
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+
+    let recipe = recipe.unwrap_or_default();
+    let seed = seed.or(recipe.seed).unwrap_or(0);
+    Ok(Options {
+        out: out.unwrap_or(format!("map-{}", seed)),
+        ascii: formats.contains("ascii"),
+        png: formats.contains("png"),
+        json: formats.contains("json"),
+        recipe,
+        seed,
+        batch,
+    })
+}
+
+fn generate(recipe: &GeneratorRecipe, seed: u64) -> (MapBuilder, Map) {
+    let mut rng = RandomNumberGenerator::seeded(seed);
+    let mut builder = recipe.builder();
+    recipe.runner().run_to_completion(&mut builder, &mut rng);
+    let MapResult { map, .. } = builder.build_map();
+    (builder, map)
+}
+
+fn ascii(theme: TileTheme, builder: &MapBuilder, map: &Map) -> String {
+    let mut text = String::with_capacity(map.tiles.len() + map.height as usize);
+    for (idx, tile) in map.tiles.iter().enumerate() {
+        let point = map.point_at(idx);
+        let glyph = if builder.player == Some(point) {
+            '@'
+        } else {
+            to_char(theme.glyph(*tile) as u8)
+        };
+        text.push(glyph);
+        if point.x == map.width - 1 {
+            text.push('\n');
+        }
+    }
+    text
+}
+
+fn write_png(path: &str, theme: TileTheme, builder: &MapBuilder, map: &Map) -> std::io::Result<()> {
+    let (width, height) = (map.width as u32 * PNG_SCALE, map.height as u32 * PNG_SCALE);
+    let mut pixels = vec![0u8; (width * height * 3) as usize];
+    for (idx, tile) in map.tiles.iter().enumerate() {
+        let point = map.point_at(idx);
+        let color = if builder.player == Some(point) {
+            RGBA::named(GREEN)
+        } else {
+            theme.color(*tile).fg
+        };
+        let rgb = [color.r, color.g, color.b].map(|c| (c * color.a * 255.0) as u8);
+        for py in 0..PNG_SCALE {
+            for px in 0..PNG_SCALE {
+                let x = point.x as u32 * PNG_SCALE + px;
+                let y = point.y as u32 * PNG_SCALE + py;
+                let offset = ((y * width + x) * 3) as usize;
+                pixels[offset..offset + 3].copy_from_slice(&rgb);
+            }
+        }
+    }
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(std::io::Error::other)
+}
+
+#[derive(Serialize)]
+struct JsonPoint {
+    x: i32,
+    y: i32,
+}
+
+impl From<Point> for JsonPoint {
+    fn from(Point { x, y }: Point) -> Self {
+        Self { x, y }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonRoom {
+    x1: i32,
+    y1: i32,
+    x2: i32,
+    y2: i32,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JsonTunnel {
+    Horizontal { x1: i32, x2: i32, y: i32 },
+    Vertical { y1: i32, y2: i32, x: i32 },
+    Line { start: JsonPoint, end: JsonPoint },
+}
+
+#[derive(Serialize)]
+struct JsonStats {
+    floor_ratio: f32,
+    rooms: usize,
+    longest_path: i32,
+    dead_ends: usize,
+    connected: bool,
+}
+
+#[derive(Serialize)]
+struct JsonMap {
+    seed: u64,
+    width: i32,
+    height: i32,
+    player: Option<JsonPoint>,
+    stairs_up: Option<JsonPoint>,
+    stairs_down: Option<JsonPoint>,
+    rooms: Vec<JsonRoom>,
+    tunnels: Vec<JsonTunnel>,
+    tiles: Vec<String>,
+    stats: JsonStats,
+}
+
+fn json(seed: u64, theme: TileTheme, builder: &MapBuilder, map: &Map) -> JsonMap {
+    let stats = MapStats::measure(builder, map);
+    let tunnels = builder
+        .tunnels
+        .iter()
+        .map(|tunnel| match *tunnel {
+            Tunnel::Horizontal { x1, x2, y } => JsonTunnel::Horizontal { x1, x2, y },
+            Tunnel::Vertical { y1, y2, x } => JsonTunnel::Vertical { y1, y2, x },
+            Tunnel::Line { start, end } => JsonTunnel::Line {
+                start: start.into(),
+                end: end.into(),
+            },
+        })
+        .collect();
+
+    JsonMap {
+        seed,
+        width: map.width,
+        height: map.height,
+        player: builder.player.map(Into::into),
+        stairs_up: builder.stairs_up.map(Into::into),
+        stairs_down: builder.stairs_down.map(Into::into),
+        rooms: builder
+            .rooms
+            .iter()
+            .map(|r| JsonRoom {
+                x1: r.x1,
+                y1: r.y1,
+                x2: r.x2,
+                y2: r.y2,
+            })
+            .collect(),
+        tunnels,
+        tiles: ascii(theme, builder, map)
+            .lines()
+            .map(String::from)
+            .collect(),
+        stats: JsonStats {
+            floor_ratio: stats.floor_ratio,
+            rooms: stats.rooms,
+            longest_path: stats.longest_path,
+            dead_ends: stats.dead_ends,
+            connected: stats.connected,
+        },
+    }
+}
+
+fn export(options: &Options) -> std::io::Result<()> {
+    let (builder, map) = generate(&options.recipe, options.seed);
+    let theme = options.recipe.theme_for(Depth::SURFACE);
+    if options.ascii {
+        std::fs::write(format!("{}.txt", options.out), ascii(theme, &builder, &map))?;
+    }
+    if options.png {
+        write_png(&format!("{}.png", options.out), theme, &builder, &map)?;
+    }
+    if options.json {
+        let file = BufWriter::new(File::create(format!("{}.json", options.out))?);
+        serde_json::to_writer_pretty(file, &json(options.seed, theme, &builder, &map))?;
+    }
+    println!("Wrote seed {} to {}.*", options.seed, options.out);
+    Ok(())
+}
+
+fn batch(options: &Options, count: u64) {
+    let stats: Vec<MapStats> = (options.seed..options.seed + count)
+        .map(|seed| {
+            let (builder, map) = generate(&options.recipe, seed);
+            let stats = MapStats::measure(&builder, &map);
+            println!(
+                "seed {:>6}: floor {:>5.1}% rooms {:>3} longest path {:>4} dead ends {:>3}{}",
+                seed,
+                stats.floor_ratio * 100.0,
+                stats.rooms,
+                stats.longest_path,
+                stats.dead_ends,
+                if stats.connected { "" } else { " DISCONNECTED" },
+            );
+            stats
+        })
+        .collect();
+
+    let n = stats.len().max(1) as f32;
+    let average = |f: fn(&MapStats) -> f32| stats.iter().map(f).sum::<f32>() / n;
+    println!("--- {} maps ---", stats.len());
+    println!(
+        "floor ratio  avg {:.1}%",
+        average(|s| s.floor_ratio) * 100.0
+    );
+    println!("rooms        avg {:.1}", average(|s| s.rooms as f32));
+    println!("longest path avg {:.1}", average(|s| s.longest_path as f32));
+    println!("dead ends    avg {:.1}", average(|s| s.dead_ends as f32));
+    println!(
+        "disconnected {}",
+        stats.iter().filter(|s| !s.connected).count()
+    );
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("mapgen: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    match options.batch {
+        Some(count) => batch(&options, count),
+        None => {
+            if let Err(error) = export(&options) {
+                eprintln!("mapgen: {}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Plays headless skirmishes between computer players and reports who won.
+//!
+//! ```text
+//! skirmish [--players N] [--difficulty easy,normal,hard] [--games N] [--turns N]
+//!          [--seed N] [--recipe PATH]
+//! ```
+//!
+//! Difficulties are handed out to the factions in turn, so `--players 2
+//! --difficulty easy,hard` pits an easy commander against a hard one. Each game
+//! after the first uses the next seed.
+
+use std::collections::BTreeMap;
+
+use ferros_rts::prelude::*;
+
+struct Options {
+    settings: SkirmishSettings,
+    difficulties: Vec<Difficulty>,
+    games: u64,
+    turns: u32,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut settings = SkirmishSettings::default();
+    let mut seed = None;
+    let mut difficulties = vec![Difficulty::default()];
+    let mut games = 1;
+    let mut turns = 2000;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} expects a value", arg));
+        match arg.as_str() {
+            "--players" => {
+                settings.factions = value()?.parse().map_err(|_| "--players expects a number")?
+            }
+            "--difficulty" => {
+                difficulties = value()?
+                    .split(',')
+                    .map(|name| {
+                        Difficulty::parse(name).ok_or(format!("unknown difficulty {}", name))
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            "--games" => games = value()?.parse().map_err(|_| "--games expects a number")?,
+            "--turns" => turns = value()?.parse().map_err(|_| "--turns expects a number")?,
+            "--seed" => seed = Some(value()?.parse().map_err(|_| "--seed expects a number")?),
+            "--recipe" => {
+                settings.recipe = GeneratorRecipe::load(value()?).map_err(|e| e.to_string())?
+            }
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+    if settings.factions < 2 {
+        return Err("--players must be at least 2".to_string());
+    }
+
+    settings.seed = seed.or(settings.recipe.seed).unwrap_or(0);
+    Ok(Options {
+        settings,
+        difficulties,
+        games,
+        turns,
+    })
+}
+
+/// Plays one game to the end or the turn limit, returning the winner and the
+/// turn it stopped on
+fn play(
+    settings: &SkirmishSettings,
+    difficulties: &[Difficulty],
+    turns: u32,
+) -> (Option<Faction>, u32) {
+    let mut skirmish = Skirmish::new(settings);
+    let mut commanders: Vec<Commander> = skirmish
+        .factions()
+        .into_iter()
+        .zip(difficulties.iter().cycle())
+        .map(|(faction, difficulty)| Commander::new(faction, *difficulty))
+        .collect();
+    while skirmish.turn() < turns && skirmish.winner().is_none() {
+        let orders = commanders
+            .iter_mut()
+            .flat_map(|commander| commander.orders(&skirmish))
+            .collect();
+        skirmish.advance(orders);
+    }
+    (skirmish.winner(), skirmish.turn())
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("skirmish: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let difficulty =
+        |faction: Faction| options.difficulties[faction.0 as usize % options.difficulties.len()];
+    let mut wins: BTreeMap<Option<Faction>, u64> = BTreeMap::new();
+    for game in 0..options.games {
+        let settings = SkirmishSettings {
+            seed: options.settings.seed + game,
+            ..options.settings.clone()
+        };
+        let (winner, turns) = play(&settings, &options.difficulties, options.turns);
+        let result = match winner {
+            Some(winner) => format!("faction {} ({:?}) won", winner.0, difficulty(winner)),
+            None => "no winner".to_string(),
+        };
+        println!("seed {}: {} after {} turns", settings.seed, result, turns);
+        *wins.entry(winner).or_default() += 1;
+    }
+
+    if options.games > 1 {
+        for (winner, count) in &wins {
+            match winner {
+                Some(winner) => println!(
+                    "faction {} ({:?}): {} wins",
+                    winner.0,
+                    difficulty(*winner),
+                    count
+                ),
+                None => println!("unfinished: {}", count),
+            }
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// How well a computer player plays. Harder commanders decide more often,
+/// keep more workers and attack with smaller armies; none of them cheat.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    /// Turns between decisions
+    fn think_every(&self) -> u32 {
+        match self {
+            Difficulty::Easy => 8,
+            Difficulty::Normal => 4,
+            Difficulty::Hard => 1,
+        }
+    }
+
+    /// Workers to keep gathering
+    fn workers(&self) -> usize {
+        match self {
+            Difficulty::Easy => 2,
+            Difficulty::Normal => 4,
+            Difficulty::Hard => 5,
+        }
+    }
+
+    /// Soldiers to gather before attacking
+    fn attack_at(&self) -> usize {
+        match self {
+            Difficulty::Easy => 8,
+            Difficulty::Normal => 6,
+            Difficulty::Hard => 5,
+        }
+    }
+}
+
+/// Enemies seen this close to the base, or within `ENGAGE_RADIUS` of a
+/// worker, draw every soldier back to defend it
+const DEFEND_RADIUS: f32 = 10.0;
+/// Attacking soldiers turn on enemies this close instead of marching on
+const ENGAGE_RADIUS: f32 = 6.0;
+/// Most steps from the base to where soldiers wait for the rest of the army
+const RALLY_DISTANCE: usize = 4;
+
+fn distance(a: Point, b: Point) -> f32 {
+    DistanceAlg::Pythagoras.distance2d(a, b)
+}
+
+fn nearest(units: &[UnitInfo], to: Point) -> Option<&UnitInfo> {
+    units.iter().min_by(|a, b| {
+        distance(a.pos, to)
+            .total_cmp(&distance(b.pos, to))
+            .then(a.id.cmp(&b.id))
+    })
+}
+
+/// Open tiles a few steps from the base but not next to it, farthest from
+/// the resource node nearest the base first so the army stays out of the
+/// workers' way
+fn rally_spots(skirmish: &Skirmish, home: Point) -> Vec<Point> {
+    let node = skirmish
+        .resource_nodes()
+        .into_iter()
+        .map(|(pos, _)| pos)
+        .min_by(|a, b| distance(*a, home).total_cmp(&distance(*b, home)));
+    let reach = RALLY_DISTANCE as i32;
+    let mut spots: Vec<Point> = (-reach..=reach)
+        .flat_map(|y| (-reach..=reach).map(move |x| home + Point::new(x, y)))
+        .filter(|spot| distance(*spot, home) >= 2.0 && skirmish.is_open(*spot))
+        .filter(|spot| Some(*spot) != node)
+        .filter(|spot| (1..=RALLY_DISTANCE).contains(&skirmish.route(home, *spot).len()))
+        .collect();
+    let from_node = |spot: &Point| node.map_or(0.0, |node| distance(*spot, node));
+    spots.sort_by(|a, b| {
+        from_node(b)
+            .total_cmp(&from_node(a))
+            .then((a.y, a.x).cmp(&(b.y, b.x)))
+    });
+    spots
+}
+
+/// A computer player for one skirmish faction. It knows the map but only
+/// what its own units can see of the other factions, and plays only by giving
+/// orders, so it can stand in for any player: it keeps workers gathering,
+/// trains workers and then soldiers, sends a scout to find an enemy base and
+/// attacks it once its army is big enough, falling back when the army is
+/// worn down and defending its base from anything that comes near.
+pub struct Commander {
+    faction: Faction,
+    difficulty: Difficulty,
+    scout: Option<UnitId>,
+    /// How many rooms scouts have reached
+    scouted: usize,
+    /// The room the scout was last sent to
+    heading: Option<Point>,
+    /// The room the army is following the scout to, and the soldiers sent
+    marching: Option<Point>,
+    marched: Vec<UnitId>,
+    enemy_base: Option<(UnitId, Point)>,
+    attacking: bool,
+    /// Where soldiers wait for the rest of the army, one to a tile
+    rally: Vec<Point>,
+    /// The rally tile each soldier on its way there was sent to
+    posts: BTreeMap<UnitId, Point>,
+}
+
+impl Commander {
+    pub fn new(faction: Faction, difficulty: Difficulty) -> Self {
+        Self {
+            faction,
+            difficulty,
+            scout: None,
+            scouted: 0,
+            heading: None,
+            marching: None,
+            marched: Vec::new(),
+            enemy_base: None,
+            attacking: false,
+            rally: Vec::new(),
+            posts: BTreeMap::new(),
+        }
+    }
+
+    pub fn faction(&self) -> Faction {
+        self.faction
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// The orders to give before the skirmish's next turn
+    pub fn orders(&mut self, skirmish: &Skirmish) -> Vec<Order> {
+        if !skirmish
+            .turn()
+            .is_multiple_of(self.difficulty.think_every())
+        {
+            return Vec::new();
+        }
+        let units = skirmish.units(self.faction);
+        let seen = skirmish.visible_to(self.faction);
+        self.remember(&units, &seen);
+
+        let mut orders = Vec::new();
+        let base = units.iter().find(|unit| unit.kind == UnitKind::Base);
+        if let Some(base) = base {
+            self.gather(skirmish, &units, base.pos, &mut orders);
+            self.train(skirmish, &units, base.id, &mut orders);
+        }
+        let home = base.map(|base| base.pos);
+        if let (true, Some(home)) = (self.rally.is_empty(), home) {
+            self.rally = rally_spots(skirmish, home);
+        }
+        self.scout_ahead(skirmish, &units, home, &mut orders);
+        self.fight(&units, &seen, home, &mut orders);
+        orders
+    }
+
+    fn order(&self, units: Vec<UnitId>, command: Command) -> Order {
+        Order::new(self.faction, units, command)
+    }
+
+    /// Notes an enemy base once it is seen, and forgets it once a unit stands
+    /// where it was without seeing it
+    fn remember(&mut self, units: &[UnitInfo], seen: &[UnitInfo]) {
+        if let Some(base) = seen.iter().find(|unit| unit.kind == UnitKind::Base) {
+            self.enemy_base = Some((base.id, base.pos));
+        }
+        if let Some((id, pos)) = self.enemy_base {
+            let gone = !seen.iter().any(|unit| unit.id == id)
+                && units.iter().any(|unit| distance(unit.pos, pos) < 2.0);
+            if gone {
+                self.enemy_base = None;
+                self.attacking = false;
+            }
+        }
+    }
+
+    /// Sends idle workers to the resource node nearest the base
+    fn gather(
+        &self,
+        skirmish: &Skirmish,
+        units: &[UnitInfo],
+        base: Point,
+        orders: &mut Vec<Order>,
+    ) {
+        let idle: Vec<UnitId> = units
+            .iter()
+            .filter(|unit| unit.kind == UnitKind::Worker && unit.idle)
+            .map(|unit| unit.id)
+            .collect();
+        let node = skirmish
+            .resource_nodes()
+            .into_iter()
+            .map(|(pos, _)| pos)
+            .min_by(|a, b| distance(*a, base).total_cmp(&distance(*b, base)));
+        if let (false, Some(node)) = (idle.is_empty(), node) {
+            orders.push(self.order(
+                idle,
+                Command::Gather {
+                    x: node.x,
+                    y: node.y,
+                },
+            ));
+        }
+    }
+
+    /// Keeps up to two units queued, workers until there are enough of them
+    /// and soldiers after that
+    fn train(
+        &self,
+        skirmish: &Skirmish,
+        units: &[UnitInfo],
+        base: UnitId,
+        orders: &mut Vec<Order>,
+    ) {
+        let queued = skirmish.production(self.faction);
+        if queued.len() >= 2 {
+            return;
+        }
+        let workers = units
+            .iter()
+            .map(|unit| unit.kind)
+            .chain(queued.iter().copied())
+            .filter(|kind| *kind == UnitKind::Worker)
+            .count();
+        let kind = if workers < self.difficulty.workers() {
+            UnitKind::Worker
+        } else {
+            UnitKind::Soldier
+        };
+        if kind
+            .cost()
+            .is_some_and(|cost| skirmish.stockpile(self.faction) >= cost)
+        {
+            orders.push(self.order(vec![base], Command::Train(kind)));
+        }
+    }
+
+    /// Until an enemy base is found, keeps one soldier walking from room to
+    /// room, farthest from home first. A scout lost on the way is replaced
+    /// and the new one sent to the same room.
+    fn scout_ahead(
+        &mut self,
+        skirmish: &Skirmish,
+        units: &[UnitInfo],
+        home: Option<Point>,
+        orders: &mut Vec<Order>,
+    ) {
+        if self.enemy_base.is_some() {
+            self.scout = None;
+            return;
+        }
+        let scout = self
+            .scout
+            .and_then(|id| units.iter().find(|unit| unit.id == id))
+            .or_else(|| units.iter().find(|unit| unit.kind == UnitKind::Soldier));
+        let scout = match scout {
+            Some(scout) => scout,
+            None => return,
+        };
+        if self.scout == Some(scout.id) {
+            if !scout.idle {
+                return;
+            }
+            self.scouted += 1;
+        }
+        self.scout = Some(scout.id);
+
+        let home = home.unwrap_or(scout.pos);
+        let mut rooms: Vec<Point> = skirmish
+            .rooms()
+            .iter()
+            .copied()
+            .filter(|room| distance(*room, home) > DEFEND_RADIUS)
+            .collect();
+        rooms.sort_by(|a, b| distance(*b, home).total_cmp(&distance(*a, home)));
+        if let Some(room) = rooms.get(self.scouted % rooms.len().max(1)) {
+            self.heading = Some(*room);
+            orders.push(self.order(
+                vec![scout.id],
+                Command::MoveTo {
+                    x: room.x,
+                    y: room.y,
+                    formation: Formation::default(),
+                },
+            ));
+        }
+    }
+
+    /// Defends the base and workers first, then attacks once the army is big
+    /// enough, following the scout until an enemy base is known, or gathers at
+    /// the rally point while it grows
+    fn fight(
+        &mut self,
+        units: &[UnitInfo],
+        seen: &[UnitInfo],
+        home: Option<Point>,
+        orders: &mut Vec<Order>,
+    ) {
+        // Soldiers, scout included, fight back against enemies right next to
+        // them rather than trying to walk past
+        let mut fighting = Vec::new();
+        for soldier in units.iter().filter(|unit| unit.kind == UnitKind::Soldier) {
+            if let Some(enemy) =
+                nearest(seen, soldier.pos).filter(|enemy| distance(enemy.pos, soldier.pos) < 1.5)
+            {
+                orders.push(self.order(vec![soldier.id], Command::Attack(enemy.id)));
+                fighting.push(soldier.id);
+            }
+        }
+        let army: Vec<&UnitInfo> = units
+            .iter()
+            .filter(|unit| unit.kind == UnitKind::Soldier && Some(unit.id) != self.scout)
+            .collect();
+        let free: Vec<&UnitInfo> = army
+            .iter()
+            .copied()
+            .filter(|soldier| !fighting.contains(&soldier.id))
+            .collect();
+
+        let workers: Vec<Point> = units
+            .iter()
+            .filter(|unit| unit.kind == UnitKind::Worker)
+            .map(|unit| unit.pos)
+            .collect();
+        let threats: Vec<UnitInfo> = seen
+            .iter()
+            .filter(|enemy| {
+                home.is_some_and(|home| distance(enemy.pos, home) <= DEFEND_RADIUS)
+                    || workers
+                        .iter()
+                        .any(|worker| distance(enemy.pos, *worker) <= ENGAGE_RADIUS)
+            })
+            .copied()
+            .collect();
+        if !threats.is_empty() {
+            for soldier in &free {
+                if let Some(threat) = nearest(&threats, soldier.pos) {
+                    orders.push(self.order(vec![soldier.id], Command::Attack(threat.id)));
+                }
+            }
+            return;
+        }
+
+        let attack_at = self.difficulty.attack_at();
+        if self.attacking && army.len() < attack_at.div_ceil(2) {
+            self.attacking = false;
+        }
+        if !self.attacking && army.len() >= attack_at {
+            self.attacking = true;
+        }
+        if !self.attacking {
+            self.wait(units, &free, orders);
+            return;
+        }
+
+        self.posts.clear();
+        if self.heading != self.marching {
+            self.marching = self.heading;
+            self.marched.clear();
+        }
+        for soldier in &free {
+            let enemy = nearest(seen, soldier.pos)
+                .filter(|enemy| distance(enemy.pos, soldier.pos) <= ENGAGE_RADIUS);
+            let command = match (enemy, self.enemy_base, self.marching) {
+                (Some(enemy), _, _) => {
+                    self.marched.retain(|id| *id != soldier.id);
+                    Command::Attack(enemy.id)
+                }
+                (None, Some((base, _)), _) => Command::Attack(base),
+                (None, None, Some(room)) if !self.marched.contains(&soldier.id) => {
+                    self.marched.push(soldier.id);
+                    Command::MoveTo {
+                        x: room.x,
+                        y: room.y,
+                        formation: Formation::default(),
+                    }
+                }
+                _ => continue,
+            };
+            orders.push(self.order(vec![soldier.id], command));
+        }
+    }
+
+    /// Sends idle soldiers that aren't standing on a rally tile to the first
+    /// tile nobody stands on or has been sent to
+    fn wait(&mut self, units: &[UnitInfo], army: &[&UnitInfo], orders: &mut Vec<Order>) {
+        self.posts.retain(|id, post| {
+            army.iter()
+                .any(|soldier| soldier.id == *id && (!soldier.idle || soldier.pos == *post))
+        });
+        let mut spots = self.rally.iter().copied().filter(|spot| {
+            !units.iter().any(|unit| unit.pos == *spot)
+                && !self.posts.values().any(|post| post == spot)
+        });
+        let mut sent = Vec::new();
+        for soldier in army {
+            if !soldier.idle || self.rally.contains(&soldier.pos) {
+                continue;
+            }
+            match spots.next() {
+                Some(spot) => sent.push((soldier.id, spot)),
+                None => break,
+            }
+        }
+        for (id, spot) in sent {
+            self.posts.insert(id, spot);
+            orders.push(self.order(
+                vec![id],
+                Command::MoveTo {
+                    x: spot.x,
+                    y: spot.y,
+                    formation: Formation::default(),
+                },
+            ));
+        }
+    }
+}
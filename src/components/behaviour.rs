@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// One reusable piece of monster behaviour. On its turn a monster asks its
+/// nodes in order and acts on the first one that has something to do, so a
+/// behaviour reads like the selector at the top of a behaviour tree.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BehaviourNode {
+    /// Run from the nearest visible enemy once health is at or below this
+    /// percentage of its maximum
+    Flee { below_percent: i32 },
+    /// Back away from a visible enemy closer than `min` tiles, and close in on
+    /// one further than `max`
+    KeepRange { min: i32, max: i32 },
+    /// Fire at a visible enemy in shooting range
+    Shoot,
+    /// Walk to the nearest visible enemy and attack it
+    Chase,
+    /// Head for where an enemy was last seen or heard, then search around it
+    Investigate,
+    /// Shout for the allies within `radius` that can't see the enemy yet, which
+    /// sets them hunting
+    CallAllies { radius: i32 },
+    /// Walk back to `post` when more than `radius` tiles away from it
+    GuardRoom { post: Point, radius: i32 },
+    /// Walk to a visible item within `radius` and pick it up
+    PickUpItems { radius: i32 },
+    /// Step in a random direction, attacking an enemy standing there
+    Wander,
+}
+
+/// The nodes a monster decides with, in order of priority
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Behaviour(pub Vec<BehaviourNode>);
+
+impl Behaviour {
+    /// Attacks anything it sees and otherwise wanders about
+    pub fn brute() -> Self {
+        Self(vec![
+            BehaviourNode::Chase,
+            BehaviourNode::Investigate,
+            BehaviourNode::Wander,
+        ])
+    }
+
+    /// Hunts in packs, raising the alarm and grabbing loot, and runs when hurt
+    pub fn pack_hunter() -> Self {
+        Self(vec![
+            BehaviourNode::Flee { below_percent: 34 },
+            BehaviourNode::CallAllies { radius: 8 },
+            BehaviourNode::Chase,
+            BehaviourNode::Investigate,
+            BehaviourNode::PickUpItems { radius: 5 },
+            BehaviourNode::Wander,
+        ])
+    }
+
+    /// Shoots from a distance and keeps out of reach
+    pub fn skirmisher(range: i32) -> Self {
+        Self(vec![
+            BehaviourNode::Flee { below_percent: 50 },
+            BehaviourNode::KeepRange { min: 3, max: range },
+            BehaviourNode::Shoot,
+            BehaviourNode::Investigate,
+            BehaviourNode::Wander,
+        ])
+    }
+
+    /// Stays near `post`, only chasing enemies that come close
+    pub fn guard(post: Point, radius: i32) -> Self {
+        Self(vec![
+            BehaviourNode::GuardRoom { post, radius },
+            BehaviourNode::Chase,
+            BehaviourNode::Investigate,
+            BehaviourNode::Wander,
+        ])
+    }
+}
+
+/// What a monster decided to do on its last turn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Decision {
+    #[default]
+    Idle,
+    Wander,
+    Chase,
+    Hunt,
+    Investigate,
+    Search,
+    Flee,
+    BackOff,
+    Approach,
+    Shoot,
+    CallAllies,
+    ReturnToPost,
+    FetchItem,
+    PickUp,
+}
+
+impl Decision {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Decision::Idle => "idle",
+            Decision::Wander => "wander",
+            Decision::Chase => "chase",
+            Decision::Hunt => "hunt",
+            Decision::Investigate => "investigate",
+            Decision::Search => "search",
+            Decision::Flee => "flee",
+            Decision::BackOff => "back off",
+            Decision::Approach => "approach",
+            Decision::Shoot => "shoot",
+            Decision::CallAllies => "call allies",
+            Decision::ReturnToPost => "return to post",
+            Decision::FetchItem => "fetch item",
+            Decision::PickUp => "pick up",
+        }
+    }
+
+    pub fn color(&self) -> (u8, u8, u8) {
+        match self {
+            Decision::Idle | Decision::Wander => GREY,
+            Decision::Chase | Decision::Approach | Decision::Shoot => RED,
+            Decision::Hunt | Decision::CallAllies => ORANGE,
+            Decision::Investigate | Decision::Search => YELLOW,
+            Decision::Flee | Decision::BackOff => CYAN,
+            Decision::ReturnToPost => YELLOW,
+            Decision::FetchItem | Decision::PickUp => MAGENTA,
+        }
+    }
+}
+
+/// How alert a monster is to its enemies
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlertState {
+    /// Unaware of any enemy
+    #[default]
+    Idle,
+    /// Heard something and is going to look
+    Suspicious,
+    /// Saw an enemy, or was told where one is, and is going after it
+    Hunting,
+    /// Lost track of its enemy and is looking around where it was last known
+    Searching,
+}
+
+impl AlertState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertState::Idle => "idle",
+            AlertState::Suspicious => "suspicious",
+            AlertState::Hunting => "hunting",
+            AlertState::Searching => "searching",
+        }
+    }
+
+    /// The colour blended into the monster's glyph, if any
+    pub fn tint(&self) -> Option<(u8, u8, u8)> {
+        match self {
+            AlertState::Idle => None,
+            AlertState::Suspicious => Some(YELLOW),
+            AlertState::Hunting => Some(MAGENTA),
+            AlertState::Searching => Some(CYAN),
+        }
+    }
+}
+
+/// What a monster has noticed of its enemies
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Awareness {
+    pub state: AlertState,
+    /// Where an enemy was last seen or heard
+    pub last_known: Option<Point>,
+    /// Turns left before the monster gives up on `last_known` and calms down
+    pub memory: i32,
+    /// The enemy it can see this turn
+    pub in_sight: Option<Entity>,
+}
+
+impl Awareness {
+    /// Turns a monster keeps after an enemy it can no longer see or hear
+    pub const MEMORY_TURNS: i32 = 12;
+    /// Turns a monster spends searching before it gives up
+    pub const SEARCH_TURNS: i32 = 6;
+
+    pub fn alert(state: AlertState, last_known: Point) -> Self {
+        Self {
+            state,
+            last_known: Some(last_known),
+            memory: Self::MEMORY_TURNS,
+            in_sight: None,
+        }
+    }
+
+    /// Counts down a turn spent at `pos` without seeing or hearing an enemy. A
+    /// hunting or suspicious monster starts searching once it gets to the last
+    /// known position or runs out of memory, and a searching one goes back to
+    /// idle when it gives up.
+    pub fn forget(&mut self, pos: Point) {
+        if self.state == AlertState::Idle {
+            return;
+        }
+        self.memory -= 1;
+        match self.state {
+            AlertState::Hunting | AlertState::Suspicious
+                if self.last_known == Some(pos) || self.memory <= 0 =>
+            {
+                self.state = AlertState::Searching;
+                self.memory = Self::SEARCH_TURNS;
+            }
+            AlertState::Searching if self.memory <= 0 => *self = Self::default(),
+            _ => {}
+        }
+    }
+}
@@ -1,19 +1,134 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+mod behaviour;
+
+pub use behaviour::{AlertState, Awareness, Behaviour, BehaviourNode, Decision};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Render {
     pub color: ColorPair,
     pub glyph: FontCharType,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Player;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Enemy;
 
+/// The side a unit fights for in a skirmish, numbered by player from 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Faction(pub u8);
+
+/// Names a skirmish unit the same way on every machine, unlike its `Entity`,
+/// so orders sent over the network can say which units they are for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct UnitId(pub u32);
+
+/// What a unit was ordered to do. It works towards it a step each turn until
+/// it is done or the unit is given another order.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub struct MovingRandomly;
+pub enum Objective {
+    MoveTo(Point),
+    Attack(UnitId),
+    /// Carry resources from the node at this point back to base until it runs dry
+    Gather(Point),
+    /// Keep to its place in the formation moving as this entity
+    InFormation(Entity),
+}
+
+/// Units sent to the same tile together. The front of the formation walks one
+/// shared path a step at a time, waiting for whoever falls behind, and each
+/// member heads for its own place around it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormationMove {
+    pub formation: Formation,
+    pub path: Vec<Point>,
+    /// How far along `path` the front of the formation is
+    pub progress: usize,
+    /// Turns the front has waited for members to catch up
+    pub waited: u32,
+    /// Each member, in the order of their places in the formation
+    pub members: Vec<FormationSlot>,
+}
+
+impl FormationMove {
+    pub fn front(&self) -> Point {
+        self.path[self.progress]
+    }
+
+    /// Which way the path runs around the front, so the formation turns as
+    /// the path does
+    pub fn facing(&self) -> Point {
+        let last = self.path.len() - 1;
+        self.path[(self.progress + 2).min(last)] - self.path[self.progress.saturating_sub(2)]
+    }
+}
+
+/// A unit in a formation and the tile it is making for this turn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FormationSlot {
+    pub entity: Entity,
+    pub target: Point,
+}
+
+/// What a skirmish unit is, which decides what it can be ordered to do
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum UnitKind {
+    Base,
+    Worker,
+    Soldier,
+}
+
+impl UnitKind {
+    /// What a base charges to train one, for the kinds it can train
+    pub fn cost(&self) -> Option<u32> {
+        match self {
+            UnitKind::Base => None,
+            UnitKind::Worker => Some(50),
+            UnitKind::Soldier => Some(80),
+        }
+    }
+
+    /// Turns a base spends training one
+    pub fn training_turns(&self) -> u32 {
+        match self {
+            UnitKind::Base => 0,
+            UnitKind::Worker => 6,
+            UnitKind::Soldier => 10,
+        }
+    }
+}
+
+/// Never moves, and can't be pushed aside by allies
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Immobile;
+
+/// A patch of resources for workers to gather
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResourceNode {
+    pub remaining: u32,
+}
+
+/// Resources a worker is carrying back to base
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cargo(pub u32);
+
+/// The units a base was told to train, in order, and how many turns it has
+/// spent on the first
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProductionQueue {
+    pub queue: VecDeque<UnitKind>,
+    pub progress: u32,
+}
+
+impl ProductionQueue {
+    pub const MAX_LENGTH: usize = 5;
+}
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct WantsToMove {
@@ -27,7 +142,328 @@ pub struct WantsToAttack {
     pub victim: Entity,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectKind {
+    /// Loses `potency` health every turn
+    Poison,
+    /// Cannot move or attack
+    Stun,
+    /// Regains `potency` health every turn
+    Regeneration,
+    /// Moves two tiles at a time
+    Haste,
+    /// Deals `potency` less damage
+    Weakness,
+}
+
+impl EffectKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EffectKind::Poison => "Poisoned",
+            EffectKind::Stun => "Stunned",
+            EffectKind::Regeneration => "Regenerating",
+            EffectKind::Haste => "Hasted",
+            EffectKind::Weakness => "Weakened",
+        }
+    }
+
+    /// Whether applying the effect again adds to its potency instead of only
+    /// refreshing how long it lasts
+    pub fn stacks(&self) -> bool {
+        matches!(self, EffectKind::Poison)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: EffectKind,
+    pub turns: i32,
+    pub potency: i32,
+}
+
+impl StatusEffect {
+    pub const fn new(kind: EffectKind, turns: i32, potency: i32) -> Self {
+        Self {
+            kind,
+            turns,
+            potency,
+        }
+    }
+}
+
+/// The timed effects currently on a unit, at most one of each kind
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+impl StatusEffects {
+    /// The most potency a stacking effect can build up to
+    pub const MAX_STACKED_POTENCY: i32 = 5;
+
+    pub fn apply(&mut self, effect: StatusEffect) {
+        match self.0.iter_mut().find(|active| active.kind == effect.kind) {
+            Some(active) => {
+                active.turns = active.turns.max(effect.turns);
+                active.potency = if effect.kind.stacks() {
+                    (active.potency + effect.potency).min(Self::MAX_STACKED_POTENCY)
+                } else {
+                    active.potency.max(effect.potency)
+                };
+            }
+            None => self.0.push(effect),
+        }
+    }
+
+    pub fn get(&self, kind: EffectKind) -> Option<&StatusEffect> {
+        self.0.iter().find(|active| active.kind == kind)
+    }
+
+    pub fn has(&self, kind: EffectKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    pub fn potency(&self, kind: EffectKind) -> i32 {
+        self.get(kind).map_or(0, |active| active.potency)
+    }
+
+    /// Counts every effect down by a turn and drops the ones that ran out
+    pub fn tick(&mut self) {
+        self.0.iter_mut().for_each(|active| active.turns -= 1);
+        self.0.retain(|active| active.turns > 0);
+    }
+
+    /// e.g. `Poisoned(3) Hasted(5)`
+    pub fn describe(&self) -> String {
+        self.0
+            .iter()
+            .map(|active| format!("{}({})", active.kind.name(), active.turns))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Puts an effect on the user when the item is used
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GrantsEffect(pub StatusEffect);
+
+/// Puts an effect on whoever this unit, weapon or item hits
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InflictsEffect(pub StatusEffect);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WantsToShoot {
+    pub shooter: Entity,
+    pub target: Point,
+}
+
+/// Flies along `path`, hitting the first unit other than its shooter that the
+/// to-hit roll succeeds against
+#[derive(Clone, Debug, PartialEq)]
+pub struct Projectile {
+    pub shooter: Entity,
+    pub path: Vec<Point>,
+    pub next: usize,
+    pub attack: i32,
+    pub damage: Dice,
+    pub effect: Option<StatusEffect>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WantsToCloseDoor {
+    pub entity: Entity,
+    pub door: Point,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Item;
+
+/// Destroyed once it has been used
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Consumable;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProvidesHealing {
+    pub amount: i32,
+}
+
+/// Hurts every enemy within `radius` of the user
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DamagesNearby {
+    pub damage: i32,
+    pub radius: i32,
+}
+
+/// A dice expression such as `1d4+1`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Dice {
+    pub count: i32,
+    pub sides: i32,
+    pub bonus: i32,
+}
+
+impl Dice {
+    pub const fn new(count: i32, sides: i32, bonus: i32) -> Self {
+        Self {
+            count,
+            sides,
+            bonus,
+        }
+    }
+
+    pub fn roll(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        rng.roll_dice(self.count, self.sides) + self.bonus
+    }
+}
+
+impl std::fmt::Display for Dice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        if self.bonus != 0 {
+            write!(f, "{:+}", self.bonus)?;
+        }
+        Ok(())
+    }
+}
+
+/// A unit's unarmed, unarmoured fighting ability
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CombatStats {
+    pub attack: i32,
+    pub defense: i32,
+    pub damage: Dice,
+}
+
+impl Default for CombatStats {
+    fn default() -> Self {
+        Self {
+            attack: 0,
+            defense: 0,
+            damage: Dice::new(1, 1, 0),
+        }
+    }
+}
+
+/// Adds `attack` to the wielder's to-hit roll and replaces their unarmed damage
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Weapon {
+    pub attack: i32,
+    pub damage: Dice,
+}
+
+/// How far a bow, or a unit that shoots without one, can fire
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ranged {
+    pub range: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Armour {
+    pub defense: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Melee,
+    Ranged,
+    Head,
+    Body,
+    Shield,
+    Ring,
+}
+
+/// The slot an item occupies once equipped
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+/// Marks a carried item as worn or wielded by `owner`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WantsToEquip {
+    pub entity: Entity,
+    pub item: Entity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WantsToUnequip {
+    pub entity: Entity,
+    pub item: Entity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WantsToUseItem {
+    pub user: Entity,
+    pub item: Entity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WantsToPickUp {
+    pub entity: Entity,
+    pub item: Entity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WantsToDrop {
+    pub entity: Entity,
+    pub item: Entity,
+}
+
+/// Lets whoever carries it open locked doors
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Key;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Carried {
+    pub owner: Entity,
+}
+
+/// Briefly marks the items players take with them when they leave a level
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TakenAlong;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StairDirection {
+    Up,
+    Down,
+}
+
+impl StairDirection {
+    /// The tile a unit must stand on to take the stairs
+    pub fn tile(&self) -> TileType {
+        match self {
+            StairDirection::Up => TileType::StairsUp,
+            StairDirection::Down => TileType::StairsDown,
+        }
+    }
+
+    /// The tile a unit arrives on at the other end of the stairs
+    pub fn arrival_tile(&self) -> TileType {
+        match self {
+            StairDirection::Up => TileType::StairsDown,
+            StairDirection::Down => TileType::StairsUp,
+        }
+    }
+
+    pub fn depth_change(&self) -> i32 {
+        match self {
+            StairDirection::Up => -1,
+            StairDirection::Down => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WantsToTakeStairs {
+    pub entity: Entity,
+    pub direction: StairDirection,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Health {
     pub current: i32,
     pub max: i32,
@@ -39,7 +475,103 @@ impl Health {
     }
 }
 
-#[derive(Clone, PartialEq)]
+/// Experience a unit has gathered towards its next level. `unspent` counts level
+/// ups that are still waiting for the player to pick a reward.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Experience {
+    pub level: i32,
+    pub current: i32,
+    pub unspent: i32,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Self {
+            level: 1,
+            current: 0,
+            unspent: 0,
+        }
+    }
+}
+
+impl Experience {
+    /// Experience needed to go from the current level to the next one
+    pub fn needed(&self) -> i32 {
+        20 * self.level
+    }
+
+    pub fn gain(&mut self, amount: i32) {
+        self.current += amount;
+        while self.current >= self.needed() {
+            self.current -= self.needed();
+            self.level += 1;
+            self.unspent += 1;
+        }
+    }
+}
+
+/// Heals a unit by one health every `turns_per_hp` turns, which is at least
+/// one. Resting counts for several turns at once.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Regeneration {
+    pub turns_per_hp: i32,
+    pub elapsed: i32,
+}
+
+impl Regeneration {
+    pub fn new(turns_per_hp: i32) -> Self {
+        Self {
+            turns_per_hp: turns_per_hp.max(1),
+            elapsed: 0,
+        }
+    }
+}
+
+/// The unit spent its turn waiting or resting
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rested;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HungerState {
+    Fed,
+    Hungry,
+    Starving,
+}
+
+/// Drops by one every turn. Hungry units stop regenerating and starving ones
+/// slowly lose health.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hunger {
+    pub satiety: i32,
+    pub max: i32,
+}
+
+impl Hunger {
+    pub fn new(max: i32) -> Self {
+        Self { satiety: max, max }
+    }
+
+    pub fn state(&self) -> HungerState {
+        if self.satiety <= 0 {
+            HungerState::Starving
+        } else if self.satiety < self.max / 5 {
+            HungerState::Hungry
+        } else {
+            HungerState::Fed
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProvidesNutrition {
+    pub amount: i32,
+}
+
+/// Experience awarded to whoever kills this unit
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExperienceValue(pub i32);
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Name(pub String);
 
 impl From<&'static str> for Name {
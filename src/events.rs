@@ -0,0 +1,64 @@
+use crate::prelude::*;
+
+/// Something that happened during a turn. Events are facts about what already
+/// happened, unlike the `WantsTo*` messages that ask for something to happen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    UnitMoved {
+        entity: Entity,
+        from: Point,
+        to: Point,
+    },
+    AttackResolved {
+        attacker: Entity,
+        victim: Entity,
+        hit: bool,
+        damage: i32,
+    },
+    /// `name` and `at` are kept because the entity is gone by the time anyone
+    /// reads this
+    UnitDied {
+        entity: Entity,
+        killer: Option<Entity>,
+        name: Option<String>,
+        at: Option<Point>,
+        was_player: bool,
+    },
+    ItemPickedUp {
+        entity: Entity,
+        item: Entity,
+    },
+    LevelEntered {
+        depth: i32,
+    },
+    TurnEnded,
+}
+
+/// The events published since the channel was last cleared.
+///
+/// Gameplay systems publish as they go. Every game schedule opens with its
+/// consumers, which read everything published during the previous schedule
+/// (including its final command flush), followed by `clear_events`. Consumers
+/// only read, so any number of them see the same events.
+#[derive(Debug, Clone, Default)]
+pub struct EventChannel {
+    events: Vec<GameEvent>,
+}
+
+impl EventChannel {
+    pub fn publish(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GameEvent> {
+        self.events.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
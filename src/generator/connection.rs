@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ConnectionStrategy {
+    /// Connects each room to the next one when sorted by the x of their centre
+    SortedChain,
+    /// Connects rooms with a minimum spanning tree over their centres, then adds
+    /// `loop_percent` extra edges (relative to the tree size) to create loops
+    SpanningTree { loop_percent: i32 },
+}
+
+impl ConnectionStrategy {
+    /// The number of connections this strategy will make between `num_of_rooms`
+    pub fn connections(&self, num_of_rooms: i32) -> i32 {
+        let tree = (num_of_rooms - 1).max(0);
+        match self {
+            ConnectionStrategy::SortedChain => tree,
+            ConnectionStrategy::SpanningTree { loop_percent } => {
+                tree + (tree * loop_percent.max(&0)) / 100
+            }
+        }
+    }
+
+    /// Returns pairs of indices into `rooms` that should be joined by a corridor
+    pub fn plan(&self, rooms: &[Rect], rng: &mut RandomNumberGenerator) -> Vec<(usize, usize)> {
+        if rooms.len() < 2 {
+            return Vec::new();
+        }
+        match self {
+            ConnectionStrategy::SortedChain => (1..rooms.len()).map(|i| (i - 1, i)).collect(),
+            ConnectionStrategy::SpanningTree { .. } => {
+                let mut edges = spanning_tree(rooms);
+                let extra = self.connections(rooms.len() as i32) as usize - edges.len();
+                add_loops(rooms, &mut edges, extra, rng);
+                edges
+            }
+        }
+    }
+}
+
+fn distance(rooms: &[Rect], a: usize, b: usize) -> i32 {
+    let a = rooms[a].center();
+    let b = rooms[b].center();
+    (a.x - b.x).pow(2) + (a.y - b.y).pow(2)
+}
+
+/// Prim's algorithm over the complete graph of room centres
+fn spanning_tree(rooms: &[Rect]) -> Vec<(usize, usize)> {
+    let mut in_tree = vec![false; rooms.len()];
+    let mut closest: Vec<(i32, usize)> = (0..rooms.len())
+        .map(|i| (distance(rooms, 0, i), 0))
+        .collect();
+    let mut edges = Vec::with_capacity(rooms.len() - 1);
+    in_tree[0] = true;
+
+    for _ in 1..rooms.len() {
+        let next = (0..rooms.len())
+            .filter(|i| !in_tree[*i])
+            .min_by_key(|i| closest[*i].0)
+            .expect("Spanning tree ran out of rooms");
+        in_tree[next] = true;
+        edges.push((closest[next].1, next));
+
+        for other in (0..rooms.len()).filter(|i| !in_tree[*i]) {
+            let dist = distance(rooms, next, other);
+            if dist < closest[other].0 {
+                closest[other] = (dist, next);
+            }
+        }
+    }
+    edges
+}
+
+/// Joins random rooms to their nearest neighbour they are not already directly joined to
+fn add_loops(
+    rooms: &[Rect],
+    edges: &mut Vec<(usize, usize)>,
+    extra: usize,
+    rng: &mut RandomNumberGenerator,
+) {
+    let is_joined = |edges: &[(usize, usize)], a: usize, b: usize| {
+        edges.iter().any(|e| *e == (a, b) || *e == (b, a))
+    };
+    for _ in 0..extra {
+        let from = rng.range(0, rooms.len());
+        let nearest = (0..rooms.len())
+            .filter(|to| *to != from && !is_joined(edges, from, *to))
+            .min_by_key(|to| distance(rooms, from, *to));
+        if let Some(to) = nearest {
+            edges.push((from, to));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CorridorShape {
+    /// A horizontal and vertical tunnel meeting at a corner
+    LShape,
+    /// A single Bresenham line between the two points
+    Straight,
+    /// Short random segments that drift towards the destination
+    Winding,
+}
+
+impl CorridorShape {
+    const MAX_WINDING_SEGMENTS: usize = 64;
+
+    pub fn tunnels(
+        &self,
+        from: Point,
+        to: Point,
+        bounds: Rect,
+        rng: &mut RandomNumberGenerator,
+    ) -> Vec<Tunnel> {
+        match self {
+            CorridorShape::LShape => l_shape(from, to, rng),
+            CorridorShape::Straight => vec![Tunnel::line(from, to)],
+            CorridorShape::Winding => winding(from, to, bounds, rng),
+        }
+    }
+}
+
+fn l_shape(from: Point, to: Point, rng: &mut RandomNumberGenerator) -> Vec<Tunnel> {
+    if rng.range(0, 2) == 1 {
+        vec![
+            Tunnel::horizontal(from.x, to.x, from.y),
+            Tunnel::vertical(from.y, to.y, to.x),
+        ]
+    } else {
+        vec![
+            Tunnel::vertical(from.y, to.y, from.x),
+            Tunnel::horizontal(from.x, to.x, to.y),
+        ]
+    }
+}
+
+fn winding(from: Point, to: Point, bounds: Rect, rng: &mut RandomNumberGenerator) -> Vec<Tunnel> {
+    let mut tunnels = Vec::new();
+    let mut current = from;
+
+    while current != to && tunnels.len() < CorridorShape::MAX_WINDING_SEGMENTS {
+        let remaining = to - current;
+        let horizontal = match (remaining.x, remaining.y) {
+            (0, _) => false,
+            (_, 0) => true,
+            _ => rng.range(0, 2) == 1,
+        };
+        let drift = rng.range(0, 4) == 0;
+        let length = rng.range(1, 5);
+
+        let next = match (horizontal, drift) {
+            (true, false) => Point::new(
+                current.x + remaining.x.signum() * length.min(remaining.x.abs()),
+                current.y,
+            ),
+            (false, false) => Point::new(
+                current.x,
+                current.y + remaining.y.signum() * length.min(remaining.y.abs()),
+            ),
+            (true, true) => Point::new(current.x, current.y + rng.range(-2, 3)),
+            (false, true) => Point::new(current.x + rng.range(-2, 3), current.y),
+        };
+        let next = Point::new(
+            next.x.max(bounds.x1).min(bounds.x2),
+            next.y.max(bounds.y1).min(bounds.y2),
+        );
+
+        if next.y == current.y {
+            tunnels.push(Tunnel::horizontal(current.x, next.x, current.y));
+        } else {
+            tunnels.push(Tunnel::vertical(current.y, next.y, current.x));
+        }
+        current = next;
+    }
+
+    if current != to {
+        tunnels.extend(l_shape(current, to, rng));
+    }
+    tunnels
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ConnectivityRepair {
+    /// Carve a tunnel from each unreachable region to the closest reachable floor
+    Tunnel,
+    /// Turn each unreachable region back into walls
+    WallOff,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConnectivityReport {
+    pub reachable_tiles: usize,
+    pub unreachable: Vec<Vec<Point>>,
+}
+
+impl ConnectivityReport {
+    pub fn is_connected(&self) -> bool {
+        self.unreachable.is_empty()
+    }
+
+    pub fn unreachable_tiles(&self) -> usize {
+        self.unreachable.iter().map(|region| region.len()).sum()
+    }
+}
@@ -0,0 +1,153 @@
+use crate::prelude::*;
+
+/// The state of world generation after a single step of the `GeneraotrRunner`
+#[derive(Clone)]
+pub struct GenerationSnapshot {
+    pub builder: MapBuilder,
+    pub runner: GeneraotrRunner,
+}
+
+/// Records every generation step so level designers can pause, scrub through
+/// and replay the build before starting the game.
+pub struct GenerationDebugger {
+    pub history: Vec<GenerationSnapshot>,
+    pub cursor: usize,
+    pub paused: bool,
+    pub confirmed: bool,
+}
+
+impl GenerationDebugger {
+    pub fn new(builder: &MapBuilder, runner: &GeneraotrRunner) -> Self {
+        Self {
+            history: vec![GenerationSnapshot {
+                builder: builder.clone(),
+                runner: runner.clone(),
+            }],
+            cursor: 0,
+            paused: true,
+            confirmed: false,
+        }
+    }
+
+    pub fn is_at_head(&self) -> bool {
+        self.cursor + 1 >= self.history.len()
+    }
+
+    pub fn current(&self) -> &GenerationSnapshot {
+        &self.history[self.cursor]
+    }
+
+    /// Moves forward one step, either through recorded history or by running the generator
+    pub fn step_forward(
+        &mut self,
+        builder: &mut MapBuilder,
+        runner: &mut GeneraotrRunner,
+        rng: &mut RandomNumberGenerator,
+    ) {
+        if !self.is_at_head() {
+            self.seek(self.cursor + 1, builder, runner);
+            return;
+        }
+        if builder.finished {
+            return;
+        }
+        runner.next(builder, rng);
+        self.history.push(GenerationSnapshot {
+            builder: builder.clone(),
+            runner: runner.clone(),
+        });
+        self.cursor = self.history.len() - 1;
+    }
+
+    pub fn step_back(&mut self, builder: &mut MapBuilder, runner: &mut GeneraotrRunner) {
+        if self.cursor > 0 {
+            self.seek(self.cursor - 1, builder, runner);
+        }
+    }
+
+    /// Jumps to the first recorded step of the command after the current one,
+    /// generating up to it when it has not been reached yet.
+    pub fn next_command(
+        &mut self,
+        builder: &mut MapBuilder,
+        runner: &mut GeneraotrRunner,
+        rng: &mut RandomNumberGenerator,
+    ) {
+        let target = self.current().runner.run_index + 1;
+        while self.current().runner.run_index < target && !self.current().builder.finished {
+            self.step_forward(builder, runner, rng);
+        }
+    }
+
+    /// Jumps to the start of the current command, or the previous one when already there
+    pub fn previous_command(&mut self, builder: &mut MapBuilder, runner: &mut GeneraotrRunner) {
+        let current = self.current().runner.run_index;
+        let start_of_current = self.command_start(current);
+        let target = match start_of_current {
+            Some(start) if start < self.cursor => start,
+            _ => current
+                .checked_sub(1)
+                .and_then(|previous| self.command_start(previous))
+                .unwrap_or(0),
+        };
+        self.seek(target, builder, runner);
+    }
+
+    fn command_start(&self, run_index: usize) -> Option<usize> {
+        self.history
+            .iter()
+            .position(|snapshot| snapshot.runner.run_index == run_index)
+    }
+
+    fn seek(&mut self, cursor: usize, builder: &mut MapBuilder, runner: &mut GeneraotrRunner) {
+        self.cursor = cursor.min(self.history.len() - 1);
+        let GenerationSnapshot {
+            builder: recorded_builder,
+            runner: recorded_runner,
+        } = self.current().clone();
+        *builder = recorded_builder;
+        *runner = recorded_runner;
+    }
+
+    pub fn handle_key(
+        &mut self,
+        key: VirtualKeyCode,
+        builder: &mut MapBuilder,
+        runner: &mut GeneraotrRunner,
+        rng: &mut RandomNumberGenerator,
+    ) {
+        match key {
+            VirtualKeyCode::Space => self.paused = !self.paused,
+            VirtualKeyCode::Right | VirtualKeyCode::L => {
+                self.paused = true;
+                self.step_forward(builder, runner, rng);
+            }
+            VirtualKeyCode::Left | VirtualKeyCode::H => {
+                self.paused = true;
+                self.step_back(builder, runner);
+            }
+            VirtualKeyCode::PageDown | VirtualKeyCode::RBracket => {
+                self.paused = true;
+                self.next_command(builder, runner, rng);
+            }
+            VirtualKeyCode::PageUp | VirtualKeyCode::LBracket => {
+                self.paused = true;
+                self.previous_command(builder, runner);
+            }
+            VirtualKeyCode::Home => {
+                self.paused = true;
+                self.seek(0, builder, runner);
+            }
+            VirtualKeyCode::End => {
+                self.paused = true;
+                let head = self.history.len() - 1;
+                self.seek(head, builder, runner);
+                while !builder.finished {
+                    self.step_forward(builder, runner, rng);
+                }
+            }
+            VirtualKeyCode::Return => self.confirmed = builder.finished,
+            _ => {}
+        }
+    }
+}
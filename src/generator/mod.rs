@@ -1,11 +1,22 @@
+mod connection;
+mod connectivity;
+mod debugger;
+mod recipe;
+mod speed;
 mod tunnel;
 
 use bracket_lib::prelude::*;
+pub use connection::*;
+pub use connectivity::*;
+pub use debugger::*;
+pub use recipe::*;
+use serde::{Deserialize, Serialize};
+pub use speed::*;
 pub use tunnel::*;
 
 use crate::prelude::*;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum GeneratorCommand {
     FillMap(TileType),
     GenerateRooms {
@@ -13,9 +24,22 @@ pub enum GeneratorCommand {
         max_room_size: i32,
     },
     PlacePlayerInRoom,
+    PlaceStairs,
+    ScatterTiles {
+        tile: TileType,
+        per_room: i32,
+    },
+    PlaceDoors {
+        lock_percent: i32,
+    },
+    PlaceItems {
+        per_room: i32,
+    },
     Tunnel {
-        num_of_tunnels: i32,
+        strategy: ConnectionStrategy,
+        corridor: CorridorShape,
     },
+    EnsureConnectivity(ConnectivityRepair),
 }
 
 impl GeneratorCommand {
@@ -24,7 +48,12 @@ impl GeneratorCommand {
             GeneratorCommand::FillMap(_) => "Filling Map",
             GeneratorCommand::GenerateRooms { .. } => "Generating Rooms",
             GeneratorCommand::PlacePlayerInRoom => "Placing Player",
+            GeneratorCommand::PlaceStairs => "Placing Stairs",
+            GeneratorCommand::ScatterTiles { .. } => "Scattering Features",
+            GeneratorCommand::PlaceDoors { .. } => "Placing Doors",
+            GeneratorCommand::PlaceItems { .. } => "Placing Items",
             GeneratorCommand::Tunnel { .. } => "Tunneling Between Rooms",
+            GeneratorCommand::EnsureConnectivity(_) => "Validating Connectivity",
         }
     }
 
@@ -40,25 +69,42 @@ impl GeneratorCommand {
                 max_room_size,
             } => builder.build_room(*num_of_rooms, *max_room_size, rng),
             GeneratorCommand::PlacePlayerInRoom => builder.place_player(rng),
-            GeneratorCommand::Tunnel { num_of_tunnels } => {
-                builder.build_tunnels(*num_of_tunnels, rng)
+            GeneratorCommand::PlaceStairs => builder.place_stairs(),
+            GeneratorCommand::ScatterTiles { tile, per_room } => {
+                builder.scatter_tiles(*tile, *per_room, rng)
+            }
+            GeneratorCommand::PlaceDoors { lock_percent } => {
+                builder.place_doors(*lock_percent, rng)
+            }
+            GeneratorCommand::PlaceItems { per_room } => builder.place_items(*per_room, rng),
+            GeneratorCommand::Tunnel { strategy, corridor } => {
+                builder.build_tunnels(*strategy, *corridor, rng)
             }
+            GeneratorCommand::EnsureConnectivity(repair) => builder.ensure_connectivity(*repair),
         }
     }
 
-    pub fn steps(&self) -> usize {
+    /// How many steps the command takes when `rooms` rooms have been generated
+    pub fn steps(&self, rooms: i32) -> usize {
         match self {
             GeneratorCommand::FillMap(_) => 1,
             GeneratorCommand::GenerateRooms { num_of_rooms, .. } => *num_of_rooms as usize,
             GeneratorCommand::PlacePlayerInRoom => 1,
-            GeneratorCommand::Tunnel { num_of_tunnels } => *num_of_tunnels as usize,
+            GeneratorCommand::PlaceStairs => 1,
+            GeneratorCommand::ScatterTiles { .. } => 1,
+            GeneratorCommand::PlaceDoors { .. } => 1,
+            GeneratorCommand::PlaceItems { .. } => 1,
+            GeneratorCommand::Tunnel { strategy, .. } => strategy.connections(rooms) as usize,
+            GeneratorCommand::EnsureConnectivity(_) => 1,
         }
     }
 }
 
+#[derive(Clone)]
 pub struct GeneraotrRunner {
     pub commands: Vec<GeneratorCommand>,
     pub run_index: usize,
+    pub command_progress: usize,
 }
 
 impl GeneraotrRunner {
@@ -66,6 +112,7 @@ impl GeneraotrRunner {
         Self {
             commands,
             run_index: 0,
+            command_progress: 0,
         }
     }
 
@@ -79,16 +126,27 @@ impl GeneraotrRunner {
     }
 
     pub fn total_steps(&self) -> i32 {
-        self.commands
+        (0..self.commands.len()).fold(0, |total, index| self.steps(index) as i32 + total)
+    }
+
+    /// Steps the command at `index` takes, given the rooms generated before it
+    fn steps(&self, index: usize) -> usize {
+        let rooms = self.commands[..index]
             .iter()
-            .fold(0, |total, cmd| cmd.steps() as i32 + total)
+            .rev()
+            .find_map(|cmd| match cmd {
+                GeneratorCommand::GenerateRooms { num_of_rooms, .. } => Some(*num_of_rooms),
+                _ => None,
+            })
+            .unwrap_or(0);
+        self.commands[index].steps(rooms)
     }
 
     fn get_subsystem_current_progress(&self, index: usize) -> i32 {
         if index != self.run_index {
-            return self.commands[index].steps() as i32;
+            return self.steps(index) as i32;
         }
-        0
+        self.command_progress as i32
     }
 
     pub fn next(&mut self, builder: &mut MapBuilder, rng: &mut RandomNumberGenerator) {
@@ -97,8 +155,21 @@ impl GeneraotrRunner {
             return;
         }
         let perform = self.commands[self.run_index].perform(builder, rng);
-        if let BuildCommandResult::Finished = perform {
-            self.run_index += 1;
+        match perform {
+            BuildCommandResult::Finished => {
+                self.run_index += 1;
+                self.command_progress = 0;
+            }
+            BuildCommandResult::Progress { total, current } => {
+                self.command_progress = current.min(total);
+            }
+        }
+    }
+
+    /// Runs every remaining command without waiting for a frame between steps
+    pub fn run_to_completion(&mut self, builder: &mut MapBuilder, rng: &mut RandomNumberGenerator) {
+        while !builder.finished {
+            self.next(builder, rng);
         }
     }
 
@@ -117,21 +188,3 @@ impl GeneraotrRunner {
         self.get_current_command().map(|cmd| cmd.generator_text())
     }
 }
-
-impl Default for GeneraotrRunner {
-    fn default() -> Self {
-        let num_of_rooms = 15;
-        let commands = vec![
-            GeneratorCommand::FillMap(TileType::Wall),
-            GeneratorCommand::GenerateRooms {
-                num_of_rooms,
-                max_room_size: 10,
-            },
-            GeneratorCommand::Tunnel {
-                num_of_tunnels: (num_of_rooms * 2) - 2,
-            },
-            GeneratorCommand::PlacePlayerInRoom,
-        ];
-        Self::new(commands)
-    }
-}
@@ -0,0 +1,248 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+const DEFAULT_RECIPE: &str = include_str!("../../recipes/default.ron");
+
+/// Where the main menu looks for recipes to offer
+pub const RECIPES_DIR: &str = "recipes";
+
+/// Everything needed to generate a level: the map size, the ordered generator
+/// commands and an optional seed for the world generation RNG.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratorRecipe {
+    pub width: i32,
+    pub height: i32,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub theme: Option<TileTheme>,
+    pub commands: Vec<GeneratorCommand>,
+}
+
+impl GeneratorRecipe {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RecipeError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|error| RecipeError::Io {
+            path: path.display().to_string(),
+            error,
+        })?;
+        Self::parse(&source).map_err(|error| error.in_file(path))
+    }
+
+    /// Every `.ron` recipe in `dir` sorted by file name, keeping the error of
+    /// any that fail to load so a menu can show why
+    pub fn list<P: AsRef<Path>>(dir: P) -> Vec<(String, Result<Self, RecipeError>)> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+            .collect();
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|path| {
+                let name = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                (name, Self::load(&path))
+            })
+            .collect()
+    }
+
+    pub fn parse(source: &str) -> Result<Self, RecipeError> {
+        let recipe: Self =
+            ron::de::from_str(source).map_err(|error| RecipeError::Parse { path: None, error })?;
+        recipe.validate()?;
+        Ok(recipe)
+    }
+
+    pub fn validate(&self) -> Result<(), RecipeError> {
+        if self.width < 10 || self.height < 10 {
+            return Err(RecipeError::InvalidSize {
+                width: self.width,
+                height: self.height,
+            });
+        }
+        if self.commands.is_empty() {
+            return Err(RecipeError::NoCommands);
+        }
+
+        let mut has_rooms = false;
+        let mut has_player = false;
+        for (index, command) in self.commands.iter().enumerate() {
+            let invalid = |reason: String| RecipeError::InvalidCommand {
+                index,
+                command: *command,
+                reason,
+            };
+            match command {
+                GeneratorCommand::FillMap(_) => {}
+                GeneratorCommand::GenerateRooms {
+                    num_of_rooms,
+                    max_room_size,
+                } => {
+                    if *num_of_rooms < 1 {
+                        return Err(invalid("num_of_rooms must be at least 1".into()));
+                    }
+                    if *max_room_size < 3 {
+                        return Err(invalid("max_room_size must be at least 3".into()));
+                    }
+                    if *max_room_size >= self.width - 1 || *max_room_size >= self.height - 1 {
+                        return Err(invalid(format!(
+                            "max_room_size {} does not fit in a {}x{} map",
+                            max_room_size, self.width, self.height
+                        )));
+                    }
+                    has_rooms = true;
+                }
+                GeneratorCommand::Tunnel { strategy, .. } => {
+                    if !has_rooms {
+                        return Err(invalid("must come after GenerateRooms".into()));
+                    }
+                    if let ConnectionStrategy::SpanningTree { loop_percent } = strategy {
+                        if !(0..=100).contains(loop_percent) {
+                            return Err(invalid("loop_percent must be between 0 and 100".into()));
+                        }
+                    }
+                }
+                GeneratorCommand::PlacePlayerInRoom => {
+                    if !has_rooms {
+                        return Err(invalid("must come after GenerateRooms".into()));
+                    }
+                    has_player = true;
+                }
+                GeneratorCommand::PlaceStairs => {
+                    if !has_player {
+                        return Err(invalid("must come after PlacePlayerInRoom".into()));
+                    }
+                }
+                GeneratorCommand::ScatterTiles { per_room, .. } => {
+                    if !has_rooms {
+                        return Err(invalid("must come after GenerateRooms".into()));
+                    }
+                    if *per_room < 0 {
+                        return Err(invalid("per_room cannot be negative".into()));
+                    }
+                }
+                GeneratorCommand::PlaceDoors { lock_percent } => {
+                    if !has_rooms {
+                        return Err(invalid("must come after GenerateRooms".into()));
+                    }
+                    if !(0..=100).contains(lock_percent) {
+                        return Err(invalid("lock_percent must be between 0 and 100".into()));
+                    }
+                }
+                GeneratorCommand::PlaceItems { per_room } => {
+                    if !has_player {
+                        return Err(invalid("must come after PlacePlayerInRoom".into()));
+                    }
+                    if *per_room < 0 {
+                        return Err(invalid("per_room cannot be negative".into()));
+                    }
+                }
+                GeneratorCommand::EnsureConnectivity(_) => {
+                    if !has_rooms {
+                        return Err(invalid("must come after GenerateRooms".into()));
+                    }
+                }
+            }
+        }
+
+        if !has_player {
+            return Err(RecipeError::MissingPlayer);
+        }
+        Ok(())
+    }
+
+    pub fn runner(&self) -> GeneraotrRunner {
+        GeneraotrRunner::new(self.commands.clone())
+    }
+
+    pub fn builder(&self) -> MapBuilder {
+        MapBuilder::new(self.width, self.height)
+    }
+
+    /// The theme to draw a level at `depth` with
+    pub fn theme_for(&self, depth: i32) -> TileTheme {
+        self.theme.unwrap_or_else(|| TileTheme::for_depth(depth))
+    }
+
+    pub fn rng(&self) -> WorldGenRng {
+        match self.seed {
+            Some(seed) => WorldGenRng::seeded(seed),
+            None => WorldGenRng::new(),
+        }
+    }
+}
+
+impl Default for GeneratorRecipe {
+    fn default() -> Self {
+        Self::parse(DEFAULT_RECIPE).expect("Default recipe is invalid")
+    }
+}
+
+#[derive(Debug)]
+pub enum RecipeError {
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Parse {
+        path: Option<String>,
+        error: ron::Error,
+    },
+    InvalidSize {
+        width: i32,
+        height: i32,
+    },
+    InvalidCommand {
+        index: usize,
+        command: GeneratorCommand,
+        reason: String,
+    },
+    NoCommands,
+    MissingPlayer,
+}
+
+impl RecipeError {
+    fn in_file(self, path: &Path) -> Self {
+        match self {
+            RecipeError::Parse { error, .. } => RecipeError::Parse {
+                path: Some(path.display().to_string()),
+                error,
+            },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecipeError::Io { path, error } => write!(f, "could not read {}: {}", path, error),
+            RecipeError::Parse {
+                path: Some(path),
+                error,
+            } => write!(f, "{}: {}", path, error),
+            RecipeError::Parse { path: None, error } => write!(f, "{}", error),
+            RecipeError::InvalidSize { width, height } => {
+                write!(f, "map size {}x{} is too small", width, height)
+            }
+            RecipeError::InvalidCommand {
+                index,
+                command,
+                reason,
+            } => write!(f, "command {} ({:?}): {}", index + 1, command, reason),
+            RecipeError::NoCommands => write!(f, "recipe has no commands"),
+            RecipeError::MissingPlayer => write!(f, "recipe never runs PlacePlayerInRoom"),
+        }
+    }
+}
+
+impl std::error::Error for RecipeError {}
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use crate::prelude::*;
+
+/// How much world generation work to do each rendered frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GenerationSpeed {
+    StepsPerFrame(usize),
+    TimeBudget(Duration),
+    /// Finish generation without showing any of the steps
+    Instant,
+}
+
+impl GenerationSpeed {
+    const MAX_STEPS_PER_FRAME: usize = 1024;
+    /// Longest time budget, so generating never takes a whole 60 FPS frame
+    const MAX_TIME_BUDGET: Duration = Duration::from_millis(15);
+
+    /// Parses `instant`, a step count like `4`, or a time budget like `5ms`.
+    /// Time budgets are capped at 15ms.
+    pub fn parse(value: &str) -> Option<Self> {
+        if value == "instant" {
+            return Some(GenerationSpeed::Instant);
+        }
+        if let Some(millis) = value.strip_suffix("ms") {
+            return millis
+                .parse()
+                .ok()
+                .filter(|millis| *millis > 0)
+                .map(|millis| Duration::from_millis(millis).min(Self::MAX_TIME_BUDGET))
+                .map(GenerationSpeed::TimeBudget);
+        }
+        value
+            .parse()
+            .ok()
+            .filter(|steps| *steps > 0)
+            .map(GenerationSpeed::StepsPerFrame)
+    }
+
+    pub fn faster(&mut self) {
+        *self = match *self {
+            GenerationSpeed::StepsPerFrame(steps) if steps >= Self::MAX_STEPS_PER_FRAME => {
+                GenerationSpeed::Instant
+            }
+            GenerationSpeed::StepsPerFrame(steps) => GenerationSpeed::StepsPerFrame(steps * 2),
+            GenerationSpeed::TimeBudget(budget) => {
+                GenerationSpeed::TimeBudget((budget * 2).min(Self::MAX_TIME_BUDGET))
+            }
+            GenerationSpeed::Instant => GenerationSpeed::Instant,
+        }
+    }
+
+    pub fn slower(&mut self) {
+        *self = match *self {
+            GenerationSpeed::StepsPerFrame(steps) => {
+                GenerationSpeed::StepsPerFrame((steps / 2).max(1))
+            }
+            GenerationSpeed::TimeBudget(budget) => {
+                GenerationSpeed::TimeBudget((budget / 2).max(Duration::from_millis(1)))
+            }
+            GenerationSpeed::Instant => GenerationSpeed::StepsPerFrame(Self::MAX_STEPS_PER_FRAME),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: VirtualKeyCode) {
+        match key {
+            VirtualKeyCode::Equals | VirtualKeyCode::Plus | VirtualKeyCode::NumpadAdd => {
+                self.faster()
+            }
+            VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => self.slower(),
+            _ => {}
+        }
+    }
+
+    /// Calls `step` until this frame's budget is spent or it returns `false`
+    /// because there is nothing left to generate. Always takes at least one step.
+    pub fn run_frame<F: FnMut() -> bool>(&self, mut step: F) {
+        match *self {
+            GenerationSpeed::StepsPerFrame(steps) => {
+                for _ in 0..steps {
+                    if !step() {
+                        return;
+                    }
+                }
+            }
+            GenerationSpeed::TimeBudget(budget) => {
+                let start = Instant::now();
+                while step() && start.elapsed() < budget {}
+            }
+            GenerationSpeed::Instant => while step() {},
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            GenerationSpeed::StepsPerFrame(1) => "1 step per frame".to_string(),
+            GenerationSpeed::StepsPerFrame(steps) => format!("{} steps per frame", steps),
+            GenerationSpeed::TimeBudget(budget) => format!("{}ms per frame", budget.as_millis()),
+            GenerationSpeed::Instant => "instant".to_string(),
+        }
+    }
+}
+
+impl Default for GenerationSpeed {
+    fn default() -> Self {
+        GenerationSpeed::StepsPerFrame(1)
+    }
+}
@@ -4,6 +4,7 @@ use crate::prelude::*;
 pub enum Tunnel {
     Horizontal { x1: i32, x2: i32, y: i32 },
     Vertical { y1: i32, y2: i32, x: i32 },
+    Line { start: Point, end: Point },
 }
 
 impl Tunnel {
@@ -13,34 +14,54 @@ impl Tunnel {
     pub fn vertical(y1: i32, y2: i32, x: i32) -> Tunnel {
         Tunnel::Vertical { y1, y2, x }
     }
+    pub fn line(start: Point, end: Point) -> Tunnel {
+        Tunnel::Line { start, end }
+    }
+}
+
+pub enum TunnelIter {
+    Straight(PointLine),
+    Line(SteppedLine),
+}
+
+impl Iterator for TunnelIter {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            TunnelIter::Straight(line) => line.next(),
+            TunnelIter::Line(line) => line.next(),
+        }
+    }
 }
 
 impl IntoIterator for Tunnel {
     type Item = Point;
-    type IntoIter = PointLine;
+    type IntoIter = TunnelIter;
 
     fn into_iter(self) -> Self::IntoIter {
         match self {
             Tunnel::Horizontal { x1, x2, y } => {
                 let current = x1.min(x2);
                 let max = x1.max(x2);
-                PointLine {
+                TunnelIter::Straight(PointLine {
                     max,
                     current,
                     static_el: y,
                     static_first: false,
-                }
+                })
             }
             Tunnel::Vertical { y1, y2, x } => {
                 let current = y1.min(y2);
                 let high = y1.max(y2);
-                PointLine {
+                TunnelIter::Straight(PointLine {
                     max: high,
                     current,
                     static_el: x,
                     static_first: true,
-                }
+                })
             }
+            Tunnel::Line { start, end } => TunnelIter::Line(SteppedLine::new(start, end)),
         }
     }
 }
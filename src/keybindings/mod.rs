@@ -0,0 +1,400 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+mod keys;
+
+pub use keys::{is_modifier, key_from_name, key_name};
+
+const DEFAULT_KEYBINDINGS: &str = include_str!("../../config/keybindings.ron");
+
+/// Where rebound keys are saved, and loaded from when `--keys` is not given
+pub const KEYBINDINGS_FILE: &str = "keybindings.ron";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    pub const CARDINALS: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    pub fn delta(&self) -> Point {
+        match self {
+            Direction::North => Point::new(0, -1),
+            Direction::NorthEast => Point::new(1, -1),
+            Direction::East => Point::new(1, 0),
+            Direction::SouthEast => Point::new(1, 1),
+            Direction::South => Point::new(0, 1),
+            Direction::SouthWest => Point::new(-1, 1),
+            Direction::West => Point::new(-1, 0),
+            Direction::NorthWest => Point::new(-1, -1),
+        }
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        let delta = self.delta();
+        delta.x != 0 && delta.y != 0
+    }
+
+    /// The directions a unit may step in under `rules`
+    pub fn allowed(rules: &MovementRules) -> &'static [Direction] {
+        if rules.diagonals {
+            &Direction::ALL
+        } else {
+            &Direction::CARDINALS
+        }
+    }
+}
+
+/// Everything a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Move(Direction),
+    Wait,
+    Rest,
+    PickUp,
+    Target,
+    Confirm,
+    Cancel,
+    Inventory,
+    Drop,
+    CloseDoor,
+    Descend,
+    Ascend,
+    Pan(Direction),
+    Keybindings,
+    SaveGame,
+    LoadGame,
+    /// Pick out the skirmish units saved in a control group
+    SelectGroup(u8),
+    /// Save the picked out skirmish units as a control group
+    AssignGroup(u8),
+    /// Switch the formation the picked out units move in
+    CycleFormation,
+}
+
+impl Action {
+    pub fn describe(&self) -> String {
+        match self {
+            Action::Move(direction) => format!("Move {:?}", direction),
+            Action::Wait => "Wait a turn".to_string(),
+            Action::Rest => "Rest until healed".to_string(),
+            Action::PickUp => "Pick up".to_string(),
+            Action::Target => "Aim / fire".to_string(),
+            Action::Confirm => "Confirm".to_string(),
+            Action::Cancel => "Cancel".to_string(),
+            Action::Inventory => "Use item".to_string(),
+            Action::Drop => "Drop item".to_string(),
+            Action::CloseDoor => "Close door".to_string(),
+            Action::Descend => "Go down stairs".to_string(),
+            Action::Ascend => "Go up stairs".to_string(),
+            Action::Pan(direction) => format!("Pan camera {:?}", direction),
+            Action::Keybindings => "Keybindings".to_string(),
+            Action::SaveGame => "Save game".to_string(),
+            Action::LoadGame => "Load game".to_string(),
+            Action::SelectGroup(group) => format!("Select group {}", group),
+            Action::AssignGroup(group) => format!("Save group {}", group),
+            Action::CycleFormation => "Next formation".to_string(),
+        }
+    }
+}
+
+/// The keyboard modifiers held down this frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyModifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+}
+
+/// A key together with the modifiers that must be held with it, written as
+/// e.g. `Ctrl+Shift+K` in the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyChord {
+    pub key: VirtualKeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(key: VirtualKeyCode, modifiers: KeyModifiers) -> Self {
+        Self { key, modifiers }
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.control {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{}", key_name(self.key))
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = String;
+
+    fn try_from(chord: String) -> Result<Self, Self::Error> {
+        let mut modifiers = KeyModifiers::default();
+        let mut parts: Vec<&str> = chord.split('+').collect();
+        let key = parts.pop().unwrap_or_default();
+        for part in parts {
+            match part {
+                "Ctrl" => modifiers.control = true,
+                "Alt" => modifiers.alt = true,
+                "Shift" => modifiers.shift = true,
+                other => return Err(format!("unknown modifier '{}' in '{}'", other, chord)),
+            }
+        }
+        let key = key_from_name(key).ok_or(format!("unknown key '{}'", chord))?;
+        Ok(Self { key, modifiers })
+    }
+}
+
+impl From<KeyChord> for String {
+    fn from(chord: KeyChord) -> Self {
+        chord.to_string()
+    }
+}
+
+/// Whether units may step diagonally
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MovementRules {
+    pub diagonals: bool,
+}
+
+/// Maps key chords to actions. Each action keeps its keys in order of
+/// preference so the first one can be shown as the action's key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keybindings {
+    #[serde(default = "diagonals_by_default")]
+    pub diagonal_movement: bool,
+    pub bindings: Vec<(Action, Vec<KeyChord>)>,
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+fn diagonals_by_default() -> bool {
+    true
+}
+
+impl Keybindings {
+    /// Most keys an action can have before rebinding pushes out the oldest
+    pub const MAX_KEYS_PER_ACTION: usize = 3;
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, KeybindingsError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|error| KeybindingsError::Io {
+            path: path.display().to_string(),
+            error,
+        })?;
+        let mut keybindings = Self::parse(&source).map_err(|error| match error {
+            KeybindingsError::Parse { error, .. } => KeybindingsError::Parse {
+                path: Some(path.display().to_string()),
+                error,
+            },
+            KeybindingsError::Duplicate {
+                chord,
+                first,
+                second,
+                ..
+            } => KeybindingsError::Duplicate {
+                path: Some(path.display().to_string()),
+                chord,
+                first,
+                second,
+            },
+            other => other,
+        })?;
+        keybindings.path = Some(path.to_path_buf());
+        Ok(keybindings)
+    }
+
+    /// Parses a config, adding any actions it leaves out with those of their
+    /// default keys it hasn't given to another action. A key bound to two
+    /// actions is an error.
+    pub fn parse(source: &str) -> Result<Self, KeybindingsError> {
+        let mut keybindings: Self = ron::de::from_str(source)
+            .map_err(|error| KeybindingsError::Parse { path: None, error })?;
+        keybindings.check_duplicates()?;
+        if let Ok(defaults) = ron::de::from_str::<Self>(DEFAULT_KEYBINDINGS) {
+            for (action, mut keys) in defaults.bindings {
+                if keybindings
+                    .bindings
+                    .iter()
+                    .any(|(bound, _)| *bound == action)
+                {
+                    continue;
+                }
+                keys.retain(|key| keybindings.action_for(*key).is_none());
+                keybindings.bindings.push((action, keys));
+            }
+        }
+        Ok(keybindings)
+    }
+
+    fn check_duplicates(&self) -> Result<(), KeybindingsError> {
+        for (index, (first, keys)) in self.bindings.iter().enumerate() {
+            for (second, other_keys) in &self.bindings[index + 1..] {
+                if let Some(chord) = keys.iter().find(|key| other_keys.contains(key)) {
+                    return Err(KeybindingsError::Duplicate {
+                        path: None,
+                        chord: *chord,
+                        first: *first,
+                        second: *second,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the bindings back to the file they came from. Bindings without a
+    /// file, such as those read from a replay, are never saved.
+    pub fn save(&self) -> Result<(), KeybindingsError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())
+            .map_err(|error| KeybindingsError::Parse { path: None, error })?;
+        std::fs::write(path, source).map_err(|error| KeybindingsError::Io {
+            path: path.display().to_string(),
+            error,
+        })
+    }
+
+    pub fn action_for(&self, chord: KeyChord) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&chord))
+            .map(|(action, _)| *action)
+    }
+
+    /// The action bound to `key` pressed with `modifiers`, if there was a key press
+    pub fn action(&self, key: Option<VirtualKeyCode>, modifiers: KeyModifiers) -> Option<Action> {
+        key.and_then(|key| self.action_for(KeyChord::new(key, modifiers)))
+    }
+
+    pub fn keys_for(&self, action: Action) -> &[KeyChord] {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map_or(&[], |(_, keys)| keys.as_slice())
+    }
+
+    /// Makes `chord` the first key for `action`, taking it away from any other action
+    pub fn bind(&mut self, action: Action, chord: KeyChord) {
+        for (_, keys) in self.bindings.iter_mut() {
+            keys.retain(|key| *key != chord);
+        }
+        if let Some((_, keys)) = self.bindings.iter_mut().find(|(bound, _)| *bound == action) {
+            keys.insert(0, chord);
+            keys.truncate(Self::MAX_KEYS_PER_ACTION);
+        }
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        if let Some((_, keys)) = self.bindings.iter_mut().find(|(bound, _)| *bound == action) {
+            keys.clear();
+        }
+    }
+
+    pub fn movement_rules(&self) -> MovementRules {
+        MovementRules {
+            diagonals: self.diagonal_movement,
+        }
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self::parse(DEFAULT_KEYBINDINGS).expect("Default keybindings are invalid")
+    }
+}
+
+#[derive(Debug)]
+pub enum KeybindingsError {
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Parse {
+        path: Option<String>,
+        error: ron::Error,
+    },
+    /// `chord` is bound to both actions
+    Duplicate {
+        path: Option<String>,
+        chord: KeyChord,
+        first: Action,
+        second: Action,
+    },
+}
+
+impl fmt::Display for KeybindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeybindingsError::Io { path, error } => {
+                write!(f, "could not access {}: {}", path, error)
+            }
+            KeybindingsError::Parse {
+                path: Some(path),
+                error,
+            } => write!(f, "{}: {}", path, error),
+            KeybindingsError::Parse { path: None, error } => write!(f, "{}", error),
+            KeybindingsError::Duplicate {
+                path,
+                chord,
+                first,
+                second,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path)?;
+                }
+                write!(
+                    f,
+                    "{} is bound to both '{}' and '{}'",
+                    chord,
+                    first.describe(),
+                    second.describe()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeybindingsError {}
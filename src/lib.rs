@@ -0,0 +1,43 @@
+pub mod commander;
+pub mod components;
+pub mod events;
+pub mod generator;
+pub mod keybindings;
+pub mod lockstep;
+pub mod maps;
+pub mod orders;
+pub mod replay;
+pub mod resources;
+pub mod save;
+pub mod skirmish;
+pub mod spawner;
+pub mod systems;
+pub mod tools;
+pub mod turn_state;
+
+pub mod prelude {
+    pub const SCREEN_WIDTH: i32 = 80;
+    pub const SCREEN_HEIGHT: i32 = 50;
+    pub const DIMENSION_WIDTH: i32 = SCREEN_WIDTH / 2;
+    pub const DIMENSION_HEIGHT: i32 = SCREEN_HEIGHT / 2;
+    pub const UI_LAYER: usize = 10_000;
+    pub use crate::commander::*;
+    pub use crate::components::*;
+    pub use crate::events::*;
+    pub use crate::generator::*;
+    pub use crate::keybindings::*;
+    pub use crate::lockstep::*;
+    pub use crate::maps::*;
+    pub use crate::orders::*;
+    pub use crate::replay::*;
+    pub use crate::resources::*;
+    pub use crate::save::*;
+    pub use crate::skirmish::*;
+    pub use crate::spawner::*;
+    pub use crate::tools::*;
+    pub use crate::turn_state::*;
+    pub use bracket_lib::prelude::*;
+    pub use legion::systems::CommandBuffer;
+    pub use legion::world::SubWorld;
+    pub use legion::*;
+}
@@ -0,0 +1,74 @@
+use std::net::TcpListener;
+use std::thread;
+
+use crate::prelude::*;
+
+/// How one player's side of a headless match ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchOutcome {
+    pub faction: Faction,
+    pub turns: u32,
+    pub winner: Option<Faction>,
+    pub checksum: u64,
+}
+
+/// Plays `session` with a computer commander until someone wins or `turns`
+/// have been played. `desync` knocks a hit point off a unit before that turn,
+/// so this player's world no longer matches anyone else's.
+pub fn play(
+    mut session: Lockstep,
+    settings: &SkirmishSettings,
+    turns: u32,
+    difficulty: Difficulty,
+    desync: Option<u32>,
+) -> Result<MatchOutcome, LockstepError> {
+    let mut skirmish = Skirmish::new(settings);
+    let mut commander = Commander::new(session.faction(), difficulty);
+    while skirmish.turn() < turns && skirmish.winner().is_none() {
+        if desync == Some(skirmish.turn()) {
+            if let Some(health) = <&mut Health>::query().iter_mut(&mut skirmish.ecs).next() {
+                health.current -= 1;
+            }
+        }
+        let orders = commander.orders(&skirmish);
+        let orders = session.exchange(orders, skirmish.checksum())?;
+        skirmish.advance(orders);
+    }
+    Ok(MatchOutcome {
+        faction: session.faction(),
+        turns: skirmish.turn(),
+        winner: skirmish.winner(),
+        checksum: skirmish.checksum(),
+    })
+}
+
+/// Hosts on an open localhost port and plays every other faction on a thread
+/// of its own, returning the host's outcome first. `desync` applies to the
+/// joining players only.
+pub fn play_local(
+    settings: &SkirmishSettings,
+    turns: u32,
+    difficulty: Difficulty,
+    desync: Option<u32>,
+) -> Result<Vec<MatchOutcome>, LockstepError> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let address = listener.local_addr()?;
+    let clients: Vec<_> = (1..settings.factions)
+        .map(|_| {
+            thread::spawn(move || {
+                let (session, settings) = Lockstep::join(address)?;
+                play(session, &settings, turns, difficulty, desync)
+            })
+        })
+        .collect();
+
+    let host = Lockstep::host(&listener, settings)
+        .and_then(|session| play(session, settings, turns, difficulty, None));
+    let mut outcomes = vec![host];
+    outcomes.extend(
+        clients
+            .into_iter()
+            .map(|client| client.join().unwrap_or(Err(LockstepError::Disconnected))),
+    );
+    outcomes.into_iter().collect()
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::prelude::*;
+
+mod headless;
+mod protocol;
+
+pub use headless::{play, play_local, MatchOutcome};
+pub use protocol::{Connection, Message, READ_TIMEOUT};
+
+/// Who the other players are, seen from one player. Every player talks only
+/// to the host, which gathers each turn's orders and passes them on.
+enum Peers {
+    Host(Vec<(Faction, Connection)>),
+    Client(Connection),
+}
+
+/// A player's side of a deterministic lockstep match. Players trade the
+/// orders for each turn and only play it once they have everyone's, so all
+/// of them run the same turns with the same orders. Each player also sends
+/// the checksum of their world, which the host compares against its own.
+pub struct Lockstep {
+    faction: Faction,
+    peers: Peers,
+    turn: u32,
+}
+
+impl Lockstep {
+    /// Commands the first faction and waits on `listener` until a player has
+    /// joined for every other faction
+    pub fn host(
+        listener: &TcpListener,
+        settings: &SkirmishSettings,
+    ) -> Result<Self, LockstepError> {
+        let mut clients = Vec::new();
+        for faction in 1..settings.factions {
+            let (stream, _) = listener.accept()?;
+            let mut connection = Connection::new(stream)?;
+            connection.send(&Message::Welcome {
+                faction: Faction(faction),
+                settings: settings.clone(),
+            })?;
+            clients.push((Faction(faction), connection));
+        }
+        Ok(Self {
+            faction: Faction(0),
+            peers: Peers::Host(clients),
+            turn: 0,
+        })
+    }
+
+    /// Joins the match hosted at `address`, returning the settings to build it
+    /// from
+    pub fn join<A: ToSocketAddrs>(address: A) -> Result<(Self, SkirmishSettings), LockstepError> {
+        let mut connection = Connection::new(TcpStream::connect(address)?)?;
+        match connection.receive()? {
+            Message::Welcome { faction, settings } => Ok((
+                Self {
+                    faction,
+                    peers: Peers::Client(connection),
+                    turn: 0,
+                },
+                settings,
+            )),
+            other => Err(LockstepError::unexpected(&other)),
+        }
+    }
+
+    pub fn faction(&self) -> Faction {
+        self.faction
+    }
+
+    /// The turn the next exchange is for
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    /// Sends this player's orders for the next turn, with the checksum of the
+    /// world before it, and waits for every player's orders for that turn.
+    /// Orders a player gives to units that are not theirs are dropped.
+    pub fn exchange(
+        &mut self,
+        orders: Vec<Order>,
+        checksum: u64,
+    ) -> Result<Vec<Order>, LockstepError> {
+        let turn = self.turn;
+        let faction = self.faction;
+        let orders = match &mut self.peers {
+            Peers::Host(clients) => {
+                let orders = orders
+                    .into_iter()
+                    .filter(|order| order.faction == faction)
+                    .collect();
+                gather_turn(clients, turn, orders, checksum)?
+            }
+            Peers::Client(host) => {
+                host.send(&Message::Orders {
+                    turn,
+                    orders,
+                    checksum,
+                })?;
+                match host.receive()? {
+                    Message::Turn {
+                        turn: theirs,
+                        orders,
+                    } if theirs == turn => orders,
+                    Message::Desync { turn, faction } => {
+                        return Err(LockstepError::Desync { turn, faction })
+                    }
+                    other => return Err(LockstepError::unexpected(&other)),
+                }
+            }
+        };
+        self.turn += 1;
+        Ok(orders)
+    }
+}
+
+/// Collects every client's orders for `turn` after the host's own and sends
+/// them all out, or tells everyone the first client found out of step
+fn gather_turn(
+    clients: &mut [(Faction, Connection)],
+    turn: u32,
+    mut orders: Vec<Order>,
+    checksum: u64,
+) -> Result<Vec<Order>, LockstepError> {
+    let mut desynced = None;
+    for (client, connection) in clients.iter_mut() {
+        match connection.receive()? {
+            Message::Orders {
+                turn: theirs,
+                orders: given,
+                checksum: their_checksum,
+            } if theirs == turn => {
+                if their_checksum != checksum && desynced.is_none() {
+                    desynced = Some(*client);
+                }
+                orders.extend(given.into_iter().filter(|order| order.faction == *client));
+            }
+            other => return Err(LockstepError::unexpected(&other)),
+        }
+    }
+    let reply = match desynced {
+        Some(faction) => Message::Desync { turn, faction },
+        None => Message::Turn {
+            turn,
+            orders: orders.clone(),
+        },
+    };
+    for (_, connection) in clients.iter_mut() {
+        connection.send(&reply)?;
+    }
+    match desynced {
+        Some(faction) => Err(LockstepError::Desync { turn, faction }),
+        None => Ok(orders),
+    }
+}
+
+#[derive(Debug)]
+pub enum LockstepError {
+    Io(std::io::Error),
+    /// A message could not be read, or was not the one expected
+    Protocol(String),
+    Disconnected,
+    /// A player sent nothing for `READ_TIMEOUT`
+    TimedOut,
+    /// `faction`'s world stopped matching the host's before `turn`
+    Desync {
+        turn: u32,
+        faction: Faction,
+    },
+}
+
+impl LockstepError {
+    fn unexpected(message: &Message) -> Self {
+        LockstepError::Protocol(format!("unexpected message {:?}", message))
+    }
+}
+
+impl From<std::io::Error> for LockstepError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            // What a read past its timeout fails with, depending on the platform
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {
+                LockstepError::TimedOut
+            }
+            _ => LockstepError::Io(error),
+        }
+    }
+}
+
+impl fmt::Display for LockstepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockstepError::Io(error) => write!(f, "{}", error),
+            LockstepError::Protocol(reason) => write!(f, "protocol error: {}", reason),
+            LockstepError::Disconnected => write!(f, "a player disconnected"),
+            LockstepError::TimedOut => write!(
+                f,
+                "a player sent nothing for {} seconds",
+                READ_TIMEOUT.as_secs()
+            ),
+            LockstepError::Desync { turn, faction } => write!(
+                f,
+                "faction {}'s world no longer matches the host's before turn {}",
+                faction.0, turn
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LockstepError {}
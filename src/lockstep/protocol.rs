@@ -0,0 +1,65 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Everything players send each other, one JSON message per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Message {
+    /// The host's greeting, telling a player which faction they command
+    Welcome {
+        faction: Faction,
+        settings: SkirmishSettings,
+    },
+    /// A player's orders for `turn`, with the checksum of the world before it
+    Orders {
+        turn: u32,
+        orders: Vec<Order>,
+        checksum: u64,
+    },
+    /// Every player's orders for `turn`, which everyone now plays
+    Turn { turn: u32, orders: Vec<Order> },
+    /// `faction`'s world no longer matched the host's before `turn`
+    Desync { turn: u32, faction: Faction },
+}
+
+/// How long to wait on a player before giving up on them. Every player waits
+/// on the slowest one each turn, and on the last to join before the first.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One end of the link between two players
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Result<Self, LockstepError> {
+        // Messages are small and every turn waits on them
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), LockstepError> {
+        let mut line = serde_json::to_string(message)
+            .map_err(|error| LockstepError::Protocol(error.to_string()))?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn receive(&mut self) -> Result<Message, LockstepError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(LockstepError::Disconnected);
+        }
+        serde_json::from_str(&line).map_err(|error| LockstepError::Protocol(error.to_string()))
+    }
+}
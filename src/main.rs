@@ -1,71 +1,111 @@
-mod components;
-mod generator;
-mod maps;
-mod resources;
-mod spawner;
-mod systems;
-mod tools;
-mod turn_state;
-
-mod prelude {
-    pub const SCREEN_WIDTH: i32 = 80;
-    pub const SCREEN_HEIGHT: i32 = 50;
-    pub const DIMENSION_WIDTH: i32 = SCREEN_WIDTH / 2;
-    pub const DIMENSION_HEIGHT: i32 = SCREEN_HEIGHT / 2;
-    pub const UI_LAYER: usize = 10_000;
-    pub use crate::components::*;
-    pub use crate::generator::*;
-    pub use crate::maps::*;
-    pub use crate::resources::*;
-    pub use crate::spawner::*;
-    pub use crate::tools::*;
-    pub use crate::turn_state::*;
-    pub use bracket_lib::prelude::*;
-    pub use legion::systems::CommandBuffer;
-    pub use legion::world::SubWorld;
-    pub use legion::*;
-}
-use crate::prelude::*;
+use ferros_rts::prelude::*;
+use ferros_rts::systems;
+
+/// Every game schedule opens by handing the events published during the
+/// previous one to their consumers and then clearing the channel
+fn read_events(builder: &mut legion::systems::Builder) -> &mut legion::systems::Builder {
+    builder
+        .add_system(systems::message_log_system())
+        .add_system(systems::drop_loot_system())
+        .add_system(systems::statistics_system())
+        .add_system(systems::achievements_system())
+        .add_system(systems::replay_turns_system())
+        .add_system(systems::record_noises_system())
+        .add_system(systems::clear_events_system())
+}
 
 fn build_input_scheduler() -> Schedule {
-    Schedule::builder()
+    read_events(&mut Schedule::builder())
+        .add_system(systems::rebind_keys_system())
+        .add_system(systems::save_game_system())
+        .add_system(systems::level_up_system())
+        .add_system(systems::rest_system())
+        .add_system(systems::targeting_system())
+        .add_system(systems::inventory_system())
         .add_system(systems::player_input_system())
         .flush()
         .add_system(systems::render::map_system())
         .add_system(systems::render::characters_system())
+        .add_system(systems::render::decisions_system())
         .add_system(systems::render::hud_system())
+        .add_system(systems::render::message_log_system())
         .add_system(systems::render::tooltips_system())
+        .add_system(systems::render::inventory_system())
+        .add_system(systems::render::targeting_system())
+        .add_system(systems::render::level_up_system())
+        .add_system(systems::render::rebind_keys_system())
         .build()
 }
 
 fn build_player_scheduler() -> Schedule {
-    Schedule::builder()
+    read_events(&mut Schedule::builder())
+        .add_system(systems::use_items_system())
+        .add_system(systems::pick_up_system())
+        .add_system(systems::drop_items_system())
+        .add_system(systems::equip_system())
+        .add_system(systems::unequip_system())
         .add_system(systems::characters::combat_system())
+        .add_system(systems::shoot_system())
         .flush()
         .add_system(systems::characters::movement_system())
+        .add_system(systems::close_door_system())
+        .flush()
+        .add_system(systems::projectiles_system())
         .flush()
         .add_system(systems::render::map_system())
         .add_system(systems::render::characters_system())
+        .add_system(systems::render::decisions_system())
         .add_system(systems::render::hud_system())
+        .add_system(systems::render::message_log_system())
         .add_system(systems::end_turn_system())
+        .add_system(systems::take_stairs_system())
         .build()
 }
 
 fn build_monster_scheduler() -> Schedule {
-    Schedule::builder()
-        .add_system(systems::characters::random_move_system())
+    read_events(&mut Schedule::builder())
+        .add_system(systems::perception_system())
+        .add_system(systems::monster_ai_system())
         .flush()
+        .add_system(systems::pick_up_system())
         .add_system(systems::characters::combat_system())
+        .add_system(systems::shoot_system())
         .flush()
         .add_system(systems::characters::movement_system())
         .flush()
+        .add_system(systems::projectiles_system())
+        .flush()
+        .add_system(systems::tick_status_effects_system())
+        .add_system(systems::metabolism_system())
+        .flush()
         .add_system(systems::render::map_system())
         .add_system(systems::render::characters_system())
+        .add_system(systems::render::decisions_system())
         .add_system(systems::render::hud_system())
+        .add_system(systems::render::message_log_system())
         .add_system(systems::end_turn_system())
         .build()
 }
 
+fn build_game_over_scheduler() -> Schedule {
+    read_events(&mut Schedule::builder())
+        .add_system(systems::save_game_system())
+        .flush()
+        .add_system(systems::render::map_system())
+        .add_system(systems::render::characters_system())
+        .add_system(systems::render::hud_system())
+        .add_system(systems::render::message_log_system())
+        .add_system(systems::render::game_over_system())
+        .build()
+}
+
+fn build_menu_scheduler() -> Schedule {
+    Schedule::builder()
+        .add_system(systems::choose_recipe_system())
+        .add_system(systems::render::recipe_menu_system())
+        .build()
+}
+
 fn build_build_scheduler() -> Schedule {
     Schedule::builder()
         .add_system(systems::world_gen_system())
@@ -74,6 +114,7 @@ fn build_build_scheduler() -> Schedule {
         .flush()
         .add_system(systems::render::builder_system())
         .add_system(systems::render::progress_bar_system())
+        .add_system(systems::render::generation_debugger_system())
         .build()
 }
 
@@ -83,22 +124,57 @@ struct Game {
     input_systems: Schedule,
     player_systems: Schedule,
     monster_systems: Schedule,
+    game_over_systems: Schedule,
     build_systems: Schedule,
+    menu_systems: Schedule,
+    replay: ReplayMode,
+    debug_generation: bool,
+    /// How many times the input schedule has run, which replays count inputs by
+    input_step: u64,
+    turns: u32,
+    /// Whether the recording has been saved since the game ended
+    game_over_saved: bool,
+}
+
+/// Closing the window drops the game, the last chance to save a recording
+impl Drop for Game {
+    fn drop(&mut self) {
+        self.replay.save();
+    }
 }
 
 impl Game {
-    fn new() -> Self {
+    /// Sets up everything but the level, which `start` generates once the
+    /// recipe is known
+    fn new(
+        keybindings: Keybindings,
+        debug_generation: bool,
+        debug_ai: bool,
+        speed: GenerationSpeed,
+        replay: ReplayMode,
+    ) -> Self {
         let ecs = World::default();
         let mut resources = Resources::default();
-        let generator = GeneraotrRunner::default();
-        let builder = MapBuilder::new(SCREEN_WIDTH + 20, SCREEN_WIDTH + 20);
-
-        resources.insert(WorldGenRng::new());
-        resources.insert(builder);
-        resources.insert(generator);
-        resources.insert(Some(ProgressBar::new(SCREEN_HEIGHT - ProgressBar::HEIGHT)));
+        resources.insert(speed);
+        resources.insert(AiDebugOverlay(debug_ai));
         resources.insert(Camera::new(Point::zero()));
         resources.insert(TurnState::AwaitingInput);
+        resources.insert(InventoryMenu::Closed);
+        resources.insert(Targeting::Inactive);
+        resources.insert(Resting::Inactive);
+        resources.insert(RebindMenu::Closed);
+        resources.insert(keybindings.movement_rules());
+        resources.insert(keybindings);
+        resources.insert(KeyModifiers::default());
+        resources.insert(Depth(Depth::SURFACE));
+        resources.insert(Dungeon::default());
+        resources.insert(SpatialIndex::default());
+        resources.insert(EventChannel::default());
+        resources.insert(Noises::default());
+        resources.insert(MessageLog::default());
+        resources.insert(Statistics::default());
+        resources.insert(Achievements::default());
+        resources.insert(ReplayTurns::default());
 
         Self {
             ecs,
@@ -107,15 +183,119 @@ impl Game {
             input_systems: build_input_scheduler(),
             player_systems: build_player_scheduler(),
             monster_systems: build_monster_scheduler(),
+            game_over_systems: build_game_over_scheduler(),
+            menu_systems: build_menu_scheduler(),
+            replay,
+            debug_generation,
+            input_step: 0,
+            turns: 0,
+            game_over_saved: false,
         }
     }
 
-    fn tick_on_command(&mut self) {
-        if self.has_map() {
-            self.run_game();
-        } else {
+    /// Begins generating the first level from `recipe`
+    fn start(&mut self, recipe: GeneratorRecipe) {
+        let mut builder = recipe.builder();
+        let mut runner = recipe.runner();
+        let mut rng = recipe.rng();
+        let speed = *self
+            .resources
+            .get::<GenerationSpeed>()
+            .expect("Resources requires GenerationSpeed");
+
+        if speed == GenerationSpeed::Instant && !self.debug_generation {
+            runner.run_to_completion(&mut builder, &mut rng);
+        }
+        self.resources.insert(
+            Some(GenerationDebugger::new(&builder, &runner)).filter(|_| self.debug_generation),
+        );
+        self.resources.insert(rng);
+        self.resources
+            .insert(recipe.seed.map_or_else(GameRng::new, GameRng::seeded));
+        self.resources.insert(builder);
+        self.resources.insert(runner);
+        self.resources.insert(recipe);
+        self.resources
+            .insert(Some(ProgressBar::new(SCREEN_HEIGHT - ProgressBar::HEIGHT)));
+    }
+
+    fn tick_on_command(&mut self, live: StepInput) {
+        if self.resources.contains::<RecipeMenu>() {
+            self.insert_input(live);
+            self.menu_systems
+                .execute(&mut self.ecs, &mut self.resources);
+            let chosen = self
+                .resources
+                .get_mut::<RecipeMenu>()
+                .and_then(|mut menu| menu.chosen.take());
+            if let Some(recipe) = chosen {
+                self.resources.remove::<RecipeMenu>();
+                self.start(recipe);
+            }
+            return;
+        }
+        if !self.has_map() {
+            // World generation only reads keys to change its speed, so a replay
+            // can leave them out without building a different level
+            let input = Some(live).filter(|_| !self.replay.is_playing());
+            self.insert_input(input.unwrap_or_default());
             self.build_systems
-                .execute(&mut self.ecs, &mut self.resources)
+                .execute(&mut self.ecs, &mut self.resources);
+            return;
+        }
+
+        let awaiting_input = matches!(
+            self.resources.get::<TurnState>().as_deref(),
+            Some(TurnState::AwaitingInput | TurnState::GameOver)
+        );
+        let input = if awaiting_input {
+            let keybindings = self
+                .resources
+                .get::<Keybindings>()
+                .expect("Resources requires Keybindings")
+                .clone();
+            let input = self.replay.step(self.input_step, live, &keybindings);
+            self.input_step += 1;
+            input
+        } else {
+            StepInput::default()
+        };
+        self.insert_input(input);
+        self.run_game();
+        self.check_turn();
+        if !self.is_game_over() {
+            return;
+        }
+        if !std::mem::replace(&mut self.game_over_saved, true) {
+            self.replay.save();
+        }
+    }
+
+    fn insert_input(&mut self, input: StepInput) {
+        self.resources.insert(input.key);
+        self.resources.insert(input.modifiers);
+        self.resources.insert(MouseClick(input.click));
+    }
+
+    /// Hands each finished turn to the replay to checksum the world
+    fn check_turn(&mut self) {
+        let turns = self
+            .resources
+            .get::<ReplayTurns>()
+            .map_or(self.turns, |turns| turns.0);
+        if turns == self.turns {
+            return;
+        }
+        self.turns = turns;
+        if let Some(expected) = self.replay.turn_ended(turns, &self.ecs, &self.resources) {
+            let checksum = world_checksum(&self.ecs, &self.resources);
+            eprintln!(
+                "Replay desynced on turn {}: expected checksum {:016x}, got {:016x}",
+                turns, expected, checksum
+            );
+            if let Some(mut log) = self.resources.get_mut::<MessageLog>() {
+                log.add(format!("Replay desynced on turn {}!", turns), RED);
+            }
         }
     }
 
@@ -134,9 +314,19 @@ impl Game {
             TurnState::MonsterTurn => self
                 .monster_systems
                 .execute(&mut self.ecs, &mut self.resources),
+            TurnState::GameOver => self
+                .game_over_systems
+                .execute(&mut self.ecs, &mut self.resources),
         }
     }
 
+    fn is_game_over(&self) -> bool {
+        matches!(
+            self.resources.get::<TurnState>().as_deref(),
+            Some(TurnState::GameOver)
+        )
+    }
+
     fn has_map(&self) -> bool {
         self.resources.get::<Map>().is_some()
     }
@@ -149,18 +339,185 @@ impl GameState for Game {
         ctx.set_active_console(1);
         ctx.cls();
 
-        self.resources.insert(ctx.key);
         ctx.set_active_console(0);
-        self.resources
-            .insert(MousePoint::from_tuple(ctx.mouse_pos()));
+        let mouse = MousePoint::from_tuple(ctx.mouse_pos());
+        self.resources.insert(mouse);
+        let live = StepInput {
+            key: ctx.key,
+            modifiers: KeyModifiers {
+                shift: ctx.shift,
+                control: ctx.control,
+                alt: ctx.alt,
+            },
+            click: Some(mouse.0).filter(|_| ctx.left_click),
+        };
 
-        self.tick_on_command();
+        let was_playing = self.replay.is_playing();
+        if let (true, Some(key)) = (was_playing, live.key) {
+            self.replay.handle_key(key);
+        }
+        for frame in 0..self.replay.frames() {
+            if frame > 0 {
+                // Only the last frame fast-forwarded through is drawn
+                clear_command_buffer().expect("Render Error");
+            }
+            self.tick_on_command(live);
+        }
+        if was_playing && !self.replay.is_playing() {
+            if let Some(mut log) = self.resources.get_mut::<MessageLog>() {
+                log.add("The replay is over, you have control.".to_string(), YELLOW);
+            }
+        }
 
         render_draw_buffer(ctx).expect("Render Error");
     }
 }
 
+/// Loads the recipe given with `--recipe <path>`, if any. Without one the
+/// main menu offers a choice.
+fn recipe_from_args() -> Result<Option<GeneratorRecipe>, RecipeError> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--recipe" {
+            if let Some(path) = args.next() {
+                return GeneratorRecipe::load(path).map(Some);
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Loads the keys given with `--keys <path>`, then a saved `keybindings.ron`,
+/// falling back to the default keys
+fn keybindings_from_args() -> Result<Keybindings, KeybindingsError> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--keys" {
+            if let Some(path) = args.next() {
+                return Keybindings::load(path);
+            }
+        }
+    }
+    if std::path::Path::new(KEYBINDINGS_FILE).exists() {
+        return Keybindings::load(KEYBINDINGS_FILE);
+    }
+    Ok(Keybindings {
+        path: Some(KEYBINDINGS_FILE.into()),
+        ..Keybindings::default()
+    })
+}
+
+/// The value given after `flag`, if the flag was passed
+fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    args.find(|arg| arg == flag)?;
+    args.next()
+}
+
+fn has_flag(flag: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == flag)
+}
+
+/// Reads `--gen-speed <steps|Nms|instant>`, defaulting to one step per frame
+fn speed_from_args() -> Result<GenerationSpeed, String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--gen-speed" {
+            let value = args.next().unwrap_or_default();
+            return GenerationSpeed::parse(&value)
+                .ok_or(format!("invalid --gen-speed '{}'", value));
+        }
+    }
+    Ok(GenerationSpeed::default())
+}
+
+/// Sets up playing back `--replay <path>` at `--replay-speed <frames>`, or
+/// recording to `--record <path>`. The seed comes from the replay, then
+/// `--seed <n>`, then the recipe, and a recording picks one if none is given.
+fn replay_from_args(
+    recipe: &mut GeneratorRecipe,
+    keybindings: &mut Keybindings,
+) -> Result<ReplayMode, String> {
+    if let Some(path) = arg_value("--replay") {
+        let replay = Replay::load(&path).map_err(|error| error.to_string())?;
+        if replay.version != GAME_VERSION {
+            eprintln!(
+                "{} was recorded with version {} and may not play back the same in {}",
+                path, replay.version, GAME_VERSION
+            );
+        }
+        let speed = match arg_value("--replay-speed") {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("invalid --replay-speed '{}'", value))?,
+            None => 1,
+        };
+        *recipe = replay.recipe.clone();
+        recipe.seed = Some(replay.seed);
+        *keybindings = replay.keybindings.clone();
+        return Ok(ReplayMode::playing(replay, speed));
+    }
+
+    if let Some(value) = arg_value("--seed") {
+        let seed = value
+            .parse()
+            .map_err(|_| format!("invalid --seed '{}'", value))?;
+        recipe.seed = Some(seed);
+    }
+    match arg_value("--record") {
+        Some(path) => {
+            if has_flag("--debug-gen") {
+                return Err("--debug-gen can't be used while recording".to_string());
+            }
+            let seed = recipe
+                .seed
+                .unwrap_or_else(|| RandomNumberGenerator::new().next_u64());
+            recipe.seed = Some(seed);
+            Ok(ReplayMode::Recording {
+                replay: Replay::new(seed, recipe.clone(), keybindings.clone()),
+                path: path.into(),
+            })
+        }
+        None => Ok(ReplayMode::Off),
+    }
+}
+
 fn main() -> BError {
+    let chosen_recipe = match recipe_from_args() {
+        Ok(recipe) => recipe,
+        Err(error) => {
+            eprintln!("Invalid generator recipe: {}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut keybindings = match keybindings_from_args() {
+        Ok(keybindings) => keybindings,
+        Err(error) => {
+            eprintln!("Invalid keybindings: {}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut speed = match speed_from_args() {
+        Ok(speed) => speed,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut recipe = chosen_recipe.clone().unwrap_or_default();
+    let replay = match replay_from_args(&mut recipe, &mut keybindings) {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut debug_generation = has_flag("--debug-gen");
+    if replay.is_playing() {
+        debug_generation = false;
+        speed = GenerationSpeed::Instant;
+    }
+
     let mut context = BTermBuilder::simple80x50()
         .with_title("Ferros RTS")
         .with_dimensions(DIMENSION_WIDTH, DIMENSION_HEIGHT)
@@ -171,5 +528,21 @@ fn main() -> BError {
 
     context.with_post_scanlines(true);
 
-    main_loop(context, Game::new())
+    // Replays and recordings need their recipe up front, so only a plain game
+    // started without `--recipe` asks for one
+    let choose_recipe = chosen_recipe.is_none() && matches!(replay, ReplayMode::Off);
+    let mut game = Game::new(
+        keybindings,
+        debug_generation,
+        has_flag("--debug-ai"),
+        speed,
+        replay,
+    );
+    if choose_recipe {
+        let seed = arg_value("--seed").and_then(|value| value.parse().ok());
+        game.resources.insert(RecipeMenu::new(RECIPES_DIR, seed));
+    } else {
+        game.start(recipe);
+    }
+    main_loop(context, game)
 }
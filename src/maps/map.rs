@@ -1,6 +1,22 @@
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
-#[derive(Debug, Clone)]
+const CARDINALS: [Point; 4] = [
+    Point { x: -1, y: 0 },
+    Point { x: 1, y: 0 },
+    Point { x: 0, y: -1 },
+    Point { x: 0, y: 1 },
+];
+
+const DIAGONALS: [Point; 4] = [
+    Point { x: -1, y: -1 },
+    Point { x: 1, y: -1 },
+    Point { x: -1, y: 1 },
+    Point { x: 1, y: 1 },
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Map {
     pub width: i32,
     pub height: i32,
@@ -19,7 +35,53 @@ impl Map {
         }
     }
     pub fn can_enter_tile(&self, point: Point) -> bool {
-        self.is_floor(point)
+        self.get_tile(point).is_some_and(|tile| tile.is_passable())
+    }
+
+    /// Whether a unit could get onto the tile, possibly by opening a door first
+    pub fn can_traverse_tile(&self, point: Point) -> bool {
+        self.get_tile(point)
+            .is_some_and(|tile| tile.is_passable() || tile.is_door())
+    }
+
+    pub fn set_tile(&mut self, Point { x, y }: Point, tile: TileType) {
+        if let Some(idx) = self.idx(x, y) {
+            self.tiles[idx] = tile;
+        }
+    }
+
+    /// Exits from `idx` for pathing, diagonal ones included when `diagonals`
+    /// is set. Units open closed doors as they walk into them, which costs a
+    /// little extra so open ways are preferred, and locked doors can only be
+    /// passed by units that can unlock them.
+    pub fn exits(
+        &self,
+        idx: usize,
+        can_unlock: bool,
+        diagonals: bool,
+    ) -> SmallVec<[(usize, f32); 10]> {
+        let point = self.point_at(idx);
+        let diagonals: &[Point] = if diagonals { &DIAGONALS } else { &[] };
+        CARDINALS
+            .iter()
+            .map(|delta| (*delta, 1.0))
+            .chain(
+                diagonals
+                    .iter()
+                    .map(|delta| (*delta, std::f32::consts::SQRT_2)),
+            )
+            .filter_map(|(delta, distance)| {
+                let next = point + delta;
+                let cost = match self.get_tile(next)? {
+                    TileType::DoorClosed => 2.0,
+                    TileType::DoorLocked if can_unlock => 2.0,
+                    tile if tile.is_passable() => tile.movement_cost(),
+                    _ => return None,
+                };
+                self.idx(next.x, next.y)
+                    .map(|next_idx| (next_idx, cost * distance))
+            })
+            .collect()
     }
 
     pub fn fill(&mut self, tile: TileType) {
@@ -42,8 +104,136 @@ impl Map {
         })
     }
 
-    pub fn is_floor(&self, point: Point) -> bool {
-        matches!(self.get_tile(point), Some(TileType::Floor))
+    pub fn carve_points(&mut self, points: &[Point], tile: TileType) {
+        points.iter().for_each(|Point { x, y }| {
+            if let Some(idx) = self.idx(*x, *y) {
+                self.tiles[idx] = tile;
+            }
+        })
+    }
+
+    /// Returns a mask over `tiles` of every tile that can be walked to from `start`
+    pub fn reachable_from(&self, start: Point) -> Vec<bool> {
+        let mut reachable = vec![false; self.tiles.len()];
+        let start_idx = match self.idx(start.x, start.y) {
+            Some(idx) if self.can_traverse_tile(start) => idx,
+            _ => return reachable,
+        };
+        reachable[start_idx] = true;
+
+        let mut open = vec![start];
+        while let Some(point) = open.pop() {
+            for delta in CARDINALS.iter() {
+                let next = point + *delta;
+                if !self.can_traverse_tile(next) {
+                    continue;
+                }
+                if let Some(idx) = self.idx(next.x, next.y) {
+                    if !reachable[idx] {
+                        reachable[idx] = true;
+                        open.push(next);
+                    }
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Walking distance from `start` to every tile, `None` when it cannot be reached
+    pub fn distances_from(&self, start: Point) -> Vec<Option<i32>> {
+        let mut distances = vec![None; self.tiles.len()];
+        let start_idx = match self.idx(start.x, start.y) {
+            Some(idx) if self.can_traverse_tile(start) => idx,
+            _ => return distances,
+        };
+        distances[start_idx] = Some(0);
+
+        let mut open = std::collections::VecDeque::from(vec![(start, 0)]);
+        while let Some((point, distance)) = open.pop_front() {
+            for delta in CARDINALS.iter() {
+                let next = point + *delta;
+                if !self.can_traverse_tile(next) {
+                    continue;
+                }
+                if let Some(idx) = self.idx(next.x, next.y) {
+                    if distances[idx].is_none() {
+                        distances[idx] = Some(distance + 1);
+                        open.push_back((next, distance + 1));
+                    }
+                }
+            }
+        }
+        distances
+    }
+
+    /// Number of cardinal neighbours of `point` that can be entered
+    pub fn open_neighbours(&self, point: Point) -> usize {
+        CARDINALS
+            .iter()
+            .filter(|delta| self.can_traverse_tile(point + **delta))
+            .count()
+    }
+
+    /// Groups every floor tile not marked in `reachable` into connected regions
+    pub fn unreachable_regions(&self, reachable: &[bool]) -> Vec<Vec<Point>> {
+        let mut seen = reachable.to_vec();
+        let mut regions = Vec::new();
+        for idx in 0..self.tiles.len() {
+            let point = self.point_at(idx);
+            if seen[idx] || !self.can_traverse_tile(point) {
+                continue;
+            }
+            let region_mask = self.reachable_from(point);
+            let region: Vec<Point> = region_mask
+                .iter()
+                .enumerate()
+                .filter(|(_, is_in_region)| **is_in_region)
+                .map(|(region_idx, _)| {
+                    seen[region_idx] = true;
+                    self.point_at(region_idx)
+                })
+                .collect();
+            regions.push(region);
+        }
+        regions
+    }
+
+    /// Whether nothing opaque lies on the straight line between the two points.
+    /// The end points themselves may be opaque.
+    pub fn has_line_of_sight(&self, from: Point, to: Point) -> bool {
+        Bresenham::new(from, to)
+            .skip(1)
+            .all(|point| self.get_tile(point).is_some_and(|tile| !tile.is_opaque()))
+    }
+
+    pub fn point_at(&self, idx: usize) -> Point {
+        let idx = idx as i32;
+        Point::new(idx % self.width, idx / self.width)
+    }
+
+    pub fn find_tile(&self, tile: TileType) -> Option<Point> {
+        self.tiles
+            .iter()
+            .position(|t| *t == tile)
+            .map(|idx| self.point_at(idx))
+    }
+
+    /// The first tile a unit can stand on, if there is one
+    pub fn first_open_tile(&self) -> Option<Point> {
+        self.tiles
+            .iter()
+            .position(|t| t.is_passable())
+            .map(|idx| self.point_at(idx))
+    }
+
+    /// The map as a unit that can or can't unlock doors paths over it, moving
+    /// as `rules` allow
+    pub fn pathing(&self, can_unlock: bool, rules: &MovementRules) -> PathingMap<'_> {
+        PathingMap {
+            map: self,
+            can_unlock,
+            diagonals: rules.diagonals,
+        }
     }
 
     pub fn get_tile(&self, Point { x, y }: Point) -> Option<TileType> {
@@ -60,3 +250,53 @@ impl Map {
         Some(((y * self.width) as usize) + x as usize)
     }
 }
+
+impl Algorithm2D for Map {
+    fn dimensions(&self) -> Point {
+        Point::new(self.width, self.height)
+    }
+}
+
+impl BaseMap for Map {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.tiles[idx].is_opaque()
+    }
+
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        self.exits(idx, false, false)
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        DistanceAlg::Pythagoras.distance2d(self.point_at(idx1), self.point_at(idx2))
+    }
+}
+
+/// Paths over a map for one particular unit, so units carrying a key can plan
+/// routes through locked doors and paths follow the diagonal movement rule.
+/// The map on its own paths like a unit without a key that can't step
+/// diagonally.
+pub struct PathingMap<'a> {
+    map: &'a Map,
+    can_unlock: bool,
+    diagonals: bool,
+}
+
+impl Algorithm2D for PathingMap<'_> {
+    fn dimensions(&self) -> Point {
+        self.map.dimensions()
+    }
+}
+
+impl BaseMap for PathingMap<'_> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.map.is_opaque(idx)
+    }
+
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        self.map.exits(idx, self.can_unlock, self.diagonals)
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.map.get_pathing_distance(idx1, idx2)
+    }
+}
@@ -1,7 +1,12 @@
 mod map;
+mod stats;
+mod theme;
 
 use crate::prelude::*;
 pub use map::*;
+use serde::{Deserialize, Serialize};
+pub use stats::*;
+pub use theme::*;
 
 pub struct MapResult {
     pub map: Map,
@@ -14,12 +19,21 @@ pub enum BuildCommandResult {
     Progress { total: usize, current: usize },
 }
 
+#[derive(Clone)]
 pub struct MapBuilder {
     pub width: i32,
     pub height: i32,
     pub rooms: Vec<Rect>,
     pub tunnels: Vec<Tunnel>,
+    pub connections: Vec<(usize, usize)>,
+    pub connected: usize,
+    pub sealed: Vec<Point>,
+    pub features: Vec<(Point, TileType)>,
+    pub items: Vec<Point>,
+    pub keys: Vec<Point>,
     pub player: Option<Point>,
+    pub stairs_up: Option<Point>,
+    pub stairs_down: Option<Point>,
     pub fill_tile: Option<TileType>,
     pub finished: bool,
     pub point: Point,
@@ -32,7 +46,15 @@ impl MapBuilder {
             height,
             rooms: Vec::with_capacity(100),
             tunnels: Vec::with_capacity(200),
+            connections: Vec::new(),
+            connected: 0,
+            sealed: Vec::new(),
+            features: Vec::new(),
+            items: Vec::new(),
+            keys: Vec::new(),
             player: None,
+            stairs_up: None,
+            stairs_down: None,
             fill_tile: None,
             finished: false,
             point: Point::zero(),
@@ -52,6 +74,140 @@ impl MapBuilder {
         BuildCommandResult::Finished
     }
 
+    /// Places the up stairs under the player and the down stairs in the room
+    /// furthest away from them.
+    pub fn place_stairs(&mut self) -> BuildCommandResult {
+        let player = match self.player {
+            Some(player) => player,
+            None => return BuildCommandResult::Finished,
+        };
+        let furthest = self
+            .rooms
+            .iter()
+            .map(|room| room.center())
+            .max_by_key(|center| (center.x - player.x).pow(2) + (center.y - player.y).pow(2));
+
+        self.stairs_up = Some(player);
+        self.stairs_down = furthest.filter(|down| *down != player);
+        if let Some(down) = self.stairs_down {
+            self.point = down;
+        }
+        BuildCommandResult::Finished
+    }
+
+    /// Scatters `per_room` tiles at random spots in every room, keeping room
+    /// centres clear since units are placed there.
+    pub fn scatter_tiles(
+        &mut self,
+        tile: TileType,
+        per_room: i32,
+        rng: &mut RandomNumberGenerator,
+    ) -> BuildCommandResult {
+        for room in self.rooms.iter() {
+            for _ in 0..per_room {
+                let point = Point::new(rng.range(room.x1, room.x2), rng.range(room.y1, room.y2));
+                if point != room.center() {
+                    self.features.push((point, tile));
+                    self.point = point;
+                }
+            }
+        }
+        BuildCommandResult::Finished
+    }
+
+    /// Puts a door wherever a one tile wide tunnel meets the edge of a room,
+    /// locking `lock_percent` of them.
+    pub fn place_doors(
+        &mut self,
+        lock_percent: i32,
+        rng: &mut RandomNumberGenerator,
+    ) -> BuildCommandResult {
+        let map = self.build_map().map;
+        let mut doors: Vec<Point> = Vec::new();
+        for room in self.rooms.iter() {
+            let sides = (room.x1..room.x2)
+                .flat_map(|x| [Point::new(x, room.y1 - 1), Point::new(x, room.y2)])
+                .map(|point| (point, Point::new(1, 0)))
+                .chain(
+                    (room.y1..room.y2)
+                        .flat_map(|y| [Point::new(room.x1 - 1, y), Point::new(room.x2, y)])
+                        .map(|point| (point, Point::new(0, 1))),
+                );
+            for (point, along_wall) in sides {
+                let is_corridor_mouth = map.can_enter_tile(point)
+                    && !map.can_enter_tile(point + along_wall)
+                    && !map.can_enter_tile(point - along_wall)
+                    && !room.point_in_rect(point);
+                if is_corridor_mouth && !doors.contains(&point) {
+                    doors.push(point);
+                }
+            }
+        }
+
+        for door in doors {
+            let tile = if rng.range(0, 100) < lock_percent {
+                TileType::DoorLocked
+            } else {
+                TileType::DoorClosed
+            };
+            self.features.push((door, tile));
+            self.point = door;
+        }
+        BuildCommandResult::Finished
+    }
+
+    /// Picks spots for `per_room` items in every room. When any door is locked a
+    /// key is also placed somewhere the player can reach without a key.
+    pub fn place_items(
+        &mut self,
+        per_room: i32,
+        rng: &mut RandomNumberGenerator,
+    ) -> BuildCommandResult {
+        let mut map = self.build_map().map;
+        for room in self.rooms.iter() {
+            for _ in 0..per_room {
+                let point = Point::new(rng.range(room.x1, room.x2), rng.range(room.y1, room.y2));
+                if point != room.center()
+                    && map.can_enter_tile(point)
+                    && !self.items.contains(&point)
+                {
+                    self.items.push(point);
+                    self.point = point;
+                }
+            }
+        }
+
+        let player = match self.player {
+            Some(player) if map.tiles.contains(&TileType::DoorLocked) => player,
+            _ => return BuildCommandResult::Finished,
+        };
+        map.tiles
+            .iter_mut()
+            .filter(|tile| **tile == TileType::DoorLocked)
+            .for_each(|tile| *tile = TileType::Wall);
+        let reachable = map.reachable_from(player);
+        let candidates: Vec<Point> = self
+            .rooms
+            .iter()
+            .flat_map(|room| {
+                (room.y1..room.y2)
+                    .flat_map(move |y| (room.x1..room.x2).map(move |x| Point::new(x, y)))
+            })
+            .filter(|point| {
+                *point != player
+                    && map.can_enter_tile(*point)
+                    && reachable[map.point2d_to_index(*point)]
+                    && !self.items.contains(point)
+            })
+            .collect();
+        if !candidates.is_empty() {
+            let key = candidates[rng.range(0, candidates.len())];
+            self.keys.push(key);
+            self.point = key;
+        }
+        BuildCommandResult::Finished
+    }
+
     pub fn build_room(
         &mut self,
         num_of_rooms: i32,
@@ -60,7 +216,7 @@ impl MapBuilder {
     ) -> BuildCommandResult {
         let num_of_rooms = num_of_rooms as usize;
         if self.rooms.len() >= num_of_rooms {
-            self.rooms.sort_by(|a, b| a.center().x.cmp(&b.center().x));
+            self.rooms.sort_by_key(|room| room.center().x);
             return BuildCommandResult::Finished;
         }
 
@@ -83,35 +239,134 @@ impl MapBuilder {
 
     pub fn build_tunnels(
         &mut self,
-        max_tunnels: i32,
+        strategy: ConnectionStrategy,
+        corridor: CorridorShape,
         rng: &mut RandomNumberGenerator,
     ) -> BuildCommandResult {
-        let max_tunnels = max_tunnels as usize;
-        if self.tunnels.len() >= max_tunnels {
+        if self.connected == 0 && self.connections.is_empty() {
+            self.connections = strategy.plan(&self.rooms, rng);
+        }
+        if self.connected >= self.connections.len() {
             return BuildCommandResult::Finished;
         }
 
-        let connecting_room_index = (self.tunnels.len() / 2) + 1;
-        let prev = self.rooms[connecting_room_index - 1].center();
-        let new = self.rooms[connecting_room_index].center();
-
+        let (from, to) = self.connections[self.connected];
+        let prev = self.rooms[from].center();
+        let new = self.rooms[to].center();
         self.point = (prev + new) / 2;
 
-        if rng.range(0, 2) == 1 {
-            self.tunnels.push(Tunnel::horizontal(prev.x, new.x, prev.y));
-            self.tunnels.push(Tunnel::vertical(prev.y, new.y, new.x));
-        } else {
-            self.tunnels.push(Tunnel::vertical(prev.y, new.y, prev.x));
-            self.tunnels.push(Tunnel::horizontal(prev.x, new.x, new.y));
-        }
+        let bounds = Rect::with_exact(1, 1, self.width - 2, self.height - 2);
+        self.tunnels
+            .extend(corridor.tunnels(prev, new, bounds, rng));
+        self.connected += 1;
 
         BuildCommandResult::Progress {
-            total: max_tunnels,
-            current: self.tunnels.len(),
+            total: self.connections.len(),
+            current: self.connected,
+        }
+    }
+
+    /// Flood fills from the player's spawn, falling back to the first room when
+    /// the player has not been placed yet.
+    pub fn connectivity_report(&self) -> ConnectivityReport {
+        let start = match self
+            .player
+            .or_else(|| self.rooms.first().map(|r| r.center()))
+        {
+            Some(start) => start,
+            None => return ConnectivityReport::default(),
+        };
+        let map = self.build_map().map;
+        let reachable = map.reachable_from(start);
+        ConnectivityReport {
+            reachable_tiles: reachable.iter().filter(|r| **r).count(),
+            unreachable: map.unreachable_regions(&reachable),
+        }
+    }
+
+    /// Tunnels are carved before features and sealed tiles, so a repair tunnel
+    /// also lays floor over any chasm, wall or sealed tile in its way. A region
+    /// gets a tunnel bending one way and, if that doesn't reach it, the other
+    /// way; regions neither reaches are walled off like the rest.
+    pub fn ensure_connectivity(&mut self, repair: ConnectivityRepair) -> BuildCommandResult {
+        let mut report = self.connectivity_report();
+        if repair == ConnectivityRepair::Tunnel {
+            // A tile from each region no tunnel could reach
+            let mut given_up: Vec<Point> = Vec::new();
+            while let Some(region) = report
+                .unreachable
+                .iter()
+                .find(|region| !region.iter().any(|point| given_up.contains(point)))
+                .cloned()
+            {
+                let before = report.unreachable.len();
+                for vertical_first in [false, true] {
+                    self.connect_region(&region, vertical_first);
+                    report = self.connectivity_report();
+                    if report.unreachable.len() < before {
+                        break;
+                    }
+                }
+                if report.unreachable.len() >= before {
+                    given_up.push(region[0]);
+                }
+            }
+        }
+        for region in report.unreachable {
+            self.sealed.extend(region);
+        }
+        BuildCommandResult::Finished
+    }
+
+    fn connect_region(&mut self, region: &[Point], vertical_first: bool) {
+        let start = self
+            .player
+            .or_else(|| self.rooms.first().map(|r| r.center()));
+        let map = self.build_map().map;
+        let reachable = match start {
+            Some(start) => map.reachable_from(start),
+            None => return,
+        };
+        let closest = reachable
+            .iter()
+            .enumerate()
+            .filter(|(_, is_reachable)| **is_reachable)
+            .map(|(idx, _)| map.point_at(idx))
+            .flat_map(|from| region.iter().map(move |to| (from, *to)))
+            .min_by_key(|(from, to)| (from.x - to.x).abs() + (from.y - to.y).abs());
+
+        if let Some((from, to)) = closest {
+            self.point = to;
+            let tunnels = if vertical_first {
+                [
+                    Tunnel::vertical(from.y, to.y, from.x),
+                    Tunnel::horizontal(from.x, to.x, to.y),
+                ]
+            } else {
+                [
+                    Tunnel::horizontal(from.x, to.x, from.y),
+                    Tunnel::vertical(from.y, to.y, to.x),
+                ]
+            };
+            for tunnel in tunnels {
+                for point in tunnel {
+                    let blocked = self
+                        .features
+                        .iter()
+                        .rev()
+                        .find(|(feature, _)| *feature == point)
+                        .is_some_and(|(_, tile)| !tile.is_passable() && !tile.is_door());
+                    if blocked {
+                        self.features.push((point, TileType::Floor));
+                    }
+                    self.sealed.retain(|sealed| *sealed != point);
+                }
+                self.tunnels.push(tunnel);
+            }
         }
     }
 
-    pub fn build_map(&mut self) -> MapResult {
+    pub fn build_map(&self) -> MapResult {
         let mut map = Map::new(self.width, self.height);
         for tile in self.fill_tile.iter() {
             map.fill(*tile);
@@ -122,6 +377,16 @@ impl MapBuilder {
         for tunnel in self.tunnels.iter() {
             map.carve_tunnel(tunnel, TileType::Floor);
         }
+        for (point, tile) in self.features.iter() {
+            map.carve_points(&[*point], *tile);
+        }
+        map.carve_points(&self.sealed, TileType::Wall);
+        for stairs in self.stairs_up.iter() {
+            map.carve_points(&[*stairs], TileType::StairsUp);
+        }
+        for stairs in self.stairs_down.iter() {
+            map.carve_points(&[*stairs], TileType::StairsDown);
+        }
         MapResult {
             map,
             player: self.player,
@@ -129,28 +394,64 @@ impl MapBuilder {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum TileType {
     Floor,
     Wall,
+    StairsDown,
+    StairsUp,
+    DoorOpen,
+    DoorClosed,
+    DoorLocked,
+    Water,
+    Lava,
+    Chasm,
+    Rubble,
+    Grass,
+}
+
+impl TileType {
+    /// Whether a unit can stand on the tile
+    pub fn is_passable(&self) -> bool {
+        !matches!(
+            self,
+            TileType::Wall | TileType::DoorClosed | TileType::DoorLocked | TileType::Chasm
+        )
+    }
+
+    /// Whether the tile blocks line of sight
+    pub fn is_opaque(&self) -> bool {
+        matches!(
+            self,
+            TileType::Wall | TileType::DoorClosed | TileType::DoorLocked
+        )
+    }
+
+    pub fn is_door(&self) -> bool {
+        matches!(
+            self,
+            TileType::DoorOpen | TileType::DoorClosed | TileType::DoorLocked
+        )
+    }
+
+    /// Relative cost of moving onto the tile, used when pathing
+    pub fn movement_cost(&self) -> f32 {
+        match self {
+            TileType::Water | TileType::Rubble => 2.0,
+            TileType::Lava => 5.0,
+            _ => 1.0,
+        }
+    }
 }
 
 impl From<TileType> for FontCharType {
     fn from(val: TileType) -> Self {
-        match val {
-            TileType::Floor => to_cp437(','),
-            TileType::Wall => to_cp437('#'),
-        }
+        TileTheme::Dungeon.glyph(val)
     }
 }
 
 impl From<TileType> for ColorPair {
     fn from(val: TileType) -> Self {
-        let fg_light = RGBA::from_f32(1.0, 1.0, 0.0, 0.5);
-        let fg_mid = RGBA::from_f32(1.0, 1.0, 0.0, 0.75);
-        match val {
-            TileType::Floor => ColorPair::new(fg_light, BLACK),
-            TileType::Wall => ColorPair::new(fg_mid, BLACK),
-        }
+        TileTheme::Dungeon.color(val)
     }
 }
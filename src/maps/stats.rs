@@ -0,0 +1,49 @@
+use crate::prelude::*;
+
+/// Measurements used to compare the output of different generator recipes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MapStats {
+    pub floor_ratio: f32,
+    pub rooms: usize,
+    pub longest_path: i32,
+    pub dead_ends: usize,
+    pub connected: bool,
+}
+
+impl MapStats {
+    pub fn measure(builder: &MapBuilder, map: &Map) -> Self {
+        let floor: Vec<Point> = (0..map.tiles.len())
+            .map(|idx| map.point_at(idx))
+            .filter(|point| map.can_enter_tile(*point))
+            .collect();
+        let dead_ends = floor
+            .iter()
+            .filter(|point| map.open_neighbours(**point) == 1)
+            .count();
+
+        Self {
+            floor_ratio: floor.len() as f32 / map.tiles.len() as f32,
+            rooms: builder.rooms.len(),
+            longest_path: builder.player.map(|p| longest_path(map, p)).unwrap_or(0),
+            dead_ends,
+            connected: builder.connectivity_report().is_connected(),
+        }
+    }
+}
+
+/// Approximates the longest shortest path with two breadth first searches: the
+/// tile furthest from `start`, then the tile furthest from that one.
+fn longest_path(map: &Map, start: Point) -> i32 {
+    let furthest = |from: Point| {
+        map.distances_from(from)
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, distance)| distance.map(|d| (idx, d)))
+            .max_by_key(|(_, distance)| *distance)
+            .map(|(idx, distance)| (map.point_at(idx), distance))
+    };
+    furthest(start)
+        .and_then(|(far, _)| furthest(far))
+        .map(|(_, distance)| distance)
+        .unwrap_or(0)
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Decides how each `TileType` looks, so levels can share tiles but not a look
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum TileTheme {
+    #[default]
+    Dungeon,
+    Cave,
+    Forest,
+    Fortress,
+}
+
+impl TileTheme {
+    const ROTATION: [TileTheme; 4] = [
+        TileTheme::Dungeon,
+        TileTheme::Cave,
+        TileTheme::Fortress,
+        TileTheme::Forest,
+    ];
+
+    /// The theme used for a level when its recipe does not pick one
+    pub fn for_depth(depth: i32) -> Self {
+        let index = (depth - Depth::SURFACE).max(0) as usize;
+        Self::ROTATION[index % Self::ROTATION.len()]
+    }
+
+    pub fn glyph(&self, tile: TileType) -> FontCharType {
+        match (self, tile) {
+            (TileTheme::Dungeon, TileType::Floor) => to_cp437(','),
+            (TileTheme::Dungeon, TileType::Wall) => to_cp437('#'),
+            (TileTheme::Cave, TileType::Floor) => to_cp437('.'),
+            (TileTheme::Cave, TileType::Wall) => to_cp437('▒'),
+            (TileTheme::Forest, TileType::Floor) => to_cp437('.'),
+            (TileTheme::Forest, TileType::Wall) => to_cp437('♣'),
+            (TileTheme::Fortress, TileType::Floor) => to_cp437('·'),
+            (TileTheme::Fortress, TileType::Wall) => to_cp437('█'),
+            (_, TileType::StairsDown) => to_cp437('>'),
+            (_, TileType::StairsUp) => to_cp437('<'),
+            (_, TileType::DoorOpen) => to_cp437('\''),
+            (_, TileType::DoorClosed) | (_, TileType::DoorLocked) => to_cp437('+'),
+            (_, TileType::Water) | (_, TileType::Lava) => to_cp437('≈'),
+            (_, TileType::Chasm) => to_cp437('░'),
+            (_, TileType::Rubble) => to_cp437(';'),
+            (_, TileType::Grass) => to_cp437('"'),
+        }
+    }
+
+    pub fn color(&self, tile: TileType) -> ColorPair {
+        let (floor, wall) = self.palette();
+        match tile {
+            TileType::Floor => ColorPair::new(floor, BLACK),
+            TileType::Wall => ColorPair::new(wall, BLACK),
+            TileType::StairsDown | TileType::StairsUp => ColorPair::new(WHITE, BLACK),
+            TileType::DoorOpen | TileType::DoorClosed => ColorPair::new(SANDY_BROWN, BLACK),
+            TileType::DoorLocked => ColorPair::new(GOLD, BLACK),
+            TileType::Water => ColorPair::new(DODGER_BLUE, NAVY),
+            TileType::Lava => ColorPair::new(ORANGE_RED, DARK_RED),
+            TileType::Chasm => ColorPair::new(DIM_GRAY, BLACK),
+            TileType::Rubble => ColorPair::new(SADDLE_BROWN, BLACK),
+            TileType::Grass => ColorPair::new(FOREST_GREEN, BLACK),
+        }
+    }
+
+    fn palette(&self) -> (RGBA, RGBA) {
+        match self {
+            TileTheme::Dungeon => (
+                RGBA::from_f32(1.0, 1.0, 0.0, 0.5),
+                RGBA::from_f32(1.0, 1.0, 0.0, 0.75),
+            ),
+            TileTheme::Cave => (RGBA::named(PERU), RGBA::named(SIENNA)),
+            TileTheme::Forest => (RGBA::named(DARKOLIVEGREEN), RGBA::named(FOREST_GREEN)),
+            TileTheme::Fortress => (RGBA::named(GRAY), RGBA::named(LIGHTSLATEGRAY)),
+        }
+    }
+}
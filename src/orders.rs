@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// What a group of units is told to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Command {
+    /// Take one step, dropping any standing objective
+    Step(Direction),
+    /// Walk to a tile, in formation when several units are sent together
+    MoveTo {
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        formation: Formation,
+    },
+    Attack(UnitId),
+    /// Send workers to gather from the resource node at this point
+    Gather {
+        x: i32,
+        y: i32,
+    },
+    /// Queue a unit at a base, paying for it now
+    Train(UnitKind),
+    /// Stop whatever the units were doing
+    Hold,
+}
+
+/// How units sent to the same tile arrange themselves around it, so they
+/// don't all try to stand on it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Formation {
+    /// Side by side, across the way they are heading
+    Line,
+    /// One behind another
+    Column,
+    /// As close to a square as the group allows
+    #[default]
+    Box,
+    /// A square with a tile left free between neighbours
+    Spread,
+}
+
+impl Formation {
+    pub const ALL: [Formation; 4] = [
+        Formation::Line,
+        Formation::Column,
+        Formation::Box,
+        Formation::Spread,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Formation::Line => "line",
+            Formation::Column => "column",
+            Formation::Box => "box",
+            Formation::Spread => "spread",
+        }
+    }
+
+    /// The formation after this one, wrapping around
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|formation| formation == self);
+        Self::ALL[index.map_or(0, |index| (index + 1) % Self::ALL.len())]
+    }
+
+    /// Where each of `count` units stands relative to the front of the
+    /// formation while heading towards `facing`, front rank first
+    pub fn offsets(&self, count: usize, facing: Point) -> Vec<Point> {
+        let forward = if facing.x == 0 && facing.y == 0 {
+            Point::new(0, -1)
+        } else if facing.x.abs() >= facing.y.abs() {
+            Point::new(facing.x.signum(), 0)
+        } else {
+            Point::new(0, facing.y.signum())
+        };
+        let right = Point::new(-forward.y, forward.x);
+        let side = (count as f32).sqrt().ceil().max(1.0) as i32;
+        // 0, 1, -1, 2, -2, ... so a line or rank grows out from its middle
+        let outwards = |i: i32| if i % 2 == 1 { (i + 1) / 2 } else { -(i / 2) };
+        (0..count as i32)
+            .map(|i| {
+                let (across, back) = match self {
+                    Formation::Line => (outwards(i), 0),
+                    Formation::Column => (0, i),
+                    Formation::Box => (outwards(i % side), i / side),
+                    Formation::Spread => (outwards(i % side) * 2, i / side * 2),
+                };
+                right * across - forward * back
+            })
+            .collect()
+    }
+}
+
+/// A command a player gives to some of their own units. Orders are the only
+/// way players change a skirmish, so sending every player the same orders in
+/// the same sequence keeps all their simulations in step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Order {
+    pub faction: Faction,
+    pub units: Vec<UnitId>,
+    pub command: Command,
+}
+
+impl Order {
+    pub fn new(faction: Faction, units: Vec<UnitId>, command: Command) -> Self {
+        Self {
+            faction,
+            units,
+            command,
+        }
+    }
+}
+
+/// The orders to carry out on the next turn, applied in sequence
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PendingOrders(pub Vec<Order>);
+
+/// The faction the player at this screen commands in a skirmish
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerFaction(pub Faction);
+
+/// The units a player has picked out in a skirmish, and the numbered control
+/// groups saved for picking the same units again. Each group remembers the
+/// formation it was last set to move in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ControlGroups {
+    pub selected: Vec<UnitId>,
+    /// The formation the picked out units move in
+    pub formation: Formation,
+    groups: BTreeMap<u8, (Vec<UnitId>, Formation)>,
+    /// The group the picked out units were recalled from or saved as, if they
+    /// haven't been picked out by hand since
+    current: Option<u8>,
+}
+
+impl ControlGroups {
+    /// Picks out units by hand
+    pub fn select(&mut self, units: Vec<UnitId>) {
+        self.selected = units;
+        self.current = None;
+    }
+
+    /// Recalls or saves a control group, or switches formation, returning
+    /// whether the action was one of those
+    pub fn handle(&mut self, action: Action) -> bool {
+        match action {
+            Action::SelectGroup(group) => {
+                if let Some((units, formation)) = self.groups.get(&group) {
+                    self.selected = units.clone();
+                    self.formation = *formation;
+                    self.current = Some(group);
+                }
+            }
+            Action::AssignGroup(group) => {
+                self.groups
+                    .insert(group, (self.selected.clone(), self.formation));
+                self.current = Some(group);
+            }
+            Action::CycleFormation => {
+                self.formation = self.formation.next();
+                if let Some((_, formation)) =
+                    self.current.and_then(|group| self.groups.get_mut(&group))
+                {
+                    *formation = self.formation;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Forgets units that are gone from the selection and every group
+    pub fn retain(&mut self, alive: impl Fn(UnitId) -> bool) {
+        self.selected.retain(|id| alive(*id));
+        for (units, _) in self.groups.values_mut() {
+            units.retain(|id| alive(*id));
+        }
+    }
+
+    /// Sends the picked out units to `target` in their formation
+    pub fn move_to(&self, faction: Faction, target: Point) -> Option<Order> {
+        (!self.selected.is_empty()).then(|| {
+            Order::new(
+                faction,
+                self.selected.clone(),
+                Command::MoveTo {
+                    x: target.x,
+                    y: target.y,
+                    formation: self.formation,
+                },
+            )
+        })
+    }
+}
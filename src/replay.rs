@@ -0,0 +1,351 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Written into every replay. A replay made by another version may not play
+/// back the same way, so it is only a warning when they differ.
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The input handed to the input schedule on one step
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StepInput {
+    pub key: Option<VirtualKeyCode>,
+    pub modifiers: KeyModifiers,
+    pub click: Option<Point>,
+}
+
+impl StepInput {
+    pub fn is_empty(&self) -> bool {
+        self.key.is_none() && self.click.is_none()
+    }
+}
+
+/// An input step where a key was pressed or the mouse clicked. Steps count
+/// how many times the input schedule has run, so quiet steps are left out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedInput {
+    pub step: u64,
+    #[serde(default)]
+    pub key: Option<KeyChord>,
+    #[serde(default)]
+    pub click: Option<(i32, i32)>,
+    /// The action the key was bound to, so a replay reads as the orders given
+    #[serde(default)]
+    pub action: Option<Action>,
+}
+
+impl RecordedInput {
+    pub fn input(&self) -> StepInput {
+        StepInput {
+            key: self.key.map(|chord| chord.key),
+            modifiers: self.key.map(|chord| chord.modifiers).unwrap_or_default(),
+            click: self.click.map(|(x, y)| Point::new(x, y)),
+        }
+    }
+}
+
+/// Everything needed to play a session again: the seed both RNGs started
+/// from, the recipe and keys it was played with, and every input given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: String,
+    pub seed: u64,
+    pub recipe: GeneratorRecipe,
+    pub keybindings: Keybindings,
+    pub checksum_interval: u32,
+    pub inputs: Vec<RecordedInput>,
+    /// The world checksum at the end of every `checksum_interval`th turn
+    pub checksums: Vec<(u32, u64)>,
+}
+
+impl Replay {
+    pub const CHECKSUM_INTERVAL: u32 = 10;
+
+    pub fn new(seed: u64, recipe: GeneratorRecipe, keybindings: Keybindings) -> Self {
+        Self {
+            version: GAME_VERSION.to_string(),
+            seed,
+            recipe,
+            keybindings,
+            checksum_interval: Self::CHECKSUM_INTERVAL,
+            inputs: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|error| ReplayError::Io {
+            path: path.display().to_string(),
+            error,
+        })?;
+        ron::de::from_str(&source).map_err(|error| ReplayError::Parse {
+            path: Some(path.display().to_string()),
+            error,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let path = path.as_ref();
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())
+            .map_err(|error| ReplayError::Parse { path: None, error })?;
+        std::fs::write(path, source).map_err(|error| ReplayError::Io {
+            path: path.display().to_string(),
+            error,
+        })
+    }
+
+    pub fn wants_checksum(&self, turn: u32) -> bool {
+        turn > 0 && turn.is_multiple_of(self.checksum_interval)
+    }
+}
+
+/// Whether the session is being recorded, played back from a replay, or neither
+pub enum ReplayMode {
+    Off,
+    Recording {
+        replay: Replay,
+        path: PathBuf,
+    },
+    Playing {
+        replay: Replay,
+        next_input: usize,
+        next_checksum: usize,
+        /// Game frames run per rendered frame, above one to fast-forward
+        speed: u32,
+    },
+}
+
+impl ReplayMode {
+    pub const MAX_SPEED: u32 = 64;
+
+    pub fn playing(replay: Replay, speed: u32) -> Self {
+        ReplayMode::Playing {
+            replay,
+            next_input: 0,
+            next_checksum: 0,
+            speed: speed.clamp(1, Self::MAX_SPEED),
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self, ReplayMode::Playing { .. })
+    }
+
+    /// How many game frames to run this rendered frame
+    pub fn frames(&self) -> u32 {
+        match self {
+            ReplayMode::Playing { speed, .. } => *speed,
+            _ => 1,
+        }
+    }
+
+    /// Speeds playback up or down while the recorded inputs stand in for the keyboard
+    pub fn handle_key(&mut self, key: VirtualKeyCode) {
+        if let ReplayMode::Playing { speed, .. } = self {
+            match key {
+                VirtualKeyCode::Equals | VirtualKeyCode::Plus | VirtualKeyCode::NumpadAdd => {
+                    *speed = (*speed * 2).min(Self::MAX_SPEED)
+                }
+                VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => {
+                    *speed = (*speed / 2).max(1)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The input to run input step `step` with. Recording writes down the live
+    /// input and passes it through, playback swaps it for the recorded one until
+    /// the replay runs out and hands control back to the player.
+    pub fn step(&mut self, step: u64, live: StepInput, keybindings: &Keybindings) -> StepInput {
+        match self {
+            ReplayMode::Off => live,
+            ReplayMode::Recording { replay, .. } => {
+                if !live.is_empty() {
+                    replay.inputs.push(RecordedInput {
+                        step,
+                        key: live.key.map(|key| KeyChord::new(key, live.modifiers)),
+                        click: live.click.map(|point| (point.x, point.y)),
+                        action: keybindings.action(live.key, live.modifiers),
+                    });
+                }
+                live
+            }
+            ReplayMode::Playing {
+                replay, next_input, ..
+            } => {
+                match replay.inputs.get(*next_input) {
+                    Some(recorded) if recorded.step == step => {
+                        *next_input += 1;
+                        recorded.input()
+                    }
+                    Some(_) => StepInput::default(),
+                    // The step after the last input still belongs to the replay,
+                    // as it counts the turn that input ended
+                    None if step <= replay.inputs.last().map_or(0, |last| last.step + 1) => {
+                        StepInput::default()
+                    }
+                    None => {
+                        *self = ReplayMode::Off;
+                        live
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes a recording out with every input so far, for when the game ends
+    /// or the window closes between checksum turns
+    pub fn save(&self) {
+        if let ReplayMode::Recording { replay, path } = self {
+            if let Err(error) = replay.save(path) {
+                eprintln!("Could not save replay: {}", error);
+            }
+        }
+    }
+
+    /// Records or checks the world checksum when a turn ends, returning the
+    /// checksum the replay expected when the world no longer matches it
+    pub fn turn_ended(&mut self, turn: u32, ecs: &World, resources: &Resources) -> Option<u64> {
+        match self {
+            ReplayMode::Off => None,
+            ReplayMode::Recording { replay, path } => {
+                // Saving rewrites the whole replay, so it only happens as
+                // often as checksums are taken
+                if replay.wants_checksum(turn) {
+                    replay
+                        .checksums
+                        .push((turn, world_checksum(ecs, resources)));
+                    if let Err(error) = replay.save(path) {
+                        eprintln!("Could not save replay: {}", error);
+                    }
+                }
+                None
+            }
+            ReplayMode::Playing {
+                replay,
+                next_checksum,
+                ..
+            } => {
+                let (checked, expected) = *replay.checksums.get(*next_checksum)?;
+                if checked != turn {
+                    return None;
+                }
+                *next_checksum += 1;
+                Some(expected).filter(|expected| *expected != world_checksum(ecs, resources))
+            }
+        }
+    }
+}
+
+/// FNV-1a, used over the std hasher because its output is fixed across builds
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Hashes what a desync shows up in: the depth, the map, every unit's place,
+/// health and side, every item lying on the floor and, in skirmishes, what is
+/// left in each resource node and stockpile. Entities are sorted by what they
+/// are rather than by id, as ids differ between runs.
+pub fn world_checksum(ecs: &World, resources: &Resources) -> u64 {
+    let mut hasher = Fnv1a::default();
+    if let Some(depth) = resources.get::<Depth>() {
+        depth.0.hash(&mut hasher);
+    }
+    if let Some(map) = resources.get::<Map>() {
+        map.width.hash(&mut hasher);
+        map.height.hash(&mut hasher);
+        map.tiles
+            .iter()
+            .for_each(|tile| (*tile as u8).hash(&mut hasher));
+    }
+
+    let mut units: Vec<_> = <(Entity, &Point, &Health)>::query()
+        .iter(ecs)
+        .map(|(entity, pos, health)| {
+            let entry = ecs.entry_ref(*entity).ok();
+            let is_player = entry
+                .as_ref()
+                .is_some_and(|entry| entry.get_component::<Player>().is_ok());
+            let faction = entry
+                .as_ref()
+                .and_then(|entry| entry.get_component::<Faction>().ok().copied());
+            let name = entry
+                .as_ref()
+                .and_then(|entry| entry.get_component::<Name>().ok().map(|n| n.0.clone()))
+                .unwrap_or_default();
+            let unit = (pos.x, pos.y, health.current, health.max);
+            (unit, is_player, faction, name)
+        })
+        .collect();
+    units.sort();
+    units.hash(&mut hasher);
+
+    let mut items: Vec<(i32, i32, String)> = <(&Point, &Name)>::query()
+        .filter(component::<Item>())
+        .iter(ecs)
+        .map(|(pos, name)| (pos.x, pos.y, name.0.clone()))
+        .collect();
+    items.sort();
+    items.hash(&mut hasher);
+
+    let mut nodes: Vec<(i32, i32, u32)> = <(&Point, &ResourceNode)>::query()
+        .iter(ecs)
+        .map(|(pos, node)| (pos.x, pos.y, node.remaining))
+        .collect();
+    nodes.sort();
+    nodes.hash(&mut hasher);
+    if let Some(stockpiles) = resources.get::<Stockpiles>() {
+        stockpiles.0.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Parse {
+        path: Option<String>,
+        error: ron::Error,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io { path, error } => write!(f, "could not access {}: {}", path, error),
+            ReplayError::Parse {
+                path: Some(path),
+                error,
+            } => write!(f, "{}: {}", path, error),
+            ReplayError::Parse { path: None, error } => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
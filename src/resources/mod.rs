@@ -1,7 +1,14 @@
+use std::collections::{BTreeMap, HashMap};
 use std::ops::Deref;
 
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
+mod spatial_index;
+
+pub use spatial_index::SpatialIndex;
+
 pub struct ProgressBar {
     pub total: i32,
     pub current: i32,
@@ -28,6 +35,10 @@ impl WorldGenRng {
     pub fn new() -> Self {
         Self(RandomNumberGenerator::new())
     }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self(RandomNumberGenerator::seeded(seed))
+    }
 }
 
 impl Default for WorldGenRng {
@@ -50,6 +61,227 @@ impl std::ops::DerefMut for WorldGenRng {
     }
 }
 
+/// Rolls for everything that happens during play, kept apart from the world
+/// generation RNG so a replay can reseed both from the recorded seed
+pub struct GameRng(RandomNumberGenerator);
+
+impl GameRng {
+    pub fn new() -> Self {
+        Self(RandomNumberGenerator::new())
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self(RandomNumberGenerator::seeded(seed))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::ops::Deref for GameRng {
+    type Target = RandomNumberGenerator;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for GameRng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Which action picking an item from the inventory panel performs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InventoryMenu {
+    Closed,
+    Use,
+    Drop,
+}
+
+/// Whether the player is picking a tile to shoot at, and which one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Targeting {
+    Inactive,
+    Aiming(Point),
+}
+
+/// Rest-until-healed. `last_health` is the player's health when the previous
+/// rest turn started, so any damage taken since then interrupts the rest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resting {
+    Inactive,
+    Active { last_health: i32 },
+}
+
+/// The keybindings screen: which action is highlighted, and whether the next
+/// key pressed will be bound to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RebindMenu {
+    Closed,
+    Browsing(usize),
+    Capturing(usize),
+}
+
+/// The main menu: the recipes a new game can be generated from, which one is
+/// highlighted and, once the player confirms, the one to generate
+pub struct RecipeMenu {
+    pub recipes: Vec<(String, Result<GeneratorRecipe, String>)>,
+    pub selected: usize,
+    /// Replaces the chosen recipe's own seed, from `--seed`
+    pub seed: Option<u64>,
+    pub chosen: Option<GeneratorRecipe>,
+}
+
+impl RecipeMenu {
+    /// Offers every recipe in `dir`, starting on `default`. The built-in
+    /// default recipe stands in when the directory has none.
+    pub fn new<P: AsRef<std::path::Path>>(dir: P, seed: Option<u64>) -> Self {
+        let mut recipes: Vec<_> = GeneratorRecipe::list(dir)
+            .into_iter()
+            .map(|(name, recipe)| (name, recipe.map_err(|error| error.to_string())))
+            .collect();
+        if !recipes.iter().any(|(name, _)| name == "default") {
+            recipes.insert(0, ("default".to_string(), Ok(GeneratorRecipe::default())));
+        }
+        let selected = recipes
+            .iter()
+            .position(|(name, _)| name == "default")
+            .unwrap_or_default();
+        Self {
+            recipes,
+            selected,
+            seed,
+            chosen: None,
+        }
+    }
+}
+
+/// Where the player has made a noise since monsters last listened, and how far
+/// each noise carries
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Noises(pub Vec<(Point, i32)>);
+
+/// Recent messages, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct MessageLog {
+    pub messages: Vec<(String, RGB)>,
+}
+
+impl MessageLog {
+    /// How many messages the log keeps
+    pub const LENGTH: usize = 50;
+
+    pub fn add(&mut self, text: String, color: (u8, u8, u8)) {
+        self.messages.push((text, RGB::named(color)));
+        if self.messages.len() > Self::LENGTH {
+            self.messages.remove(0);
+        }
+    }
+}
+
+/// Running totals for the current game
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Statistics {
+    pub turns: u32,
+    pub kills: u32,
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+    pub items_picked_up: u32,
+    pub tiles_walked: u32,
+    pub deepest_level: i32,
+}
+
+/// A milestone the player can reach once per game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Achievement {
+    FirstBlood,
+    Slayer,
+    Hoarder,
+    DeepDelver,
+}
+
+impl Achievement {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Achievement::FirstBlood => "First Blood",
+            Achievement::Slayer => "Slayer",
+            Achievement::Hoarder => "Hoarder",
+            Achievement::DeepDelver => "Deep Delver",
+        }
+    }
+}
+
+/// The achievements unlocked this game, and the counts that lead to them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Achievements {
+    pub unlocked: Vec<Achievement>,
+    pub kills: u32,
+    pub items_picked_up: u32,
+}
+
+impl Achievements {
+    pub const SLAYER_KILLS: u32 = 10;
+    pub const HOARDER_ITEMS: u32 = 10;
+    pub const DEEP_DELVER_DEPTH: i32 = 5;
+
+    pub fn has(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// Unlocks `achievement`, returning whether it was new
+    pub fn unlock(&mut self, achievement: Achievement) -> bool {
+        if self.has(achievement) {
+            return false;
+        }
+        self.unlocked.push(achievement);
+        true
+    }
+}
+
+/// How many turns the replay recorder has seen end. The recording is
+/// checksummed each time this moves on.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayTurns(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Depth(pub i32);
+
+impl Depth {
+    pub const SURFACE: i32 = 1;
+}
+
+/// A level the player has left, waiting to be restored when they return
+pub struct StoredLevel {
+    pub map: Map,
+    pub theme: TileTheme,
+    pub entities: World,
+}
+
+#[derive(Default)]
+pub struct Dungeon {
+    levels: HashMap<i32, StoredLevel>,
+}
+
+impl Dungeon {
+    pub fn store(&mut self, depth: i32, level: StoredLevel) {
+        self.levels.insert(depth, level);
+    }
+
+    pub fn take(&mut self, depth: i32) -> Option<StoredLevel> {
+        self.levels.remove(&depth)
+    }
+
+    /// Every stored level with its depth, in no particular order
+    pub fn levels(&self) -> impl Iterator<Item = (i32, &StoredLevel)> {
+        self.levels.iter().map(|(depth, level)| (*depth, level))
+    }
+}
+
 pub struct Camera {
     pub left_x: i32,
     pub right_x: i32,
@@ -152,3 +384,48 @@ impl Deref for MousePoint {
         &self.0
     }
 }
+
+/// Where on screen the left mouse button was clicked this frame, if it was
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseClick(pub Option<Point>);
+
+/// Whether every monster on screen is labelled with what it decided to do on
+/// its last turn, turned on with `--debug-ai`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AiDebugOverlay(pub bool);
+
+/// Resources each skirmish faction has gathered and not yet spent
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stockpiles(pub BTreeMap<Faction, u32>);
+
+impl Stockpiles {
+    pub fn of(&self, faction: Faction) -> u32 {
+        self.0.get(&faction).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, faction: Faction, amount: u32) {
+        *self.0.entry(faction).or_insert(0) += amount;
+    }
+
+    /// Takes `amount` from the faction's stockpile if it has that much
+    pub fn spend(&mut self, faction: Faction, amount: u32) -> bool {
+        match self.0.get_mut(&faction) {
+            Some(stock) if *stock >= amount => {
+                *stock -= amount;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The id the next skirmish unit to be spawned gets
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NextUnitId(pub u32);
+
+impl NextUnitId {
+    pub fn take(&mut self) -> UnitId {
+        self.0 += 1;
+        UnitId(self.0 - 1)
+    }
+}
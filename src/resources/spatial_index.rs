@@ -0,0 +1,98 @@
+use crate::prelude::*;
+
+/// Every entity with a `Point`, bucketed by tile so "who is here" lookups cost
+/// the same however many units there are. Cells are indexed like `Map::tiles`.
+///
+/// The index is built once when a level is entered or loaded. From then on
+/// whatever spawns, moves, picks up, drops or removes a positioned entity
+/// updates it as it goes. A dead player is kept in the world but taken out of
+/// the index.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    width: i32,
+    height: i32,
+    cells: Vec<Vec<Entity>>,
+}
+
+impl SpatialIndex {
+    pub fn new(map: &Map) -> Self {
+        let mut index = Self::default();
+        index.clear(map);
+        index
+    }
+
+    /// An index of every entity with a `Point` in `ecs`
+    pub fn build<W: EntityStore>(map: &Map, ecs: &W) -> Self {
+        let mut index = Self::new(map);
+        <(Entity, &Point)>::query().for_each(ecs, |(entity, pos)| index.insert(*entity, *pos));
+        index
+    }
+
+    /// Empties the index and resizes it to `map`
+    pub fn clear(&mut self, map: &Map) {
+        self.width = map.width;
+        self.height = map.height;
+        self.cells.iter_mut().for_each(|cell| cell.clear());
+        self.cells
+            .resize_with((map.width * map.height) as usize, Vec::new);
+    }
+
+    fn idx(&self, Point { x, y }: Point) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        Some((y * self.width + x) as usize)
+    }
+
+    pub fn insert(&mut self, entity: Entity, point: Point) {
+        if let Some(idx) = self.idx(point) {
+            self.cells[idx].push(entity);
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity, point: Point) {
+        if let Some(idx) = self.idx(point) {
+            self.cells[idx].retain(|other| *other != entity);
+        }
+    }
+
+    pub fn move_entity(&mut self, entity: Entity, from: Point, to: Point) {
+        self.remove(entity, from);
+        self.insert(entity, to);
+    }
+
+    /// The entities on `point`
+    pub fn at(&self, point: Point) -> &[Entity] {
+        match self.idx(point) {
+            Some(idx) => &self.cells[idx],
+            None => &[],
+        }
+    }
+
+    /// The entities inside `rect`, which like `Rect::for_each` excludes its
+    /// right and bottom edges
+    pub fn in_rect(&self, rect: Rect) -> impl Iterator<Item = (Entity, Point)> + '_ {
+        let (x1, x2) = (rect.x1.max(0), rect.x2.min(self.width));
+        let (y1, y2) = (rect.y1.max(0), rect.y2.min(self.height));
+        (y1..y2)
+            .flat_map(move |y| (x1..x2).map(move |x| Point::new(x, y)))
+            .flat_map(move |point| self.at(point).iter().map(move |entity| (*entity, point)))
+    }
+
+    /// The entities no further than `radius` from `center`
+    pub fn in_radius(
+        &self,
+        center: Point,
+        radius: f32,
+    ) -> impl Iterator<Item = (Entity, Point)> + '_ {
+        let reach = radius.floor() as i32;
+        let bounds = Rect::with_exact(
+            center.x - reach,
+            center.y - reach,
+            center.x + reach + 1,
+            center.y + reach + 1,
+        );
+        self.in_rect(bounds)
+            .filter(move |(_, point)| DistanceAlg::Pythagoras.distance2d(center, *point) <= radius)
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+use std::path::Path;
+
+use legion::serialize::{Canon, UnknownType};
+use serde::de::DeserializeSeed;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Where the game is saved to and loaded from
+pub const SAVE_FILE: &str = "savegame.json";
+
+/// The components that make up the dungeon and everything in it. Messages
+/// such as `WantsToMove` only live for a turn and are left out.
+fn registry() -> Registry<String> {
+    let mut registry = Registry::<String>::default();
+    registry.on_unknown(UnknownType::Ignore);
+    registry.register::<Point>("point".to_string());
+    registry.register::<Render>("render".to_string());
+    registry.register::<Name>("name".to_string());
+    registry.register::<Player>("player".to_string());
+    registry.register::<Enemy>("enemy".to_string());
+    registry.register::<Health>("health".to_string());
+    registry.register::<CombatStats>("combat_stats".to_string());
+    registry.register::<StatusEffects>("status_effects".to_string());
+    registry.register::<Experience>("experience".to_string());
+    registry.register::<ExperienceValue>("experience_value".to_string());
+    registry.register::<Regeneration>("regeneration".to_string());
+    registry.register::<Hunger>("hunger".to_string());
+    registry.register::<Behaviour>("behaviour".to_string());
+    registry.register::<Awareness>("awareness".to_string());
+    registry.register::<Decision>("decision".to_string());
+    registry.register::<Ranged>("ranged".to_string());
+    registry.register::<InflictsEffect>("inflicts_effect".to_string());
+    registry.register::<Item>("item".to_string());
+    registry.register::<Consumable>("consumable".to_string());
+    registry.register::<ProvidesHealing>("provides_healing".to_string());
+    registry.register::<ProvidesNutrition>("provides_nutrition".to_string());
+    registry.register::<DamagesNearby>("damages_nearby".to_string());
+    registry.register::<GrantsEffect>("grants_effect".to_string());
+    registry.register::<Weapon>("weapon".to_string());
+    registry.register::<Armour>("armour".to_string());
+    registry.register::<Equippable>("equippable".to_string());
+    registry.register::<Equipped>("equipped".to_string());
+    registry.register::<Key>("key".to_string());
+    registry.register::<Carried>("carried".to_string());
+    registry
+}
+
+/// A level and everything on it, with the entities written out by `registry`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedLevel {
+    pub map: Map,
+    pub theme: TileTheme,
+    pub entities: serde_json::Value,
+}
+
+impl SavedLevel {
+    fn capture(
+        map: &Map,
+        theme: TileTheme,
+        entities: &World,
+        registry: &Registry<String>,
+        canon: &Canon,
+    ) -> Result<Self, SaveError> {
+        let entities = entities.as_serializable(
+            component::<Point>() | component::<Carried>(),
+            registry,
+            canon,
+        );
+        Ok(Self {
+            map: map.clone(),
+            theme,
+            entities: serde_json::to_value(&entities).map_err(SaveError::Format)?,
+        })
+    }
+
+    fn restore(self, registry: &Registry<String>, canon: &Canon) -> Result<StoredLevel, SaveError> {
+        let entities = registry
+            .as_deserialize(canon)
+            .deserialize(self.entities)
+            .map_err(SaveError::Format)?;
+        Ok(StoredLevel {
+            map: self.map,
+            theme: self.theme,
+            entities,
+        })
+    }
+}
+
+/// The level being played, every level the player has left behind, and how
+/// deep they are. Units, items and their status effects are kept; whatever
+/// only lasts for a turn is not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: String,
+    pub depth: i32,
+    pub statistics: Statistics,
+    #[serde(default)]
+    pub achievements: Achievements,
+    pub current: SavedLevel,
+    pub levels: Vec<(i32, SavedLevel)>,
+}
+
+impl SaveGame {
+    /// Everything in `ecs` and `resources` that makes up the game being played
+    pub fn capture(ecs: &World, resources: &Resources) -> Result<Self, SaveError> {
+        let map = resources.get::<Map>().ok_or(SaveError::NoLevel)?;
+        let theme = resources.get::<TileTheme>().map(|t| *t).unwrap_or_default();
+        let registry = registry();
+        // One canon for every level, so an entity keeps its name across them
+        let canon = Canon::default();
+
+        let current = SavedLevel::capture(&map, theme, ecs, &registry, &canon)?;
+        let mut levels = Vec::new();
+        if let Some(dungeon) = resources.get::<Dungeon>() {
+            for (depth, level) in dungeon.levels() {
+                let saved = SavedLevel::capture(
+                    &level.map,
+                    level.theme,
+                    &level.entities,
+                    &registry,
+                    &canon,
+                )?;
+                levels.push((depth, saved));
+            }
+        }
+        levels.sort_by_key(|(depth, _)| *depth);
+
+        Ok(Self {
+            version: GAME_VERSION.to_string(),
+            depth: resources.get::<Depth>().map_or(Depth::SURFACE, |d| d.0),
+            statistics: resources
+                .get::<Statistics>()
+                .map(|s| *s)
+                .unwrap_or_default(),
+            achievements: resources
+                .get::<Achievements>()
+                .map(|a| a.clone())
+                .unwrap_or_default(),
+            current,
+            levels,
+        })
+    }
+
+    /// Replaces the game in `ecs` and `resources` with the saved one. Nothing
+    /// is touched unless the whole save could be read.
+    pub fn restore(self, ecs: &mut World, resources: &mut Resources) -> Result<(), SaveError> {
+        let registry = registry();
+        let canon = Canon::default();
+
+        let mut current = self.current.restore(&registry, &canon)?;
+        let mut dungeon = Dungeon::default();
+        for (depth, level) in self.levels {
+            dungeon.store(depth, level.restore(&registry, &canon)?);
+        }
+        let player = <&Point>::query()
+            .filter(component::<Player>())
+            .iter(&current.entities)
+            .next()
+            .copied()
+            .ok_or(SaveError::NoPlayer)?;
+
+        ecs.clear();
+        ecs.move_from(&mut current.entities, &any());
+        resources.insert(SpatialIndex::build(&current.map, ecs));
+        resources.insert(Camera::new(player));
+        resources.insert(current.map);
+        resources.insert(current.theme);
+        resources.insert(dungeon);
+        resources.insert(Depth(self.depth));
+        resources.insert(self.statistics);
+        resources.insert(self.achievements);
+        resources.insert(TurnState::AwaitingInput);
+        resources.insert(Noises::default());
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SaveError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|error| SaveError::Io {
+            path: path.display().to_string(),
+            error,
+        })?;
+        serde_json::from_str(&source).map_err(SaveError::Format)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        let source = serde_json::to_string(self).map_err(SaveError::Format)?;
+        std::fs::write(path, source).map_err(|error| SaveError::Io {
+            path: path.display().to_string(),
+            error,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    Format(serde_json::Error),
+    /// The level is still being generated
+    NoLevel,
+    NoPlayer,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io { path, error } => write!(f, "could not access {}: {}", path, error),
+            SaveError::Format(error) => write!(f, "{}", error),
+            SaveError::NoLevel => write!(f, "there is no level to save yet"),
+            SaveError::NoPlayer => write!(f, "the save has no player in it"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
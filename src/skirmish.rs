@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::systems;
+
+/// Everything every player needs to build the same skirmish
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkirmishSettings {
+    pub recipe: GeneratorRecipe,
+    pub seed: u64,
+    pub factions: u8,
+    pub workers_per_faction: u32,
+    pub soldiers_per_faction: u32,
+    pub starting_resources: u32,
+}
+
+impl Default for SkirmishSettings {
+    fn default() -> Self {
+        Self {
+            recipe: GeneratorRecipe::default(),
+            seed: 0,
+            factions: 2,
+            workers_per_faction: 3,
+            soldiers_per_faction: 2,
+            starting_resources: 100,
+        }
+    }
+}
+
+/// A skirmish unit as players see it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitInfo {
+    pub id: UnitId,
+    pub faction: Faction,
+    pub kind: UnitKind,
+    pub pos: Point,
+    pub health: i32,
+    /// Whether it has no objective to carry out
+    pub idle: bool,
+}
+
+fn build_turn_scheduler() -> Schedule {
+    Schedule::builder()
+        .add_system(systems::clear_events_system())
+        .add_system(systems::apply_orders_system())
+        .flush()
+        .add_system(systems::keep_formation_system())
+        .add_system(systems::gather_system())
+        .add_system(systems::pursue_objectives_system())
+        .flush()
+        .add_system(systems::characters::combat_system())
+        .flush()
+        .add_system(systems::characters::movement_system())
+        .add_system(systems::produce_system())
+        .flush()
+        .build()
+}
+
+fn build_input_scheduler() -> Schedule {
+    Schedule::builder()
+        .add_system(systems::command_units_system())
+        .build()
+}
+
+/// A headless match between factions, driven only by the orders given each
+/// turn. Two skirmishes built from the same settings and given the same
+/// orders stay identical, which is what lockstep play relies on.
+pub struct Skirmish {
+    pub ecs: World,
+    pub resources: Resources,
+    schedule: Schedule,
+    input_schedule: Schedule,
+    rooms: Vec<Point>,
+    turn: u32,
+}
+
+impl Skirmish {
+    /// How far units see, for `visible_to`
+    pub const SIGHT_RADIUS: f32 = 8.0;
+    const VEIN_SIZE: u32 = 400;
+    /// How far from its base a faction's first resource node lies
+    const HOME_VEIN_DISTANCE: i32 = 4;
+
+    /// Generates the map and starts each faction with a base, workers and
+    /// soldiers around a room of its own, spread through the order the
+    /// generator placed the rooms in. Each faction gets a resource node near
+    /// its base and every other room gets one in its centre.
+    pub fn new(settings: &SkirmishSettings) -> Self {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        let mut rng = WorldGenRng::seeded(settings.seed);
+        let mut builder = settings.recipe.builder();
+        settings
+            .recipe
+            .runner()
+            .run_to_completion(&mut builder, &mut rng);
+        let rooms: Vec<Point> = builder.rooms.iter().map(|room| room.center()).collect();
+        let MapResult { map, player } = builder.build_map();
+
+        let mut commands = CommandBuffer::new(&ecs);
+        let mut next_id = NextUnitId::default();
+        let mut stockpiles = Stockpiles::default();
+        let mut taken = HashSet::new();
+        let mut home_rooms = HashSet::new();
+        let open: Vec<Point> = (0..map.tiles.len())
+            .map(|idx| map.point_at(idx))
+            .filter(|point| map.can_enter_tile(*point))
+            .collect();
+        for faction in (0..settings.factions).map(Faction) {
+            let home = match rooms.len() {
+                0 => player.unwrap_or_else(|| map.point_at(0)),
+                len => {
+                    let room = faction.0 as usize * len / settings.factions as usize;
+                    home_rooms.insert(room);
+                    rooms[room]
+                }
+            };
+            let distance = |point: &Point| {
+                let offset = *point - home;
+                offset.x * offset.x + offset.y * offset.y
+            };
+            let mut spots: Vec<Point> = open
+                .iter()
+                .copied()
+                .filter(|point| !taken.contains(point))
+                .collect();
+            spots.sort_by_key(|point| (distance(point), point.y, point.x));
+
+            let kinds = std::iter::once(UnitKind::Base)
+                .chain((0..settings.workers_per_faction).map(|_| UnitKind::Worker))
+                .chain((0..settings.soldiers_per_faction).map(|_| UnitKind::Soldier));
+            for (kind, pos) in kinds.zip(spots.iter()) {
+                spawn_unit(&mut commands, kind, faction, next_id.take(), *pos);
+                taken.insert(*pos);
+            }
+            let vein = spots.iter().find(|point| {
+                distance(point) >= Self::HOME_VEIN_DISTANCE.pow(2) && !taken.contains(*point)
+            });
+            if let Some(vein) = vein {
+                spawn_resource_node(&mut commands, *vein, Self::VEIN_SIZE);
+                taken.insert(*vein);
+            }
+            stockpiles.add(faction, settings.starting_resources);
+        }
+        for (room, center) in rooms.iter().enumerate() {
+            if !home_rooms.contains(&room) && !taken.contains(center) {
+                spawn_resource_node(&mut commands, *center, Self::VEIN_SIZE);
+                taken.insert(*center);
+            }
+        }
+        commands.flush(&mut ecs, &mut resources);
+
+        resources.insert(SpatialIndex::build(&map, &ecs));
+        resources.insert(map);
+        resources.insert(Camera::new(Point::zero()));
+        resources.insert(MovementRules { diagonals: true });
+        resources.insert(EventChannel::default());
+        resources.insert(GameRng::seeded(settings.seed));
+        resources.insert(PendingOrders::default());
+        resources.insert(stockpiles);
+        resources.insert(next_id);
+
+        Self {
+            ecs,
+            resources,
+            schedule: build_turn_scheduler(),
+            input_schedule: build_input_scheduler(),
+            rooms,
+            turn: 0,
+        }
+    }
+
+    /// How many turns have been played
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    /// Plays one turn, carrying out `orders` in the sequence given
+    pub fn advance(&mut self, orders: Vec<Order>) {
+        self.resources.insert(PendingOrders(orders));
+        self.schedule.execute(&mut self.ecs, &mut self.resources);
+        self.turn += 1;
+    }
+
+    /// Lets the player at this screen command `faction` with `keybindings`
+    pub fn take_command(&mut self, faction: Faction, keybindings: Keybindings) {
+        self.resources.insert(PlayerFaction(faction));
+        self.resources.insert(keybindings);
+        self.resources.insert(ControlGroups::default());
+    }
+
+    /// Hands one step of input to the player who took command, returning the
+    /// orders they gave. Like everyone else's, the orders only take effect
+    /// once they are passed to `advance`.
+    pub fn command(&mut self, input: StepInput) -> Vec<Order> {
+        self.resources.insert(input.key);
+        self.resources.insert(input.modifiers);
+        self.resources.insert(MouseClick(input.click));
+        self.resources.insert(PendingOrders::default());
+        self.input_schedule
+            .execute(&mut self.ecs, &mut self.resources);
+        self.resources
+            .remove::<PendingOrders>()
+            .map_or_else(Vec::new, |orders| orders.0)
+    }
+
+    pub fn checksum(&self) -> u64 {
+        world_checksum(&self.ecs, &self.resources)
+    }
+
+    /// The centre of every room on the map, in the order they were generated
+    pub fn rooms(&self) -> &[Point] {
+        &self.rooms
+    }
+
+    /// Whether units can stand on the tile
+    pub fn is_open(&self, point: Point) -> bool {
+        self.resources
+            .get::<Map>()
+            .is_some_and(|map| map.can_enter_tile(point))
+    }
+
+    /// The tiles a unit would walk from `from` to `to`, not counting the one
+    /// it starts on, or nothing if there is no way there
+    pub fn route(&self, from: Point, to: Point) -> Vec<Point> {
+        let map = self
+            .resources
+            .get::<Map>()
+            .expect("Skirmish requires a Map");
+        let rules = self
+            .resources
+            .get::<MovementRules>()
+            .expect("Skirmish requires MovementRules");
+        let path = a_star_search(
+            map.point2d_to_index(from),
+            map.point2d_to_index(to),
+            &map.pathing(false, &rules),
+        );
+        match path.success {
+            true => path
+                .steps
+                .iter()
+                .skip(1)
+                .map(|idx| map.index_to_point2d(*idx))
+                .collect(),
+            false => Vec::new(),
+        }
+    }
+
+    fn all_units(&self) -> Vec<UnitInfo> {
+        let mut units: Vec<UnitInfo> =
+            <(Entity, &UnitId, &Faction, &UnitKind, &Point, &Health)>::query()
+                .iter(&self.ecs)
+                .map(|(entity, id, faction, kind, pos, health)| UnitInfo {
+                    id: *id,
+                    faction: *faction,
+                    kind: *kind,
+                    pos: *pos,
+                    health: health.current,
+                    idle: self
+                        .ecs
+                        .entry_ref(*entity)
+                        .is_ok_and(|entry| entry.get_component::<Objective>().is_err()),
+                })
+                .collect();
+        units.sort_by_key(|unit| unit.id);
+        units
+    }
+
+    /// A faction's living units, by id
+    pub fn units(&self, faction: Faction) -> Vec<UnitInfo> {
+        self.all_units()
+            .into_iter()
+            .filter(|unit| unit.faction == faction)
+            .collect()
+    }
+
+    /// Other factions' units that one of `faction`'s units can see
+    pub fn visible_to(&self, faction: Faction) -> Vec<UnitInfo> {
+        let map = self
+            .resources
+            .get::<Map>()
+            .expect("Skirmish requires a Map");
+        let (own, others): (Vec<UnitInfo>, Vec<UnitInfo>) = self
+            .all_units()
+            .into_iter()
+            .partition(|unit| unit.faction == faction);
+        others
+            .into_iter()
+            .filter(|other| {
+                own.iter().any(|unit| {
+                    DistanceAlg::Pythagoras.distance2d(unit.pos, other.pos) <= Self::SIGHT_RADIUS
+                        && map.has_line_of_sight(unit.pos, other.pos)
+                })
+            })
+            .collect()
+    }
+
+    /// What a faction has in its stockpile
+    pub fn stockpile(&self, faction: Faction) -> u32 {
+        self.resources
+            .get::<Stockpiles>()
+            .map_or(0, |stockpiles| stockpiles.of(faction))
+    }
+
+    /// The units a faction's base has queued, first to be trained first
+    pub fn production(&self, faction: Faction) -> Vec<UnitKind> {
+        <(&Faction, &ProductionQueue)>::query()
+            .iter(&self.ecs)
+            .filter(|(owner, _)| **owner == faction)
+            .flat_map(|(_, production)| production.queue.iter().copied())
+            .collect()
+    }
+
+    /// Every resource node left, with how much it holds
+    pub fn resource_nodes(&self) -> Vec<(Point, u32)> {
+        let mut nodes: Vec<(Point, u32)> = <(&Point, &ResourceNode)>::query()
+            .iter(&self.ecs)
+            .map(|(pos, node)| (*pos, node.remaining))
+            .collect();
+        nodes.sort_by_key(|(pos, _)| (pos.y, pos.x));
+        nodes
+    }
+
+    /// The factions with units left standing
+    pub fn factions(&self) -> Vec<Faction> {
+        let mut factions: Vec<Faction> = <&Faction>::query()
+            .filter(component::<UnitId>())
+            .iter(&self.ecs)
+            .copied()
+            .collect();
+        factions.sort();
+        factions.dedup();
+        factions
+    }
+
+    /// The last faction standing, once there is only one
+    pub fn winner(&self) -> Option<Faction> {
+        match self.factions().as_slice() {
+            [winner] => Some(*winner),
+            _ => None,
+        }
+    }
+}
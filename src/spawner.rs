@@ -1,11 +1,98 @@
 use crate::prelude::*;
 
-fn goblin() -> (Health, Name, FontCharType) {
-    (Health::new(1), "Goblin".into(), to_cp437('g'))
+type MonsterTemplate = (
+    Health,
+    CombatStats,
+    ExperienceValue,
+    Regeneration,
+    Name,
+    FontCharType,
+    Behaviour,
+);
+
+fn goblin() -> MonsterTemplate {
+    let stats = CombatStats {
+        attack: 0,
+        defense: 0,
+        damage: Dice::new(1, 2, 0),
+    };
+    (
+        Health::new(1),
+        stats,
+        ExperienceValue(5),
+        Regeneration::new(10),
+        "Goblin".into(),
+        to_cp437('g'),
+        Behaviour::pack_hunter(),
+    )
 }
 
-fn orc() -> (Health, Name, FontCharType) {
-    (Health::new(2), "Orc".into(), to_cp437('o'))
+fn goblin_archer() -> MonsterTemplate {
+    let stats = CombatStats {
+        attack: 1,
+        defense: 0,
+        damage: Dice::new(1, 3, 0),
+    };
+    (
+        Health::new(1),
+        stats,
+        ExperienceValue(8),
+        Regeneration::new(10),
+        "Goblin Archer".into(),
+        to_cp437('a'),
+        Behaviour::skirmisher(6),
+    )
+}
+
+fn orc(post: Point) -> MonsterTemplate {
+    let stats = CombatStats {
+        attack: 1,
+        defense: 1,
+        damage: Dice::new(1, 3, 0),
+    };
+    (
+        Health::new(2),
+        stats,
+        ExperienceValue(10),
+        Regeneration::new(8),
+        "Orc".into(),
+        to_cp437('o'),
+        Behaviour::guard(post, 6),
+    )
+}
+
+fn giant_spider() -> MonsterTemplate {
+    let stats = CombatStats {
+        attack: 1,
+        defense: 0,
+        damage: Dice::new(1, 1, 0),
+    };
+    (
+        Health::new(2),
+        stats,
+        ExperienceValue(10),
+        Regeneration::new(12),
+        "Giant Spider".into(),
+        to_cp437('s'),
+        Behaviour::brute(),
+    )
+}
+
+fn ogre(post: Point) -> MonsterTemplate {
+    let stats = CombatStats {
+        attack: 2,
+        defense: 1,
+        damage: Dice::new(1, 4, 1),
+    };
+    (
+        Health::new(4),
+        stats,
+        ExperienceValue(20),
+        Regeneration::new(5),
+        "Ogre".into(),
+        to_cp437('O'),
+        Behaviour::guard(post, 4),
+    )
 }
 
 pub fn spawn_player(commands: &mut CommandBuffer, pos: Point) {
@@ -13,7 +100,7 @@ pub fn spawn_player(commands: &mut CommandBuffer, pos: Point) {
         color: ColorPair::new(WHITE, BLACK),
         glyph: to_cp437('@'),
     };
-    commands.push((
+    let player = commands.push((
         Player,
         pos,
         render,
@@ -21,26 +108,336 @@ pub fn spawn_player(commands: &mut CommandBuffer, pos: Point) {
             current: 20,
             max: 20,
         },
+        CombatStats {
+            attack: 2,
+            defense: 0,
+            damage: Dice::new(1, 2, 0),
+        },
+        StatusEffects::default(),
+        Experience::default(),
     ));
+    commands.add_component(player, Hunger::new(1000));
+    commands.add_component(player, Regeneration::new(6));
 }
 
 pub fn spawn_monster(
     commands: &mut CommandBuffer,
     rng: &mut RandomNumberGenerator,
     pos: Point,
+    depth: i32,
 ) -> Entity {
-    let (hp, name, glyph) = match rng.roll_dice(1, 10) {
-        1..=9 => goblin(),
-        _ => orc(),
-    };
+    let depth = (depth - Depth::SURFACE).max(0);
+    let ((mut hp, stats, experience, regeneration, name, glyph, behaviour), ranged, inflicts) =
+        match rng.roll_dice(1, 10) + depth {
+            1..=7 => (goblin(), None, None),
+            8..=9 => (goblin_archer(), Some(Ranged { range: 6 }), None),
+            10..=12 => (orc(pos), None, None),
+            13..=14 => (
+                giant_spider(),
+                None,
+                Some(StatusEffect::new(EffectKind::Poison, 4, 1)),
+            ),
+            _ => (
+                ogre(pos),
+                None,
+                Some(StatusEffect::new(EffectKind::Stun, 2, 1)),
+            ),
+        };
+    hp.max += depth / 2;
+    hp.current = hp.max;
 
     let color = ColorPair::new(RED, BLACK);
-    commands.push((
+    let entity = commands.push((
         Enemy,
         pos,
         Render { color, glyph },
-        MovingRandomly {},
+        behaviour,
         hp,
+        stats,
         name,
+        StatusEffects::default(),
+    ));
+    commands.add_component(entity, ExperienceValue(experience.0 + depth));
+    commands.add_component(entity, regeneration);
+    commands.add_component(entity, Awareness::default());
+    if let Some(ranged) = ranged {
+        commands.add_component(entity, ranged);
+    }
+    if let Some(effect) = inflicts {
+        commands.add_component(entity, InflictsEffect(effect));
+    }
+    entity
+}
+
+fn healing_potion(commands: &mut CommandBuffer, pos: Point) -> Entity {
+    commands.push((
+        Item,
+        pos,
+        Render {
+            color: ColorPair::new(MAGENTA, BLACK),
+            glyph: to_cp437('!'),
+        },
+        Name::from("Healing Potion"),
+        Consumable,
+        ProvidesHealing { amount: 6 },
+    ))
+}
+
+fn fire_scroll(commands: &mut CommandBuffer, pos: Point) -> Entity {
+    commands.push((
+        Item,
+        pos,
+        Render {
+            color: ColorPair::new(ORANGE, BLACK),
+            glyph: to_cp437('?'),
+        },
+        Name::from("Scroll of Fire"),
+        Consumable,
+        DamagesNearby {
+            damage: 2,
+            radius: 3,
+        },
+    ))
+}
+
+fn potion(
+    commands: &mut CommandBuffer,
+    pos: Point,
+    name: &'static str,
+    color: (u8, u8, u8),
+    effect: StatusEffect,
+) -> Entity {
+    commands.push((
+        Item,
+        pos,
+        Render {
+            color: ColorPair::new(color, BLACK),
+            glyph: to_cp437('!'),
+        },
+        Name::from(name),
+        Consumable,
+        GrantsEffect(effect),
+    ))
+}
+
+fn ration(commands: &mut CommandBuffer, pos: Point) -> Entity {
+    commands.push((
+        Item,
+        pos,
+        Render {
+            color: ColorPair::new(GREEN, BLACK),
+            glyph: to_cp437('%'),
+        },
+        Name::from("Ration"),
+        Consumable,
+        ProvidesNutrition { amount: 500 },
+    ))
+}
+
+fn weakness_scroll(commands: &mut CommandBuffer, pos: Point) -> Entity {
+    commands.push((
+        Item,
+        pos,
+        Render {
+            color: ColorPair::new(PURPLE, BLACK),
+            glyph: to_cp437('?'),
+        },
+        Name::from("Scroll of Weakness"),
+        Consumable,
+        DamagesNearby {
+            damage: 0,
+            radius: 4,
+        },
+        InflictsEffect(StatusEffect::new(EffectKind::Weakness, 8, 2)),
+    ))
+}
+
+fn weapon(
+    commands: &mut CommandBuffer,
+    pos: Point,
+    name: &'static str,
+    slot: EquipmentSlot,
+    weapon: Weapon,
+) -> Entity {
+    commands.push((
+        Item,
+        pos,
+        Render {
+            color: ColorPair::new(CYAN, BLACK),
+            glyph: to_cp437(')'),
+        },
+        Name::from(name),
+        Equippable { slot },
+        weapon,
+    ))
+}
+
+fn armour(
+    commands: &mut CommandBuffer,
+    pos: Point,
+    name: &'static str,
+    glyph: char,
+    slot: EquipmentSlot,
+    defense: i32,
+) -> Entity {
+    commands.push((
+        Item,
+        pos,
+        Render {
+            color: ColorPair::new(SADDLE_BROWN, BLACK),
+            glyph: to_cp437(glyph),
+        },
+        Name::from(name),
+        Equippable { slot },
+        Armour { defense },
+    ))
+}
+
+pub fn spawn_item(
+    commands: &mut CommandBuffer,
+    rng: &mut RandomNumberGenerator,
+    pos: Point,
+) -> Entity {
+    match rng.roll_dice(1, 30) {
+        26..=30 => ration(commands, pos),
+        1..=7 => healing_potion(commands, pos),
+        8..=10 => fire_scroll(commands, pos),
+        21..=22 => potion(
+            commands,
+            pos,
+            "Potion of Haste",
+            YELLOW,
+            StatusEffect::new(EffectKind::Haste, 10, 1),
+        ),
+        23..=24 => potion(
+            commands,
+            pos,
+            "Potion of Regeneration",
+            GREEN,
+            StatusEffect::new(EffectKind::Regeneration, 10, 1),
+        ),
+        25 => weakness_scroll(commands, pos),
+        11..=12 => weapon(
+            commands,
+            pos,
+            "Dagger",
+            EquipmentSlot::Melee,
+            Weapon {
+                attack: 1,
+                damage: Dice::new(1, 4, 0),
+            },
+        ),
+        13 => weapon(
+            commands,
+            pos,
+            "Short Sword",
+            EquipmentSlot::Melee,
+            Weapon {
+                attack: 0,
+                damage: Dice::new(1, 6, 0),
+            },
+        ),
+        14 => {
+            let bow = weapon(
+                commands,
+                pos,
+                "Short Bow",
+                EquipmentSlot::Ranged,
+                Weapon {
+                    attack: 1,
+                    damage: Dice::new(1, 4, 0),
+                },
+            );
+            commands.add_component(bow, Ranged { range: 8 });
+            bow
+        }
+        15..=16 => armour(commands, pos, "Leather Armour", '[', EquipmentSlot::Body, 1),
+        17 => armour(commands, pos, "Iron Helm", '^', EquipmentSlot::Head, 1),
+        18..=19 => armour(
+            commands,
+            pos,
+            "Wooden Shield",
+            '(',
+            EquipmentSlot::Shield,
+            1,
+        ),
+        _ => armour(
+            commands,
+            pos,
+            "Ring of Protection",
+            '=',
+            EquipmentSlot::Ring,
+            1,
+        ),
+    }
+}
+
+pub fn spawn_key(commands: &mut CommandBuffer, pos: Point) -> Entity {
+    commands.push((
+        Item,
+        pos,
+        Render {
+            color: ColorPair::new(GOLD, BLACK),
+            glyph: to_cp437('k'),
+        },
+        Name::from("Key"),
+        Key,
+    ))
+}
+
+/// The colour each skirmish faction's units are drawn in, by faction number
+pub const FACTION_COLORS: [(u8, u8, u8); 4] = [CYAN, ORANGE, MAGENTA, GREEN];
+
+pub fn spawn_unit(
+    commands: &mut CommandBuffer,
+    kind: UnitKind,
+    faction: Faction,
+    id: UnitId,
+    pos: Point,
+) -> Entity {
+    let color = FACTION_COLORS[faction.0 as usize % FACTION_COLORS.len()];
+    let (name, glyph, hp, attack, damage) = match kind {
+        UnitKind::Base => ("Base", 'B', 60, 0, Dice::new(1, 1, 0)),
+        UnitKind::Worker => ("Worker", 'w', 6, 0, Dice::new(1, 2, 0)),
+        UnitKind::Soldier => ("Soldier", 's', 10, 1, Dice::new(1, 4, 0)),
+    };
+    let unit = commands.push((
+        kind,
+        faction,
+        id,
+        pos,
+        Render {
+            color: ColorPair::new(color, BLACK),
+            glyph: to_cp437(glyph),
+        },
+        Name::from(name),
+        Health::new(hp),
+        CombatStats {
+            attack,
+            defense: 0,
+            damage,
+        },
+    ));
+    commands.add_component(unit, StatusEffects::default());
+    match kind {
+        UnitKind::Base => {
+            commands.add_component(unit, Immobile);
+            commands.add_component(unit, ProductionQueue::default());
+        }
+        UnitKind::Worker => commands.add_component(unit, Cargo::default()),
+        UnitKind::Soldier => {}
+    }
+    unit
+}
+
+pub fn spawn_resource_node(commands: &mut CommandBuffer, pos: Point, amount: u32) -> Entity {
+    commands.push((
+        pos,
+        Render {
+            color: ColorPair::new(GOLD, BLACK),
+            glyph: to_cp437('$'),
+        },
+        Name::from("Gold Vein"),
+        ResourceNode { remaining: amount },
     ))
 }
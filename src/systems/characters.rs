@@ -1,111 +1,241 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
 use crate::prelude::*;
+use crate::systems::{
+    apply_effect, attack_profile, award_experience, has_effect, inflicted_effect, kill,
+    total_defense,
+};
+
+/// Whether `entity` carries a key
+pub fn holds_key(ecs: &SubWorld, entity: Entity) -> bool {
+    <&Carried>::query()
+        .filter(component::<Key>())
+        .iter(ecs)
+        .any(|carried| carried.owner == entity)
+}
+
+fn position(ecs: &SubWorld, entity: Entity) -> Option<Point> {
+    ecs.entry_ref(entity)
+        .ok()
+        .and_then(|entry| entry.get_component::<Point>().ok().copied())
+}
+
+/// The unit standing on `point`. Units killed earlier this phase may still be in
+/// the index, so only entities that still exist count.
+pub fn unit_at(ecs: &SubWorld, index: &SpatialIndex, point: Point) -> Option<Entity> {
+    index.at(point).iter().copied().find(|entity| {
+        ecs.entry_ref(*entity)
+            .is_ok_and(|entry| entry.get_component::<Health>().is_ok())
+    })
+}
 
-#[system(for_each)]
+/// Units are on the same side when both or neither are enemies and they
+/// belong to the same skirmish faction, if any
+fn allies(ecs: &SubWorld, a: Entity, b: Entity) -> bool {
+    let side = |entity: Entity| {
+        ecs.entry_ref(entity).ok().map(|entry| {
+            (
+                entry.get_component::<Enemy>().is_ok(),
+                entry.get_component::<Faction>().ok().copied(),
+            )
+        })
+    };
+    side(a) == side(b)
+}
+
+fn is_immobile(ecs: &SubWorld, entity: Entity) -> bool {
+    ecs.entry_ref(entity)
+        .is_ok_and(|entry| entry.get_component::<Immobile>().is_ok())
+}
+
+/// Resolves every move request in the order they were made. A unit moving
+/// onto an ally that has not moved yet this turn and is free to move swaps
+/// places with it; any other move onto an occupied tile bounces and the unit
+/// stays put. A move onto an ally that is itself about to step somewhere else
+/// waits until every other move is resolved, so units queued up behind each
+/// other walk on together instead of swapping back and forth.
+#[system]
+#[read_component(WantsToMove)]
 #[read_component(Player)]
+#[read_component(Enemy)]
+#[read_component(Faction)]
+#[read_component(Immobile)]
+#[read_component(Carried)]
+#[read_component(Key)]
+#[read_component(Point)]
+#[read_component(Health)]
+#[read_component(StatusEffects)]
 pub fn movement(
-    entity: &Entity,
-    want_move: &WantsToMove,
-    #[resource] map: &Map,
-    #[resource] camera: &mut Camera,
-    ecs: &mut SubWorld,
+    ecs: &SubWorld,
     commands: &mut CommandBuffer,
+    #[resource] map: &mut Map,
+    #[resource] camera: &mut Camera,
+    #[resource] index: &mut SpatialIndex,
+    #[resource] events: &mut EventChannel,
 ) {
-    if map.can_enter_tile(want_move.destination) {
-        commands.add_component(want_move.entity, want_move.destination);
+    let moves: Vec<(Entity, WantsToMove)> = <(Entity, &WantsToMove)>::query()
+        .iter(ecs)
+        .map(|(message, want_move)| (*message, *want_move))
+        .collect();
+    // Units that already moved or were swapped this turn
+    let mut moved: HashSet<Entity> = HashSet::new();
+    // Where units whose moves are not resolved yet want to go
+    let mut pending: HashMap<Entity, Point> = HashMap::new();
+    for (message, want_move) in moves.iter().rev() {
+        commands.remove(*message);
+        pending.insert(want_move.entity, want_move.destination);
+    }
+
+    // Moves still to resolve, and whether each has already waited once
+    let mut queue: VecDeque<(WantsToMove, bool)> = moves
+        .into_iter()
+        .map(|(_, want_move)| (want_move, false))
+        .collect();
+    while let Some((want_move, waited)) = queue.pop_front() {
+        let mover = want_move.entity;
+        let mut destination = want_move.destination;
+        if moved.contains(&mover) || has_effect(ecs, mover, EffectKind::Stun) {
+            continue;
+        }
+        let current = match position(ecs, mover) {
+            Some(current) => current,
+            None => continue,
+        };
+        let walking_on = unit_at(ecs, index, destination).is_some_and(|ally| {
+            allies(ecs, mover, ally) && pending.get(&ally).is_some_and(|to| *to != current)
+        });
+        if walking_on && !waited {
+            queue.push_back((want_move, true));
+            continue;
+        }
+        pending.remove(&mover);
+        match map.get_tile(destination) {
+            Some(TileType::DoorClosed) => map.set_tile(destination, TileType::DoorOpen),
+            Some(TileType::DoorLocked) if holds_key(ecs, mover) => {
+                map.set_tile(destination, TileType::DoorOpen)
+            }
+            _ => {}
+        }
+        if !map.can_enter_tile(destination) {
+            continue;
+        }
+
+        match unit_at(ecs, index, destination) {
+            Some(occupant) if occupant == mover => continue,
+            Some(ally)
+                if allies(ecs, mover, ally)
+                    && !moved.contains(&ally)
+                    && !has_effect(ecs, ally, EffectKind::Stun)
+                    && !is_immobile(ecs, ally) =>
+            {
+                index.move_entity(ally, destination, current);
+                index.move_entity(mover, current, destination);
+                commands.add_component(ally, current);
+                events.publish(GameEvent::UnitMoved {
+                    entity: ally,
+                    from: destination,
+                    to: current,
+                });
+                moved.insert(ally);
+            }
+            Some(_) => continue,
+            None => {
+                if has_effect(ecs, mover, EffectKind::Haste) {
+                    let dash = destination + (destination - current);
+                    if map.can_enter_tile(dash) && unit_at(ecs, index, dash).is_none() {
+                        destination = dash;
+                    }
+                }
+                index.move_entity(mover, current, destination);
+            }
+        }
+        commands.add_component(mover, destination);
+        events.publish(GameEvent::UnitMoved {
+            entity: mover,
+            from: current,
+            to: destination,
+        });
+        moved.insert(mover);
 
         if ecs
-            .entry_ref(want_move.entity)
+            .entry_ref(mover)
             .unwrap()
             .get_component::<Player>()
             .is_ok()
         {
-            camera.update(want_move.destination);
+            camera.update(destination);
         }
     }
-    commands.remove(*entity);
 }
 
-#[system]
-#[write_component(Point)]
-#[read_component(MovingRandomly)]
-#[read_component(Health)]
-#[read_component(Player)]
-pub fn random_move(ecs: &mut SubWorld, commands: &mut CommandBuffer) {
-    // TODO: This should be a resource, so we can make RNG more predictable
-    let mut rng = RandomNumberGenerator::new();
-    let mut movers = <(Entity, &Point, &MovingRandomly)>::query();
-    let mut positions = <(Entity, &Point, &Health)>::query();
-    let mut attacked = false;
-
-    let movers: Vec<(Entity, Point)> = movers
-        .iter_mut(ecs)
-        .map(|(entity, pos, _)| {
-            let destination = match rng.range(0, 4) {
-                0 => Point::new(-1, 0),
-                1 => Point::new(1, 0),
-                2 => Point::new(0, -1),
-                _ => Point::new(0, 1),
-            } + *pos;
-            (*entity, destination)
-        })
-        .collect();
-
-    for (entity, destination) in movers {
-        positions
-            .iter(ecs)
-            .filter(|(_, target_pos, _)| **target_pos == destination)
-            .for_each(|(victim, _, _)| {
-                if ecs
-                    .entry_ref(*victim)
-                    .unwrap()
-                    .get_component::<Player>()
-                    .is_ok()
-                {
-                    commands.push((
-                        (),
-                        WantsToAttack {
-                            attacker: entity,
-                            victim: *victim,
-                        },
-                    ));
-                    attacked = true;
-                }
-            });
-
-        if !attacked {
-            commands.push((
-                (),
-                WantsToMove {
-                    entity,
-                    destination,
-                },
-            ));
-        }
-    }
-}
+/// An attack hits when d20 + attack reaches this plus the victim's defense
+pub const TO_HIT: i32 = 10;
 
 #[system]
 #[read_component(WantsToAttack)]
+#[read_component(CombatStats)]
+#[read_component(Equipped)]
+#[read_component(Weapon)]
+#[read_component(Armour)]
+#[read_component(InflictsEffect)]
+#[write_component(StatusEffects)]
+#[read_component(ExperienceValue)]
+#[write_component(Experience)]
 #[write_component(Health)]
-pub fn combat(ecs: &mut SubWorld, commands: &mut CommandBuffer) {
+#[read_component(Player)]
+#[read_component(Name)]
+#[read_component(Point)]
+pub fn combat(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] events: &mut EventChannel,
+    #[resource] index: &mut SpatialIndex,
+    #[resource] rng: &mut GameRng,
+) {
     let mut attacks = <(Entity, &WantsToAttack)>::query();
-    let victims: Vec<(Entity, Entity)> = attacks
+    let attacks: Vec<(Entity, Entity, Entity)> = attacks
         .iter(ecs)
-        .map(|(entity, attack)| (*entity, attack.victim))
+        .map(|(entity, attack)| (*entity, attack.attacker, attack.victim))
         .collect();
-    victims.iter().for_each(|(message, victim)| {
-        if let Ok(mut health) = ecs
+    attacks.iter().for_each(|(message, attacker, victim)| {
+        commands.remove(*message);
+        if has_effect(ecs, *attacker, EffectKind::Stun) {
+            return;
+        }
+        let (attack, damage) = attack_profile(ecs, *attacker, EquipmentSlot::Melee);
+        let defense = total_defense(ecs, *victim);
+        if rng.roll_dice(1, 20) + attack < TO_HIT + defense {
+            events.publish(GameEvent::AttackResolved {
+                attacker: *attacker,
+                victim: *victim,
+                hit: false,
+                damage: 0,
+            });
+            return;
+        }
+        let damage = damage.roll(rng).max(1);
+        let mut killed = false;
+        if let Ok(health) = ecs
             .entry_mut(*victim)
             .unwrap()
             .get_component_mut::<Health>()
         {
-            println!("Health before attack: {}", health.current);
-            health.current -= 1;
-            if health.current < 1 {
-                commands.remove(*victim);
-            }
-            println!("Health after attack: {}", health.current);
+            // A victim already killed by an earlier attack this turn can't die again
+            killed = health.current >= 1 && health.current - damage < 1;
+            health.current -= damage;
+        }
+        events.publish(GameEvent::AttackResolved {
+            attacker: *attacker,
+            victim: *victim,
+            hit: true,
+            damage,
+        });
+        if killed {
+            kill(ecs, commands, events, index, *victim, Some(*attacker));
+            award_experience(ecs, *attacker, *victim);
+        }
+        if let Some(effect) = inflicted_effect(ecs, *attacker, EquipmentSlot::Melee) {
+            apply_effect(ecs, *victim, effect);
         }
-        commands.remove(*message);
     });
 }
@@ -0,0 +1,50 @@
+use crate::prelude::*;
+
+/// Turns the player's input into orders for their units in a skirmish. The
+/// control group and formation keys go to `ControlGroups`; clicking one of
+/// their own units picks out everything on that tile, and clicking anywhere
+/// else sends the picked out units there in formation.
+#[system]
+#[read_component(UnitId)]
+#[read_component(Faction)]
+#[read_component(Point)]
+#[allow(clippy::too_many_arguments)]
+pub fn command_units(
+    ecs: &SubWorld,
+    #[resource] key: &Option<VirtualKeyCode>,
+    #[resource] modifiers: &KeyModifiers,
+    #[resource] keybindings: &Keybindings,
+    #[resource] click: &MouseClick,
+    #[resource] camera: &Camera,
+    #[resource] player: &PlayerFaction,
+    #[resource] groups: &mut ControlGroups,
+    #[resource] orders: &mut PendingOrders,
+) {
+    let own: Vec<(UnitId, Point)> = <(&UnitId, &Faction, &Point)>::query()
+        .iter(ecs)
+        .filter(|(_, faction, _)| **faction == player.0)
+        .map(|(id, _, pos)| (*id, *pos))
+        .collect();
+    groups.retain(|id| own.iter().any(|(unit, _)| *unit == id));
+
+    if let Some(action) = keybindings.action(*key, *modifiers) {
+        if groups.handle(action) {
+            return;
+        }
+    }
+
+    let target = match click.0 {
+        Some(clicked) => clicked + camera.top_left_corner(),
+        None => return,
+    };
+    let picked: Vec<UnitId> = own
+        .iter()
+        .filter(|(_, pos)| *pos == target)
+        .map(|(id, _)| *id)
+        .collect();
+    if !picked.is_empty() {
+        groups.select(picked);
+    } else if let Some(order) = groups.move_to(player.0, target) {
+        orders.0.push(order);
+    }
+}
@@ -0,0 +1,17 @@
+use crate::prelude::*;
+
+#[system(for_each)]
+pub fn close_door(
+    entity: &Entity,
+    wants_close: &WantsToCloseDoor,
+    #[resource] map: &mut Map,
+    #[resource] index: &SpatialIndex,
+    commands: &mut CommandBuffer,
+) {
+    let door = wants_close.door;
+    let is_blocked = !index.at(door).is_empty();
+    if map.get_tile(door) == Some(TileType::DoorOpen) && !is_blocked {
+        map.set_tile(door, TileType::DoorClosed);
+    }
+    commands.remove(*entity);
+}
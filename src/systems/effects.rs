@@ -0,0 +1,82 @@
+use crate::prelude::*;
+use crate::systems::{equipped_in, kill};
+
+/// Whether `entity` is currently under an effect of `kind`
+pub fn has_effect(ecs: &SubWorld, entity: Entity, kind: EffectKind) -> bool {
+    effect_potency(ecs, entity, kind) > 0
+}
+
+pub fn effect_potency(ecs: &SubWorld, entity: Entity, kind: EffectKind) -> i32 {
+    ecs.entry_ref(entity)
+        .ok()
+        .and_then(|entry| {
+            entry
+                .get_component::<StatusEffects>()
+                .ok()
+                .map(|effects| effects.potency(kind))
+        })
+        .unwrap_or(0)
+}
+
+/// Puts `effect` on `entity` if it is a unit that can carry effects
+pub fn apply_effect(ecs: &mut SubWorld, entity: Entity, effect: StatusEffect) {
+    if let Ok(mut entry) = ecs.entry_mut(entity) {
+        if let Ok(effects) = entry.get_component_mut::<StatusEffects>() {
+            effects.apply(effect);
+        }
+    }
+}
+
+/// The effect a hit from `attacker` puts on its victim: the one on the weapon
+/// in `slot`, otherwise the attacker's own
+pub fn inflicted_effect(
+    ecs: &SubWorld,
+    attacker: Entity,
+    slot: EquipmentSlot,
+) -> Option<StatusEffect> {
+    let inflicts = |entity: Entity| {
+        ecs.entry_ref(entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<InflictsEffect>().ok().copied())
+    };
+    equipped_in(ecs, attacker, slot)
+        .and_then(inflicts)
+        .or_else(|| inflicts(attacker))
+        .map(|inflicts| inflicts.0)
+}
+
+/// Applies poison and regeneration, then counts every effect down a turn. Runs
+/// at the end of each monster turn so a full round has passed.
+#[system]
+#[write_component(StatusEffects)]
+#[write_component(Health)]
+#[read_component(Player)]
+#[read_component(Name)]
+#[read_component(Point)]
+pub fn tick_status_effects(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] events: &mut EventChannel,
+    #[resource] index: &mut SpatialIndex,
+) {
+    let mut dead = Vec::new();
+    <(Entity, &mut StatusEffects, &mut Health)>::query().for_each_mut(
+        ecs,
+        |(entity, effects, health)| {
+            // A dead player stays in the world, and can't heal or die again
+            if health.current < 1 {
+                return;
+            }
+            health.current += effects.potency(EffectKind::Regeneration);
+            health.current = health.current.min(health.max);
+            health.current -= effects.potency(EffectKind::Poison);
+            if health.current < 1 {
+                dead.push(*entity);
+            }
+            effects.tick();
+        },
+    );
+    for entity in dead {
+        kill(ecs, commands, events, index, entity, None);
+    }
+}
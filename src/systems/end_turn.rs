@@ -1,11 +1,26 @@
 use crate::prelude::*;
 
 #[system]
-pub fn end_turn(#[resource] turn_state: &mut TurnState) {
-    let new_state = match turn_state {
-        TurnState::AwaitingInput => return,
+pub fn end_turn(#[resource] turn_state: &mut TurnState, #[resource] events: &mut EventChannel) {
+    let mut new_state = match turn_state {
+        TurnState::AwaitingInput | TurnState::GameOver => return,
         TurnState::PlayerTurn => TurnState::MonsterTurn,
-        TurnState::MonsterTurn => TurnState::AwaitingInput,
+        TurnState::MonsterTurn => {
+            events.publish(GameEvent::TurnEnded);
+            TurnState::AwaitingInput
+        }
     };
+    let player_died = events.iter().any(|event| {
+        matches!(
+            event,
+            GameEvent::UnitDied {
+                was_player: true,
+                ..
+            }
+        )
+    });
+    if player_died {
+        new_state = TurnState::GameOver;
+    }
     *turn_state = new_state;
 }
@@ -0,0 +1,84 @@
+use crate::prelude::*;
+use crate::systems::effect_potency;
+
+/// The item `owner` has equipped in `slot`, if any
+pub fn equipped_in(ecs: &SubWorld, owner: Entity, slot: EquipmentSlot) -> Option<Entity> {
+    <(Entity, &Equipped)>::query()
+        .iter(ecs)
+        .find(|(_, equipped)| equipped.owner == owner && equipped.slot == slot)
+        .map(|(entity, _)| *entity)
+}
+
+fn base_stats(ecs: &SubWorld, entity: Entity) -> CombatStats {
+    ecs.entry_ref(entity)
+        .ok()
+        .and_then(|entry| entry.get_component::<CombatStats>().ok().copied())
+        .unwrap_or_default()
+}
+
+/// To-hit bonus and damage dice for an attack made with whatever is in `slot`,
+/// falling back to the unit's unarmed stats. Weakness lowers the damage.
+pub fn attack_profile(ecs: &SubWorld, entity: Entity, slot: EquipmentSlot) -> (i32, Dice) {
+    let base = base_stats(ecs, entity);
+    let weapon = equipped_in(ecs, entity, slot).and_then(|item| {
+        ecs.entry_ref(item)
+            .ok()
+            .and_then(|entry| entry.get_component::<Weapon>().ok().copied())
+    });
+    let (attack, mut damage) = match weapon {
+        Some(weapon) => (base.attack + weapon.attack, weapon.damage),
+        None => (base.attack, base.damage),
+    };
+    damage.bonus -= effect_potency(ecs, entity, EffectKind::Weakness);
+    (attack, damage)
+}
+
+/// Base defense plus the defense of every piece of armour worn
+pub fn total_defense(ecs: &SubWorld, entity: Entity) -> i32 {
+    let armour: i32 = <(&Equipped, &Armour)>::query()
+        .iter(ecs)
+        .filter(|(equipped, _)| equipped.owner == entity)
+        .map(|(_, armour)| armour.defense)
+        .sum();
+    base_stats(ecs, entity).defense + armour
+}
+
+/// Equips items into their slot, taking off whatever was there. Units can only
+/// equip items they carry.
+#[system]
+#[read_component(WantsToEquip)]
+#[read_component(Equippable)]
+#[read_component(Equipped)]
+#[read_component(Carried)]
+pub fn equip(ecs: &SubWorld, commands: &mut CommandBuffer) {
+    for (message, WantsToEquip { entity, item }) in <(Entity, &WantsToEquip)>::query().iter(ecs) {
+        let slot = ecs
+            .entry_ref(*item)
+            .ok()
+            .filter(|entry| {
+                entry
+                    .get_component::<Carried>()
+                    .is_ok_and(|carried| carried.owner == *entity)
+            })
+            .and_then(|entry| entry.get_component::<Equippable>().ok().copied());
+        if let Some(Equippable { slot }) = slot {
+            if let Some(previous) = equipped_in(ecs, *entity, slot) {
+                commands.remove_component::<Equipped>(previous);
+            }
+            commands.add_component(
+                *item,
+                Equipped {
+                    owner: *entity,
+                    slot,
+                },
+            );
+        }
+        commands.remove(*message);
+    }
+}
+
+#[system(for_each)]
+pub fn unequip(entity: &Entity, wants_unequip: &WantsToUnequip, commands: &mut CommandBuffer) {
+    commands.remove_component::<Equipped>(wants_unequip.item);
+    commands.remove(*entity);
+}
@@ -0,0 +1,223 @@
+use crate::prelude::*;
+
+fn is_player(ecs: &SubWorld, entity: Entity) -> bool {
+    ecs.entry_ref(entity)
+        .is_ok_and(|entry| entry.get_component::<Player>().is_ok())
+}
+
+fn name_of(ecs: &SubWorld, entity: Entity) -> Option<String> {
+    ecs.entry_ref(entity).ok().and_then(|entry| {
+        entry
+            .get_component::<Name>()
+            .ok()
+            .map(|name| name.0.clone())
+    })
+}
+
+/// The event for `entity` dying, captured while it still exists so consumers
+/// can describe it after it has been removed
+pub fn unit_died(ecs: &SubWorld, entity: Entity, killer: Option<Entity>) -> GameEvent {
+    GameEvent::UnitDied {
+        entity,
+        killer,
+        name: name_of(ecs, entity),
+        at: ecs
+            .entry_ref(entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<Point>().ok().copied()),
+        was_player: is_player(ecs, entity),
+    }
+}
+
+/// Publishes the death of `entity` and removes it from the world and the
+/// spatial index. A dead player is left in the world for the game over screen,
+/// which `end_turn` switches to.
+pub fn kill(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    events: &mut EventChannel,
+    index: &mut SpatialIndex,
+    entity: Entity,
+    killer: Option<Entity>,
+) {
+    let died = unit_died(ecs, entity, killer);
+    if let GameEvent::UnitDied { at: Some(at), .. } = died {
+        index.remove(entity, at);
+    }
+    if !is_player(ecs, entity) {
+        commands.remove(entity);
+    }
+    events.publish(died);
+}
+
+/// Empties the event channel once this schedule's consumers have read it
+#[system]
+pub fn clear_events(#[resource] events: &mut EventChannel) {
+    events.clear();
+}
+
+fn describe(ecs: &SubWorld, entity: Entity) -> String {
+    if is_player(ecs, entity) {
+        "you".to_string()
+    } else {
+        name_of(ecs, entity).unwrap_or_else(|| "something".to_string())
+    }
+}
+
+fn capitalise(text: String) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => text,
+    }
+}
+
+/// Turns combat, deaths, pickups and level changes into lines for the log
+#[system]
+#[read_component(Player)]
+#[read_component(Name)]
+pub fn message_log(
+    ecs: &SubWorld,
+    #[resource] events: &EventChannel,
+    #[resource] log: &mut MessageLog,
+) {
+    for event in events.iter() {
+        let (text, color) = match event {
+            // A unit killed by the attack is described by its death instead
+            GameEvent::AttackResolved { victim, .. } if ecs.entry_ref(*victim).is_err() => continue,
+            GameEvent::AttackResolved {
+                attacker,
+                victim,
+                hit,
+                damage,
+            } => {
+                let player_attacked = is_player(ecs, *attacker);
+                let verb = match (hit, player_attacked) {
+                    (true, true) => format!("hit {} for {}", describe(ecs, *victim), damage),
+                    (true, false) => format!("hits {} for {}", describe(ecs, *victim), damage),
+                    (false, true) => format!("miss {}", describe(ecs, *victim)),
+                    (false, false) => format!("misses {}", describe(ecs, *victim)),
+                };
+                let color = if is_player(ecs, *victim) && *hit {
+                    RED
+                } else {
+                    WHITE
+                };
+                (format!("{} {}.", describe(ecs, *attacker), verb), color)
+            }
+            GameEvent::UnitDied {
+                was_player: true, ..
+            } => ("You die...".to_string(), RED),
+            GameEvent::UnitDied { killer, name, .. } => {
+                let name = name.clone().unwrap_or_else(|| "something".to_string());
+                match killer {
+                    Some(killer) if is_player(ecs, *killer) => {
+                        (format!("You kill {}.", name), GOLD)
+                    }
+                    _ => (format!("{} dies.", name), GREY),
+                }
+            }
+            GameEvent::ItemPickedUp { entity, item } if is_player(ecs, *entity) => {
+                (format!("You pick up {}.", describe(ecs, *item)), CYAN)
+            }
+            GameEvent::LevelEntered { depth } => (format!("You enter depth {}.", depth), YELLOW),
+            _ => continue,
+        };
+        log.add(capitalise(text), color);
+    }
+}
+
+/// Keeps the running totals shown at the bottom of the screen
+#[system]
+#[read_component(Player)]
+pub fn statistics(
+    ecs: &SubWorld,
+    #[resource] events: &EventChannel,
+    #[resource] stats: &mut Statistics,
+) {
+    for event in events.iter() {
+        match event {
+            GameEvent::UnitMoved { entity, .. } if is_player(ecs, *entity) => {
+                stats.tiles_walked += 1
+            }
+            GameEvent::AttackResolved {
+                attacker,
+                victim,
+                hit: true,
+                damage,
+            } => {
+                if is_player(ecs, *attacker) {
+                    stats.damage_dealt += damage;
+                }
+                if is_player(ecs, *victim) {
+                    stats.damage_taken += damage;
+                }
+            }
+            GameEvent::UnitDied {
+                killer: Some(killer),
+                ..
+            } if is_player(ecs, *killer) => stats.kills += 1,
+            GameEvent::ItemPickedUp { entity, .. } if is_player(ecs, *entity) => {
+                stats.items_picked_up += 1
+            }
+            GameEvent::LevelEntered { depth } => {
+                stats.deepest_level = stats.deepest_level.max(*depth)
+            }
+            GameEvent::TurnEnded => stats.turns += 1,
+            _ => (),
+        }
+    }
+}
+
+/// Unlocks the player's achievements, announcing each one in the log
+#[system]
+#[read_component(Player)]
+pub fn achievements(
+    ecs: &SubWorld,
+    #[resource] events: &EventChannel,
+    #[resource] achievements: &mut Achievements,
+    #[resource] log: &mut MessageLog,
+) {
+    for event in events.iter() {
+        let reached = match event {
+            GameEvent::UnitDied {
+                killer: Some(killer),
+                ..
+            } if is_player(ecs, *killer) => {
+                achievements.kills += 1;
+                if achievements.kills >= Achievements::SLAYER_KILLS {
+                    Some(Achievement::Slayer)
+                } else {
+                    Some(Achievement::FirstBlood)
+                }
+            }
+            GameEvent::ItemPickedUp { entity, .. } if is_player(ecs, *entity) => {
+                achievements.items_picked_up += 1;
+                Some(Achievement::Hoarder)
+                    .filter(|_| achievements.items_picked_up >= Achievements::HOARDER_ITEMS)
+            }
+            GameEvent::LevelEntered { depth } if *depth >= Achievements::DEEP_DELVER_DEPTH => {
+                Some(Achievement::DeepDelver)
+            }
+            _ => None,
+        };
+        if let Some(achievement) = reached {
+            if achievements.unlock(achievement) {
+                log.add(
+                    format!("Achievement unlocked: {}!", achievement.describe()),
+                    MAGENTA,
+                );
+            }
+        }
+    }
+}
+
+/// Counts ended turns for the replay recorder
+#[system]
+pub fn replay_turns(#[resource] events: &EventChannel, #[resource] turns: &mut ReplayTurns) {
+    let ended = events
+        .iter()
+        .filter(|event| matches!(event, GameEvent::TurnEnded))
+        .count();
+    turns.0 += ended as u32;
+}
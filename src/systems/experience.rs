@@ -0,0 +1,102 @@
+use crate::prelude::*;
+
+/// What the player can pick each time they gain a level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LevelUpReward {
+    Vitality,
+    Accuracy,
+    Toughness,
+    Venom,
+}
+
+impl LevelUpReward {
+    pub const ALL: [LevelUpReward; 4] = [
+        LevelUpReward::Vitality,
+        LevelUpReward::Accuracy,
+        LevelUpReward::Toughness,
+        LevelUpReward::Venom,
+    ];
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            LevelUpReward::Vitality => "Vitality: +5 max health and heal fully",
+            LevelUpReward::Accuracy => "Accuracy: +1 to hit",
+            LevelUpReward::Toughness => "Toughness: +1 defense",
+            LevelUpReward::Venom => "Venom: your hits poison their target",
+        }
+    }
+}
+
+/// Gives `killer` the experience `victim` is worth, if the killer gathers any
+pub fn award_experience(ecs: &mut SubWorld, killer: Entity, victim: Entity) {
+    let value = ecs
+        .entry_ref(victim)
+        .ok()
+        .and_then(|entry| entry.get_component::<ExperienceValue>().ok().copied());
+    if let (Some(ExperienceValue(value)), Ok(mut entry)) = (value, ecs.entry_mut(killer)) {
+        if let Ok(experience) = entry.get_component_mut::<Experience>() {
+            experience.gain(value);
+        }
+    }
+}
+
+/// Shows the level-up choices while the player has levels to spend. Every key
+/// is consumed until a reward is picked.
+#[system]
+#[read_component(Player)]
+#[write_component(Experience)]
+#[write_component(Health)]
+#[write_component(CombatStats)]
+pub fn level_up(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] key: &mut Option<VirtualKeyCode>,
+    #[resource] modifiers: &KeyModifiers,
+) {
+    let player = match <(Entity, &Experience)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .find(|(_, experience)| experience.unspent > 0)
+    {
+        Some((player, _)) => *player,
+        None => return,
+    };
+    // Held modifiers leave the number keys to the control group bindings
+    let pressed = key.take().filter(|_| *modifiers == KeyModifiers::default());
+    let reward = match pressed {
+        Some(VirtualKeyCode::Key1) => LevelUpReward::ALL[0],
+        Some(VirtualKeyCode::Key2) => LevelUpReward::ALL[1],
+        Some(VirtualKeyCode::Key3) => LevelUpReward::ALL[2],
+        Some(VirtualKeyCode::Key4) => LevelUpReward::ALL[3],
+        _ => return,
+    };
+
+    let mut entry = ecs.entry_mut(player).unwrap();
+    match reward {
+        LevelUpReward::Vitality => {
+            if let Ok(health) = entry.get_component_mut::<Health>() {
+                health.max += 5;
+                health.current = health.max;
+            }
+        }
+        LevelUpReward::Accuracy => {
+            if let Ok(stats) = entry.get_component_mut::<CombatStats>() {
+                stats.attack += 1;
+            }
+        }
+        LevelUpReward::Toughness => {
+            if let Ok(stats) = entry.get_component_mut::<CombatStats>() {
+                stats.defense += 1;
+            }
+        }
+        LevelUpReward::Venom => {
+            commands.add_component(
+                player,
+                InflictsEffect(StatusEffect::new(EffectKind::Poison, 3, 1)),
+            );
+        }
+    }
+    if let Ok(experience) = entry.get_component_mut::<Experience>() {
+        experience.unspent -= 1;
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::HashSet;
+
+use crate::prelude::*;
+use crate::systems::{apply_effect, award_experience, kill};
+
+/// The items `owner` is carrying, sorted by name so hotkeys stay stable
+pub fn carried_items(ecs: &SubWorld, owner: Entity) -> Vec<(Entity, String)> {
+    let mut items: Vec<(Entity, String)> = <(Entity, &Carried, &Name)>::query()
+        .filter(component::<Item>())
+        .iter(ecs)
+        .filter(|(_, carried, _)| carried.owner == owner)
+        .map(|(entity, _, name)| (*entity, name.0.clone()))
+        .collect();
+    items.sort_by(|a, b| a.1.cmp(&b.1));
+    items
+}
+
+/// Whether `item` is in `owner`'s inventory
+fn carries(ecs: &SubWorld, owner: Entity, item: Entity) -> bool {
+    ecs.entry_ref(item).is_ok_and(|entry| {
+        entry
+            .get_component::<Carried>()
+            .is_ok_and(|carried| carried.owner == owner)
+    })
+}
+
+fn hotkey_index(key: VirtualKeyCode) -> Option<usize> {
+    use VirtualKeyCode::*;
+    [Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9]
+        .iter()
+        .position(|hotkey| *hotkey == key)
+}
+
+/// Opens and closes the inventory panel and turns a hotkey into a use or drop
+/// request. Any key it handles is consumed so `player_input` never sees it.
+#[system]
+#[read_component(Player)]
+#[read_component(Item)]
+#[read_component(Carried)]
+#[read_component(Name)]
+#[read_component(Equippable)]
+#[read_component(Equipped)]
+pub fn inventory(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] key: &mut Option<VirtualKeyCode>,
+    #[resource] modifiers: &KeyModifiers,
+    #[resource] keybindings: &Keybindings,
+    #[resource] menu: &mut InventoryMenu,
+    #[resource] turn_state: &mut TurnState,
+) {
+    let pressed = match *key {
+        Some(pressed) => pressed,
+        None => return,
+    };
+    let player = match <Entity>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(player) => *player,
+        None => return,
+    };
+
+    let action = keybindings.action_for(KeyChord::new(pressed, *modifiers));
+    match (*menu, action) {
+        (InventoryMenu::Closed, Some(Action::Inventory)) => *menu = InventoryMenu::Use,
+        (InventoryMenu::Closed, Some(Action::Drop)) => *menu = InventoryMenu::Drop,
+        (InventoryMenu::Closed, _) => return,
+        (_, Some(Action::Cancel)) => *menu = InventoryMenu::Closed,
+        (mode, _) => {
+            let item = hotkey_index(pressed)
+                .filter(|_| *modifiers == KeyModifiers::default())
+                .and_then(|index| carried_items(ecs, player).get(index).map(|(item, _)| *item));
+            if let Some(item) = item {
+                let entry = ecs.entry_ref(item).unwrap();
+                let equippable = entry.get_component::<Equippable>().is_ok();
+                let equipped = entry.get_component::<Equipped>().is_ok();
+                match mode {
+                    InventoryMenu::Use if equipped => {
+                        commands.push((
+                            (),
+                            WantsToUnequip {
+                                entity: player,
+                                item,
+                            },
+                        ));
+                    }
+                    InventoryMenu::Use if equippable => {
+                        commands.push((
+                            (),
+                            WantsToEquip {
+                                entity: player,
+                                item,
+                            },
+                        ));
+                    }
+                    InventoryMenu::Use => {
+                        commands.push(((), WantsToUseItem { user: player, item }));
+                    }
+                    _ => {
+                        commands.push((
+                            (),
+                            WantsToDrop {
+                                entity: player,
+                                item,
+                            },
+                        ));
+                    }
+                }
+                *menu = InventoryMenu::Closed;
+                *turn_state = TurnState::PlayerTurn;
+            }
+        }
+    }
+    *key = None;
+}
+
+#[system]
+#[read_component(WantsToUseItem)]
+#[read_component(ProvidesHealing)]
+#[read_component(ProvidesNutrition)]
+#[write_component(Hunger)]
+#[read_component(DamagesNearby)]
+#[read_component(GrantsEffect)]
+#[read_component(InflictsEffect)]
+#[write_component(StatusEffects)]
+#[read_component(ExperienceValue)]
+#[write_component(Experience)]
+#[read_component(Consumable)]
+#[read_component(Point)]
+#[read_component(Enemy)]
+#[write_component(Health)]
+#[read_component(Player)]
+#[read_component(Name)]
+#[read_component(Carried)]
+pub fn use_items(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] index: &mut SpatialIndex,
+    #[resource] events: &mut EventChannel,
+) {
+    let requests: Vec<(Entity, WantsToUseItem)> = <(Entity, &WantsToUseItem)>::query()
+        .iter(ecs)
+        .map(|(entity, request)| (*entity, *request))
+        .collect();
+
+    for (message, WantsToUseItem { user, item }) in requests {
+        commands.remove(message);
+        // Units can only use items they carry
+        if !carries(ecs, user, item) {
+            continue;
+        }
+        let (healing, blast, consumable) = match ecs.entry_ref(item) {
+            Ok(entry) => (
+                entry.get_component::<ProvidesHealing>().ok().copied(),
+                entry.get_component::<DamagesNearby>().ok().copied(),
+                entry.get_component::<Consumable>().is_ok(),
+            ),
+            Err(_) => (None, None, false),
+        };
+        let (granted, inflicted) = match ecs.entry_ref(item) {
+            Ok(entry) => (
+                entry.get_component::<GrantsEffect>().ok().copied(),
+                entry.get_component::<InflictsEffect>().ok().copied(),
+            ),
+            Err(_) => (None, None),
+        };
+
+        let nutrition = ecs
+            .entry_ref(item)
+            .ok()
+            .and_then(|entry| entry.get_component::<ProvidesNutrition>().ok().copied());
+        if let Some(ProvidesNutrition { amount }) = nutrition {
+            if let Ok(hunger) = ecs.entry_mut(user).unwrap().get_component_mut::<Hunger>() {
+                hunger.satiety = (hunger.satiety.max(0) + amount).min(hunger.max);
+            }
+        }
+
+        if let Some(GrantsEffect(effect)) = granted {
+            apply_effect(ecs, user, effect);
+        }
+
+        if let Some(ProvidesHealing { amount }) = healing {
+            if let Ok(health) = ecs.entry_mut(user).unwrap().get_component_mut::<Health>() {
+                health.current = (health.current + amount).min(health.max);
+            }
+        }
+
+        if let Some(DamagesNearby { damage, radius }) = blast {
+            let origin = ecs
+                .entry_ref(user)
+                .ok()
+                .and_then(|entry| entry.get_component::<Point>().ok().copied());
+            if let Some(origin) = origin {
+                let victims: Vec<(Entity, Point)> = index
+                    .in_radius(origin, radius as f32)
+                    .filter(|(entity, _)| {
+                        ecs.entry_ref(*entity)
+                            .is_ok_and(|entry| entry.get_component::<Enemy>().is_ok())
+                    })
+                    .collect();
+                for (victim, _) in victims {
+                    if let Some(InflictsEffect(effect)) = inflicted {
+                        apply_effect(ecs, victim, effect);
+                    }
+                    let mut killed = false;
+                    if let Ok(health) = ecs.entry_mut(victim).unwrap().get_component_mut::<Health>()
+                    {
+                        // A victim already killed this turn can't die again
+                        killed = health.current >= 1 && health.current - damage < 1;
+                        health.current -= damage;
+                    }
+                    if damage > 0 {
+                        events.publish(GameEvent::AttackResolved {
+                            attacker: user,
+                            victim,
+                            hit: true,
+                            damage,
+                        });
+                    }
+                    if killed {
+                        kill(ecs, commands, events, index, victim, Some(user));
+                        award_experience(ecs, user, victim);
+                    }
+                }
+            }
+        }
+
+        if consumable {
+            commands.remove(item);
+        }
+    }
+}
+
+/// Puts items lying on the floor into the inventory of whoever stands on them
+/// and asked for them. When several units reach for the same item in one turn
+/// it goes to whoever asked first.
+#[system]
+#[read_component(WantsToPickUp)]
+#[read_component(Item)]
+#[read_component(Carried)]
+#[read_component(Point)]
+pub fn pick_up(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] events: &mut EventChannel,
+    #[resource] index: &mut SpatialIndex,
+) {
+    let position = |entity: Entity| {
+        ecs.entry_ref(entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<Point>().ok().copied())
+    };
+    let on_floor = |item: Entity| {
+        ecs.entry_ref(item).is_ok_and(|entry| {
+            entry.get_component::<Item>().is_ok() && entry.get_component::<Carried>().is_err()
+        })
+    };
+    // Items already picked up this turn
+    let mut taken: HashSet<Entity> = HashSet::new();
+
+    for (message, wants_pick_up) in <(Entity, &WantsToPickUp)>::query().iter(ecs) {
+        commands.remove(*message);
+        let WantsToPickUp {
+            entity: owner,
+            item,
+        } = *wants_pick_up;
+        let at = match position(item) {
+            Some(at) if position(owner) == Some(at) => at,
+            _ => continue,
+        };
+        if !on_floor(item) || !taken.insert(item) {
+            continue;
+        }
+        index.remove(item, at);
+        commands.remove_component::<Point>(item);
+        commands.add_component(item, Carried { owner });
+        events.publish(GameEvent::ItemPickedUp {
+            entity: owner,
+            item,
+        });
+    }
+}
+
+/// Leaves items on the tile of the unit dropping them. Units can only drop
+/// items they carry.
+#[system(for_each)]
+#[read_component(Point)]
+#[read_component(Carried)]
+pub fn drop_items(
+    entity: &Entity,
+    wants_drop: &WantsToDrop,
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] index: &mut SpatialIndex,
+) {
+    let pos = ecs
+        .entry_ref(wants_drop.entity)
+        .ok()
+        .and_then(|entry| entry.get_component::<Point>().ok().copied());
+    if let Some(pos) = pos.filter(|_| carries(ecs, wants_drop.entity, wants_drop.item)) {
+        commands.remove_component::<Carried>(wants_drop.item);
+        commands.remove_component::<Equipped>(wants_drop.item);
+        commands.add_component(wants_drop.item, pos);
+        index.insert(wants_drop.item, pos);
+    }
+    commands.remove(*entity);
+}
+
+/// Leaves whatever a unit that died was carrying on the tile it died on
+#[system]
+#[read_component(Carried)]
+pub fn drop_loot(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] events: &EventChannel,
+    #[resource] index: &mut SpatialIndex,
+) {
+    for event in events.iter() {
+        if let GameEvent::UnitDied {
+            entity,
+            at: Some(at),
+            ..
+        } = event
+        {
+            <(Entity, &Carried)>::query()
+                .iter(ecs)
+                .filter(|(_, carried)| carried.owner == *entity)
+                .for_each(|(item, _)| {
+                    commands.remove_component::<Carried>(*item);
+                    commands.remove_component::<Equipped>(*item);
+                    commands.add_component(*item, *at);
+                    index.insert(*item, *at);
+                });
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use crate::prelude::*;
+
+#[system(for_each)]
+#[read_component(Point)]
+pub fn take_stairs(
+    entity: &Entity,
+    wants_stairs: &WantsToTakeStairs,
+    #[resource] map: &Map,
+    #[resource] depth: &Depth,
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+) {
+    commands.remove(*entity);
+
+    let WantsToTakeStairs { entity, direction } = *wants_stairs;
+    let standing_on = ecs
+        .entry_ref(entity)
+        .ok()
+        .and_then(|entry| entry.get_component::<Point>().ok().copied())
+        .and_then(|pos| map.get_tile(pos));
+    if standing_on != Some(direction.tile()) {
+        return;
+    }
+
+    let target = depth.0 + direction.depth_change();
+    if target < Depth::SURFACE {
+        return;
+    }
+    commands.exec_mut(move |world, resources| change_level(world, resources, target, direction));
+}
+
+/// Stashes the current level with every entity on it except the players and
+/// what they carry, then either restores the target level or hands over to the
+/// world generator to build it. Items other units carry stay with their owners.
+fn change_level(
+    world: &mut World,
+    resources: &mut Resources,
+    target: i32,
+    direction: StairDirection,
+) {
+    let current = resources
+        .get::<Depth>()
+        .map(|d| d.0)
+        .unwrap_or(Depth::SURFACE);
+    let map = match resources.remove::<Map>() {
+        Some(map) => map,
+        None => return,
+    };
+
+    let theme = resources.get::<TileTheme>().map(|t| *t).unwrap_or_default();
+    let players: HashSet<Entity> = <Entity>::query()
+        .filter(component::<Player>())
+        .iter(world)
+        .copied()
+        .collect();
+    let taken: Vec<Entity> = <(Entity, &Carried)>::query()
+        .iter(world)
+        .filter(|(_, carried)| players.contains(&carried.owner))
+        .map(|(item, _)| *item)
+        .collect();
+    for item in taken.iter() {
+        if let Some(mut entry) = world.entry(*item) {
+            entry.add_component(TakenAlong);
+        }
+    }
+    let mut entities = World::default();
+    entities.move_from(
+        world,
+        &(!component::<Player>() & !component::<TakenAlong>()),
+    );
+    for item in taken {
+        if let Some(mut entry) = world.entry(item) {
+            entry.remove_component::<TakenAlong>();
+        }
+    }
+    let mut dungeon = resources.get_mut_or_default::<Dungeon>();
+    dungeon.store(
+        current,
+        StoredLevel {
+            map,
+            theme,
+            entities,
+        },
+    );
+    let restored = dungeon.take(target);
+    drop(dungeon);
+
+    resources.insert(Depth(target));
+    resources.insert(TurnState::AwaitingInput);
+    // Nothing on the new level heard what happened on the old one
+    resources.insert(Noises::default());
+
+    match restored {
+        Some(StoredLevel {
+            map,
+            theme,
+            mut entities,
+        }) => {
+            world.move_from(&mut entities, &any());
+            let arrival = map
+                .find_tile(direction.arrival_tile())
+                .or_else(|| map.first_open_tile())
+                .expect("Stored level has nowhere to stand");
+            for player in players {
+                if let Some(mut entry) = world.entry(player) {
+                    entry.add_component(arrival);
+                }
+            }
+            resources.insert(theme);
+            resources.insert(Camera::new(arrival));
+            resources.insert(SpatialIndex::build(&map, world));
+            resources.insert(map);
+            resources
+                .get_mut_or_default::<EventChannel>()
+                .publish(GameEvent::LevelEntered { depth: target });
+        }
+        None => {
+            let recipe = resources.get_or_default::<GeneratorRecipe>().clone();
+            let (builder, runner) = (recipe.builder(), recipe.runner());
+            if let Some(mut debugger) = resources.get_mut::<Option<GenerationDebugger>>() {
+                if debugger.is_some() {
+                    *debugger = Some(GenerationDebugger::new(&builder, &runner));
+                }
+            }
+            resources.insert(builder);
+            resources.insert(runner);
+            resources.insert(Some(ProgressBar::new(SCREEN_HEIGHT - ProgressBar::HEIGHT)));
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use crate::prelude::*;
+
+/// The main menu: Up and Down pick a recipe and Return starts a game with it.
+/// A recipe that failed to load can be highlighted to read why, but not chosen.
+#[system]
+pub fn choose_recipe(#[resource] key: &Option<VirtualKeyCode>, #[resource] menu: &mut RecipeMenu) {
+    let count = menu.recipes.len();
+    match key {
+        Some(VirtualKeyCode::Up) => menu.selected = (menu.selected + count - 1) % count,
+        Some(VirtualKeyCode::Down) => menu.selected = (menu.selected + 1) % count,
+        Some(VirtualKeyCode::Return) => {
+            if let (_, Ok(recipe)) = &menu.recipes[menu.selected] {
+                let mut recipe = recipe.clone();
+                recipe.seed = menu.seed.or(recipe.seed);
+                menu.chosen = Some(recipe);
+            }
+        }
+        _ => (),
+    }
+}
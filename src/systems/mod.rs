@@ -1,12 +1,55 @@
 mod camera;
+mod command;
+mod doors;
+mod effects;
 mod end_turn;
+mod equipment;
+mod events;
+mod experience;
+mod inventory;
+mod level;
+mod menu;
+mod monster_ai;
+mod orders;
+mod perception;
 mod player_input;
+mod ranged;
+mod rebind;
+mod rest;
+mod save;
 mod world_gen;
 
 pub mod characters;
 pub mod render;
 
 pub use camera::keep_camera_on_map_system;
+pub use command::command_units_system;
+pub use doors::close_door_system;
+pub use effects::{
+    apply_effect, effect_potency, has_effect, inflicted_effect, tick_status_effects_system,
+};
 pub use end_turn::end_turn_system;
+pub use equipment::{attack_profile, equip_system, equipped_in, total_defense, unequip_system};
+pub use events::{
+    achievements_system, clear_events_system, kill, message_log_system, replay_turns_system,
+    statistics_system, unit_died,
+};
+pub use experience::{award_experience, level_up_system, LevelUpReward};
+pub use inventory::{
+    carried_items, drop_items_system, drop_loot_system, inventory_system, pick_up_system,
+    use_items_system,
+};
+pub use level::take_stairs_system;
+pub use menu::choose_recipe_system;
+pub use monster_ai::monster_ai_system;
+pub use orders::{
+    apply_orders_system, first_step, gather_system, keep_formation_system, produce_system,
+    pursue_objectives_system, WORKER_LOAD,
+};
+pub use perception::{perception_system, record_noises_system, sees, SIGHT_RANGE};
 pub use player_input::player_input_system;
+pub use ranged::{can_shoot, projectiles_system, shoot_system, shooting_range, targeting_system};
+pub use rebind::rebind_keys_system;
+pub use rest::{metabolism_system, rest_system};
+pub use save::save_game_system;
 pub use world_gen::{finish_world_gen_system, world_gen_progress_system, world_gen_system};
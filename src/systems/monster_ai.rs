@@ -0,0 +1,298 @@
+use crate::prelude::*;
+use crate::systems::characters::{holds_key, unit_at};
+use crate::systems::{can_shoot, first_step, sees, shooting_range, SIGHT_RANGE};
+
+/// How far from where an enemy was last known a searching monster looks around
+const SEARCH_RADIUS: i32 = 3;
+
+/// What a monster does once one of its nodes has decided
+enum Intent {
+    Move(Point),
+    Attack(Entity),
+    Shoot(Point),
+    PickUp(Entity),
+    Call { allies: Vec<Entity>, enemy: Point },
+}
+
+/// What a monster knows while it works through its behaviour
+struct Situation<'a> {
+    ecs: &'a SubWorld<'a>,
+    map: &'a Map,
+    index: &'a SpatialIndex,
+    rules: &'a MovementRules,
+    entity: Entity,
+    pos: Point,
+    /// Whether it carries a key to path through locked doors with
+    can_unlock: bool,
+    health: Health,
+    awareness: Awareness,
+    /// The enemy it can see
+    enemy: Option<(Entity, Point)>,
+}
+
+fn has<T: legion::storage::Component>(ecs: &SubWorld, entity: Entity) -> bool {
+    ecs.entry_ref(entity)
+        .is_ok_and(|entry| entry.get_component::<T>().is_ok())
+}
+
+fn is_hunting(ecs: &SubWorld, entity: Entity) -> bool {
+    ecs.entry_ref(entity).is_ok_and(|entry| {
+        entry
+            .get_component::<Awareness>()
+            .is_ok_and(|awareness| awareness.state == AlertState::Hunting)
+    })
+}
+
+impl Situation<'_> {
+    fn adjacent(&self, target: Point) -> bool {
+        Direction::allowed(self.rules)
+            .iter()
+            .any(|direction| self.pos + direction.delta() == target)
+    }
+
+    /// The free neighbouring tile furthest from `threat`, if any is further away
+    /// than where the monster stands
+    fn step_away(&self, threat: Point) -> Option<Point> {
+        let distance = |point: Point| DistanceAlg::Pythagoras.distance2d(point, threat);
+        Direction::allowed(self.rules)
+            .iter()
+            .map(|direction| self.pos + direction.delta())
+            .filter(|step| {
+                self.map.can_enter_tile(*step) && unit_at(self.ecs, self.index, *step).is_none()
+            })
+            .filter(|step| distance(*step) > distance(self.pos))
+            .max_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+    }
+
+    fn step_towards(&self, target: Point) -> Option<Point> {
+        first_step(
+            &self.map.pathing(self.can_unlock, self.rules),
+            self.pos,
+            target,
+        )
+    }
+
+    fn decide(&self, node: BehaviourNode, rng: &mut GameRng) -> Option<(Decision, Intent)> {
+        match node {
+            BehaviourNode::Flee { below_percent } => {
+                let (_, enemy) = self.enemy?;
+                if self.health.current * 100 > self.health.max * below_percent {
+                    return None;
+                }
+                self.step_away(enemy)
+                    .map(|step| (Decision::Flee, Intent::Move(step)))
+            }
+            BehaviourNode::KeepRange { min, max } => {
+                let (_, enemy) = self.enemy?;
+                let distance = DistanceAlg::Pythagoras.distance2d(self.pos, enemy);
+                if distance < min as f32 {
+                    self.step_away(enemy)
+                        .map(|step| (Decision::BackOff, Intent::Move(step)))
+                } else if distance > max as f32 {
+                    self.step_towards(enemy)
+                        .map(|step| (Decision::Approach, Intent::Move(step)))
+                } else {
+                    None
+                }
+            }
+            BehaviourNode::Shoot => {
+                let (_, enemy) = self.enemy?;
+                let range = shooting_range(self.ecs, self.entity)?;
+                can_shoot(self.map, self.pos, enemy, range)
+                    .then_some((Decision::Shoot, Intent::Shoot(enemy)))
+            }
+            BehaviourNode::Chase => {
+                let (victim, enemy) = self.enemy?;
+                if self.adjacent(enemy) {
+                    return Some((Decision::Chase, Intent::Attack(victim)));
+                }
+                self.step_towards(enemy)
+                    .map(|step| (Decision::Chase, Intent::Move(step)))
+            }
+            BehaviourNode::Investigate => {
+                let last_known = self.awareness.last_known?;
+                let decision = match self.awareness.state {
+                    AlertState::Idle => return None,
+                    AlertState::Hunting => Decision::Hunt,
+                    AlertState::Suspicious => Decision::Investigate,
+                    AlertState::Searching => {
+                        let steps: Vec<Point> = Direction::allowed(self.rules)
+                            .iter()
+                            .map(|direction| self.pos + direction.delta())
+                            .filter(|step| {
+                                self.map.can_enter_tile(*step)
+                                    && DistanceAlg::Pythagoras.distance2d(*step, last_known)
+                                        <= SEARCH_RADIUS as f32
+                            })
+                            .collect();
+                        return (!steps.is_empty()).then(|| {
+                            let step = steps[rng.range(0, steps.len())];
+                            (Decision::Search, Intent::Move(step))
+                        });
+                    }
+                };
+                self.step_towards(last_known)
+                    .map(|step| (decision, Intent::Move(step)))
+            }
+            BehaviourNode::CallAllies { radius } => {
+                let (_, enemy) = self.enemy?;
+                let allies: Vec<Entity> = self
+                    .index
+                    .in_radius(self.pos, radius as f32)
+                    .filter(|(ally, pos)| {
+                        *ally != self.entity
+                            && has::<Behaviour>(self.ecs, *ally)
+                            && !is_hunting(self.ecs, *ally)
+                            && !sees(self.map, *pos, enemy, SIGHT_RANGE)
+                    })
+                    .map(|(ally, _)| ally)
+                    .collect();
+                (!allies.is_empty())
+                    .then_some((Decision::CallAllies, Intent::Call { allies, enemy }))
+            }
+            BehaviourNode::GuardRoom { post, radius } => {
+                if DistanceAlg::Pythagoras.distance2d(self.pos, post) <= radius as f32 {
+                    return None;
+                }
+                self.step_towards(post)
+                    .map(|step| (Decision::ReturnToPost, Intent::Move(step)))
+            }
+            BehaviourNode::PickUpItems { radius } => {
+                let is_loot =
+                    |item: Entity| has::<Item>(self.ecs, item) && !has::<Key>(self.ecs, item);
+                if let Some(item) = self
+                    .index
+                    .at(self.pos)
+                    .iter()
+                    .copied()
+                    .find(|item| is_loot(*item))
+                {
+                    return Some((Decision::PickUp, Intent::PickUp(item)));
+                }
+                let (_, item) = self
+                    .index
+                    .in_radius(self.pos, radius as f32)
+                    .filter(|(item, pos)| {
+                        is_loot(*item) && sees(self.map, self.pos, *pos, SIGHT_RANGE)
+                    })
+                    .min_by_key(|(_, pos)| {
+                        DistanceAlg::Manhattan.distance2d(self.pos, *pos) as i32
+                    })?;
+                self.step_towards(item)
+                    .map(|step| (Decision::FetchItem, Intent::Move(step)))
+            }
+            BehaviourNode::Wander => {
+                let directions = Direction::allowed(self.rules);
+                let destination = directions[rng.range(0, directions.len())].delta() + self.pos;
+                let victim = unit_at(self.ecs, self.index, destination)
+                    .filter(|victim| has::<Player>(self.ecs, *victim));
+                match victim {
+                    Some(victim) => Some((Decision::Wander, Intent::Attack(victim))),
+                    None => Some((Decision::Wander, Intent::Move(destination))),
+                }
+            }
+        }
+    }
+}
+
+/// Lets every monster work through its behaviour and act on the first node that
+/// decides something, turning the decision into the same move, attack, shot and
+/// pick up requests the player makes
+#[system]
+#[read_component(Point)]
+#[read_component(Behaviour)]
+#[read_component(Awareness)]
+#[read_component(Health)]
+#[read_component(Player)]
+#[read_component(Item)]
+#[read_component(Key)]
+#[read_component(Carried)]
+#[read_component(Equipped)]
+#[read_component(Ranged)]
+pub fn monster_ai(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &Map,
+    #[resource] rules: &MovementRules,
+    #[resource] index: &SpatialIndex,
+    #[resource] rng: &mut GameRng,
+) {
+    let mut monsters = <(Entity, &Point, &Behaviour, &Health, Option<&Awareness>)>::query();
+    for (entity, pos, behaviour, health, awareness) in monsters.iter(ecs) {
+        let awareness = awareness.copied().unwrap_or_default();
+        let enemy = awareness.in_sight.and_then(|enemy| {
+            ecs.entry_ref(enemy)
+                .ok()
+                .and_then(|entry| entry.get_component::<Point>().ok().copied())
+                .map(|pos| (enemy, pos))
+        });
+
+        let situation = Situation {
+            ecs,
+            map,
+            index,
+            rules,
+            entity: *entity,
+            pos: *pos,
+            can_unlock: holds_key(ecs, *entity),
+            health: *health,
+            awareness,
+            enemy,
+        };
+        let (decision, intent) = match behaviour
+            .0
+            .iter()
+            .find_map(|node| situation.decide(*node, rng))
+        {
+            Some(decided) => decided,
+            None => {
+                commands.add_component(*entity, Decision::Idle);
+                continue;
+            }
+        };
+        commands.add_component(*entity, decision);
+        match intent {
+            Intent::Move(destination) => {
+                commands.push((
+                    (),
+                    WantsToMove {
+                        entity: *entity,
+                        destination,
+                    },
+                ));
+            }
+            Intent::Attack(victim) => {
+                commands.push((
+                    (),
+                    WantsToAttack {
+                        attacker: *entity,
+                        victim,
+                    },
+                ));
+            }
+            Intent::Shoot(target) => {
+                commands.push((
+                    (),
+                    WantsToShoot {
+                        shooter: *entity,
+                        target,
+                    },
+                ));
+            }
+            Intent::PickUp(item) => {
+                commands.push((
+                    (),
+                    WantsToPickUp {
+                        entity: *entity,
+                        item,
+                    },
+                ));
+            }
+            Intent::Call { allies, enemy } => {
+                for ally in allies {
+                    commands.add_component(ally, Awareness::alert(AlertState::Hunting, enemy));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,716 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::prelude::*;
+use crate::systems::characters::unit_at;
+
+/// Most resources a worker carries back in one trip
+pub const WORKER_LOAD: u32 = 10;
+
+/// How many turns the front of a formation waits for stragglers before
+/// moving on without them
+const FORMATION_WAIT: u32 = 2;
+
+/// How far a place in a formation may move aside to find somewhere to stand
+const REFORM_RADIUS: i32 = 3;
+
+/// A skirmish unit as orders see it
+#[derive(Clone, Copy)]
+struct Unit {
+    entity: Entity,
+    pos: Point,
+    faction: Faction,
+    kind: UnitKind,
+}
+
+/// Every skirmish unit by id
+fn units_by_id(ecs: &SubWorld) -> BTreeMap<UnitId, Unit> {
+    <(Entity, &UnitId, &Point, &Faction, &UnitKind)>::query()
+        .iter(ecs)
+        .map(|(entity, id, pos, faction, kind)| {
+            let unit = Unit {
+                entity: *entity,
+                pos: *pos,
+                faction: *faction,
+                kind: *kind,
+            };
+            (*id, unit)
+        })
+        .collect()
+}
+
+/// Where each faction's base stands
+fn bases(ecs: &SubWorld) -> BTreeMap<Faction, Point> {
+    <(&UnitKind, &Faction, &Point)>::query()
+        .iter(ecs)
+        .filter(|(kind, _, _)| **kind == UnitKind::Base)
+        .map(|(_, faction, pos)| (*faction, *pos))
+        .collect()
+}
+
+/// Whether a unit at `from` can act on `target` without moving, either from
+/// on top of it or from a neighbouring tile
+fn within_reach(from: Point, target: Point, directions: &[Direction]) -> bool {
+    from == target || directions.iter().any(|d| from + d.delta() == target)
+}
+
+/// The first step on the way from `from` to `to`, if there is a way
+pub fn first_step<M: BaseMap + Algorithm2D>(map: &M, from: Point, to: Point) -> Option<Point> {
+    let path = a_star_search(map.point2d_to_index(from), map.point2d_to_index(to), map);
+    path.steps
+        .get(1)
+        .filter(|_| path.success)
+        .map(|idx| map.index_to_point2d(*idx))
+}
+
+/// The first step on the way from `from` to `to`, going around units that
+/// never move, if there is a way
+fn next_step(
+    map: &Map,
+    rules: &MovementRules,
+    from: Point,
+    to: Point,
+    immobile: &HashSet<Point>,
+) -> Option<Point> {
+    match first_step(&map.pathing(false, rules), from, to) {
+        Some(step) if step != to && immobile.contains(&step) => {
+            let mut detour = map.clone();
+            immobile
+                .iter()
+                .filter(|pos| **pos != to)
+                .for_each(|pos| detour.set_tile(*pos, TileType::Wall));
+            first_step(&detour.pathing(false, rules), from, to)
+        }
+        step => step,
+    }
+}
+
+/// Whether a unit of the same faction as `entity` stands at `point` with
+/// nowhere to go
+fn stopped_ally_at(ecs: &SubWorld, index: &SpatialIndex, entity: Entity, point: Point) -> bool {
+    let faction = |entity: Entity| {
+        ecs.entry_ref(entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<Faction>().ok().copied())
+    };
+    unit_at(ecs, index, point).is_some_and(|other| {
+        faction(other) == faction(entity)
+            && ecs
+                .entry_ref(other)
+                .is_ok_and(|entry| entry.get_component::<Objective>().is_err())
+    })
+}
+
+/// Sends `units` to `destination` together in `formation`, along the path
+/// from whichever of them stands nearest the middle of the group, or each on
+/// its own if there is no such path
+fn form_up(
+    commands: &mut CommandBuffer,
+    map: &Map,
+    rules: &MovementRules,
+    units: &[Unit],
+    destination: Point,
+    formation: Formation,
+) {
+    let distance = |a: Point, b: Point| DistanceAlg::Pythagoras.distance2d(a, b);
+    let count = units.len() as i32;
+    let middle = units.iter().fold(Point::zero(), |sum, unit| sum + unit.pos) / count;
+    let start = units
+        .iter()
+        .map(|unit| unit.pos)
+        .min_by(|a, b| distance(*a, middle).total_cmp(&distance(*b, middle)))
+        .unwrap_or(destination);
+    let path = a_star_search(
+        map.point2d_to_index(start),
+        map.point2d_to_index(destination),
+        &map.pathing(false, rules),
+    );
+    if !path.success {
+        for unit in units {
+            commands.add_component(unit.entity, Objective::MoveTo(destination));
+        }
+        return;
+    }
+
+    let members: Vec<FormationSlot> = units
+        .iter()
+        .map(|unit| FormationSlot {
+            entity: unit.entity,
+            target: unit.pos,
+        })
+        .collect();
+    let group = commands.push((FormationMove {
+        formation,
+        path: path
+            .steps
+            .iter()
+            .map(|idx| map.index_to_point2d(*idx))
+            .collect(),
+        progress: 0,
+        waited: 0,
+        members: members.clone(),
+    },));
+    for member in members {
+        commands.add_component(member.entity, Objective::InFormation(group));
+    }
+}
+
+/// Where each place in a formation is. A place the front can't reach in a
+/// few steps without going further along the path, such as one on a wall or
+/// a base or through a doorway ahead, moves aside to a tile off the path
+/// near both the place and the front, or failing that back along the path,
+/// so the group files through doorways and bends around corners rather than
+/// splitting up or getting in its own way.
+fn places(map: &Map, immobile: &HashSet<Point>, formation: &FormationMove) -> Vec<Point> {
+    let front = formation.front();
+    let offsets = formation
+        .formation
+        .offsets(formation.members.len(), formation.facing());
+    let ahead: HashSet<Point> = formation
+        .path
+        .iter()
+        .skip(formation.progress + 1)
+        .copied()
+        .collect();
+
+    // Every tile within walking distance of the furthest place without
+    // going further along the path
+    let reach = offsets
+        .iter()
+        .map(|offset| offset.x.abs() + offset.y.abs())
+        .max()
+        .unwrap_or(0)
+        + REFORM_RADIUS;
+    let mut reachable: HashSet<Point> = HashSet::from([front]);
+    let mut edge = vec![front];
+    for _ in 0..reach {
+        edge = edge
+            .iter()
+            .flat_map(|pos| Direction::CARDINALS.iter().map(move |d| *pos + d.delta()))
+            .filter(|step| {
+                map.can_enter_tile(*step) && !immobile.contains(step) && !ahead.contains(step)
+            })
+            .filter(|step| reachable.insert(*step))
+            .collect();
+    }
+
+    let distance = |a: Point, b: Point| DistanceAlg::Pythagoras.distance2d(a, b);
+    let free = |point: &Point, taken: &[Point]| reachable.contains(point) && !taken.contains(point);
+    // The way the front came, nearest first, which places only move aside
+    // onto when nothing else is free so the group doesn't block doorways
+    let behind: Vec<Point> = formation.path[..formation.progress]
+        .iter()
+        .rev()
+        .copied()
+        .collect();
+    let mut taken: Vec<Point> = Vec::new();
+    for offset in offsets {
+        let place = front + offset;
+        let place = if free(&place, &taken) {
+            place
+        } else {
+            (-REFORM_RADIUS..=REFORM_RADIUS)
+                .flat_map(|dy| (-REFORM_RADIUS..=REFORM_RADIUS).map(move |dx| Point::new(dx, dy)))
+                .map(|delta| place + delta)
+                .filter(|spot| free(spot, &taken) && !behind.contains(spot))
+                .min_by(|a, b| {
+                    let aside = |spot: Point| distance(spot, place) + distance(spot, front);
+                    aside(*a)
+                        .total_cmp(&aside(*b))
+                        .then(distance(*a, place).total_cmp(&distance(*b, place)))
+                })
+                .or_else(|| {
+                    behind
+                        .iter()
+                        .copied()
+                        .find(|spot| !immobile.contains(spot) && !taken.contains(spot))
+                })
+                .unwrap_or(front)
+        };
+        taken.push(place);
+    }
+    taken
+}
+
+/// Hands this turn's orders to the units they name. Orders for units that
+/// are gone, belong to another faction or can't carry them out are ignored.
+/// Several units sent to the same tile go in formation.
+#[system]
+#[read_component(UnitId)]
+#[read_component(Faction)]
+#[read_component(UnitKind)]
+#[read_component(Point)]
+#[write_component(ProductionQueue)]
+pub fn apply_orders(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] orders: &mut PendingOrders,
+    #[resource] stockpiles: &mut Stockpiles,
+    #[resource] rules: &MovementRules,
+    #[resource] map: &Map,
+) {
+    let units = units_by_id(ecs);
+    for order in orders.0.drain(..) {
+        if let Command::MoveTo { x, y, formation } = order.command {
+            let mut movers: Vec<Unit> = Vec::new();
+            for id in &order.units {
+                match units.get(id) {
+                    Some(unit)
+                        if unit.faction == order.faction
+                            && unit.kind != UnitKind::Base
+                            && !movers.iter().any(|mover| mover.entity == unit.entity) =>
+                    {
+                        movers.push(*unit)
+                    }
+                    _ => {}
+                }
+            }
+            if movers.len() > 1 {
+                form_up(commands, map, rules, &movers, Point::new(x, y), formation);
+                continue;
+            }
+        }
+        for id in &order.units {
+            let unit = match units.get(id) {
+                Some(unit) if unit.faction == order.faction => *unit,
+                _ => continue,
+            };
+            let entity = unit.entity;
+            let mobile = unit.kind != UnitKind::Base;
+            match order.command {
+                Command::Step(direction) if mobile => {
+                    commands.remove_component::<Objective>(entity);
+                    if Direction::allowed(rules).contains(&direction) {
+                        commands.push((
+                            (),
+                            WantsToMove {
+                                entity,
+                                destination: unit.pos + direction.delta(),
+                            },
+                        ));
+                    }
+                }
+                Command::MoveTo { x, y, .. } if mobile => {
+                    commands.add_component(entity, Objective::MoveTo(Point::new(x, y)))
+                }
+                Command::Attack(target) if mobile => {
+                    commands.add_component(entity, Objective::Attack(target))
+                }
+                Command::Gather { x, y } if unit.kind == UnitKind::Worker => {
+                    commands.add_component(entity, Objective::Gather(Point::new(x, y)))
+                }
+                Command::Train(kind) => {
+                    let cost = match kind.cost() {
+                        Some(cost) => cost,
+                        None => continue,
+                    };
+                    if let Ok(production) = ecs
+                        .entry_mut(entity)
+                        .unwrap()
+                        .get_component_mut::<ProductionQueue>()
+                    {
+                        if production.queue.len() < ProductionQueue::MAX_LENGTH
+                            && stockpiles.spend(unit.faction, cost)
+                        {
+                            production.queue.push_back(kind);
+                        }
+                    }
+                }
+                Command::Hold => commands.remove_component::<Objective>(entity),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Workers gathering fill up at their node and empty out at their base once
+/// they are within reach of it. Nodes that run dry are removed.
+#[system]
+#[read_component(Point)]
+#[read_component(Faction)]
+#[read_component(UnitKind)]
+#[read_component(Objective)]
+#[write_component(Cargo)]
+#[write_component(ResourceNode)]
+pub fn gather(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] stockpiles: &mut Stockpiles,
+    #[resource] rules: &MovementRules,
+    #[resource] index: &mut SpatialIndex,
+) {
+    let bases = bases(ecs);
+    let directions = Direction::allowed(rules);
+    let workers: Vec<(Entity, Point, Faction, Point)> =
+        <(Entity, &Point, &Faction, &Objective)>::query()
+            .filter(component::<Cargo>())
+            .iter(ecs)
+            .filter_map(|(entity, pos, faction, objective)| match objective {
+                Objective::Gather(node) => Some((*entity, *pos, *faction, *node)),
+                _ => None,
+            })
+            .collect();
+
+    for (worker, pos, faction, node) in workers {
+        let carrying = ecs
+            .entry_ref(worker)
+            .ok()
+            .and_then(|entry| entry.get_component::<Cargo>().ok().copied())
+            .unwrap_or_default();
+        let load = if carrying.0 > 0 {
+            match bases.get(&faction) {
+                Some(base) if within_reach(pos, *base, directions) => {
+                    stockpiles.add(faction, carrying.0);
+                    0
+                }
+                _ => continue,
+            }
+        } else if within_reach(pos, node, directions) {
+            let mut veins = <(Entity, &Point, &mut ResourceNode)>::query();
+            match veins.iter_mut(ecs).find(|(_, at, _)| **at == node) {
+                Some((vein, _, resources)) => {
+                    let load = resources.remaining.min(WORKER_LOAD);
+                    resources.remaining -= load;
+                    if resources.remaining == 0 {
+                        commands.remove(*vein);
+                        index.remove(*vein, node);
+                    }
+                    load
+                }
+                None => continue,
+            }
+        } else {
+            continue;
+        };
+        if let Ok(cargo) = ecs.entry_mut(worker).unwrap().get_component_mut::<Cargo>() {
+            cargo.0 = load;
+        }
+    }
+}
+
+/// Moves the front of each formation a step along its path once every member
+/// is next to its place, so the group goes at the pace of its slowest member,
+/// and steps each member towards its place around the front. The front stops
+/// waiting for stragglers after a few turns. Once everyone is in place at the
+/// end of the path, or has had time to get there, the formation breaks up and
+/// any member still on its way walks the rest of it alone.
+#[system]
+#[read_component(Point)]
+#[read_component(Objective)]
+#[read_component(Immobile)]
+#[write_component(FormationMove)]
+pub fn keep_formation(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &Map,
+    #[resource] rules: &MovementRules,
+) {
+    let immobile: HashSet<Point> = <&Point>::query()
+        .filter(component::<Immobile>())
+        .iter(ecs)
+        .copied()
+        .collect();
+    // Where each unit keeping to a formation stands, and which one
+    let in_formation: HashMap<Entity, (Point, Entity)> = <(Entity, &Point, &Objective)>::query()
+        .iter(ecs)
+        .filter_map(|(entity, pos, objective)| match objective {
+            Objective::InFormation(group) => Some((*entity, (*pos, *group))),
+            _ => None,
+        })
+        .collect();
+    let position = |entity: Entity| in_formation.get(&entity).map(|(pos, _)| *pos);
+    let groups: Vec<(Entity, FormationMove)> = <(Entity, &FormationMove)>::query()
+        .iter(ecs)
+        .map(|(group, formation)| (*group, formation.clone()))
+        .collect();
+    let directions = Direction::allowed(rules);
+
+    for (group, mut formation) in groups {
+        formation.members.retain(|member| {
+            in_formation
+                .get(&member.entity)
+                .is_some_and(|(_, of)| *of == group)
+        });
+        if formation.members.is_empty() {
+            commands.remove(group);
+            continue;
+        }
+        let last = formation.path.len() - 1;
+        let kept_up = |members: &[FormationSlot]| {
+            members.iter().all(|member| {
+                position(member.entity)
+                    .is_some_and(|pos| within_reach(pos, member.target, directions))
+            })
+        };
+        // Hands out places from the front back, each to the nearest member
+        // left, so members never have to get past each other
+        let assign = |formation: &mut FormationMove| {
+            let front = formation.front();
+            let targets = places(map, &immobile, formation);
+            let distance = |a: Point, b: Point| DistanceAlg::Pythagoras.distance2d(a, b);
+            let mut left: Vec<Entity> = formation
+                .members
+                .iter()
+                .map(|member| member.entity)
+                .collect();
+            let mut order: Vec<usize> = (0..targets.len()).collect();
+            order.sort_by(|a, b| {
+                distance(targets[*a], front).total_cmp(&distance(targets[*b], front))
+            });
+            for slot in order {
+                let place = targets[slot];
+                let away = |entity: &Entity| {
+                    position(*entity).map_or(f32::MAX, |pos| distance(pos, place))
+                };
+                if let Some(nearest) =
+                    (0..left.len()).min_by(|a, b| away(&left[*a]).total_cmp(&away(&left[*b])))
+                {
+                    formation.members[slot].entity = left.remove(nearest);
+                }
+                formation.members[slot].target = place;
+            }
+        };
+        assign(&mut formation);
+        let arrived = formation
+            .members
+            .iter()
+            .all(|member| position(member.entity) == Some(member.target));
+        if formation.progress < last {
+            if kept_up(&formation.members) || formation.waited >= FORMATION_WAIT {
+                formation.progress += 1;
+                formation.waited = 0;
+                assign(&mut formation);
+            } else {
+                formation.waited += 1;
+            }
+        } else if !arrived && formation.waited < formation.members.len() as u32 {
+            // Members filing through a doorway arrive a turn apart
+            formation.waited += 1;
+        } else {
+            for member in &formation.members {
+                if position(member.entity) == Some(member.target) {
+                    commands.remove_component::<Objective>(member.entity);
+                } else {
+                    commands.add_component(member.entity, Objective::MoveTo(member.target));
+                }
+            }
+            commands.remove(group);
+            continue;
+        }
+        // Members go around those already in place rather than swapping with
+        // them, and those nearest the front go first so those behind can step
+        // into the tiles they leave
+        let mut in_the_way = immobile.clone();
+        in_the_way.extend(
+            formation
+                .members
+                .iter()
+                .filter(|member| position(member.entity) == Some(member.target))
+                .map(|member| member.target),
+        );
+        let mut steps: Vec<(f32, Entity, Point)> = formation
+            .members
+            .iter()
+            .filter_map(|member| {
+                let pos = position(member.entity).filter(|pos| *pos != member.target)?;
+                let step = next_step(map, rules, pos, member.target, &in_the_way)?;
+                Some((
+                    DistanceAlg::Pythagoras.distance2d(pos, formation.front()),
+                    member.entity,
+                    step,
+                ))
+            })
+            .collect();
+        steps.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (_, entity, destination) in steps {
+            commands.push((
+                (),
+                WantsToMove {
+                    entity,
+                    destination,
+                },
+            ));
+        }
+        if let Ok(current) = ecs
+            .entry_mut(group)
+            .unwrap()
+            .get_component_mut::<FormationMove>()
+        {
+            *current = formation;
+        }
+    }
+}
+
+/// Moves every unit with an objective a step towards it, around any base in
+/// the way. Units sent after another unit attack once they are next to it,
+/// and gatherers walk between their node and their base. Objectives that are
+/// reached, unreachable or whose target is gone are dropped, as is a move
+/// onto a tile another unit is standing on once the mover is next to it or
+/// held up behind an ally that has stopped.
+#[system]
+#[read_component(UnitId)]
+#[read_component(Faction)]
+#[read_component(UnitKind)]
+#[read_component(Point)]
+#[read_component(Objective)]
+#[read_component(Cargo)]
+#[read_component(ResourceNode)]
+#[read_component(Immobile)]
+#[read_component(Health)]
+pub fn pursue_objectives(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &Map,
+    #[resource] index: &SpatialIndex,
+    #[resource] rules: &MovementRules,
+) {
+    let units = units_by_id(ecs);
+    let bases = bases(ecs);
+    let veins: HashSet<Point> = <&Point>::query()
+        .filter(component::<ResourceNode>())
+        .iter(ecs)
+        .copied()
+        .collect();
+    let immobile: HashSet<Point> = <&Point>::query()
+        .filter(component::<Immobile>())
+        .iter(ecs)
+        .copied()
+        .collect();
+    let directions = Direction::allowed(rules);
+    <(Entity, &Point, &Objective)>::query()
+        .iter(ecs)
+        .for_each(|(entity, pos, objective)| {
+            let target = match *objective {
+                Objective::MoveTo(destination) => {
+                    let blocked = unit_at(ecs, index, destination)
+                        .is_some_and(|occupant| occupant != *entity);
+                    let settled = blocked
+                        && (within_reach(*pos, destination, directions)
+                            || next_step(map, rules, *pos, destination, &immobile)
+                                .is_some_and(|step| stopped_ally_at(ecs, index, *entity, step)));
+                    if settled {
+                        *pos
+                    } else {
+                        destination
+                    }
+                }
+                Objective::Attack(id) => match units.get(&id) {
+                    Some(victim) if within_reach(*pos, victim.pos, directions) => {
+                        commands.push((
+                            (),
+                            WantsToAttack {
+                                attacker: *entity,
+                                victim: victim.entity,
+                            },
+                        ));
+                        return;
+                    }
+                    Some(victim) => victim.pos,
+                    None => *pos,
+                },
+                Objective::Gather(node) => {
+                    let carrying = ecs
+                        .entry_ref(*entity)
+                        .ok()
+                        .and_then(|entry| entry.get_component::<Cargo>().ok().copied())
+                        .is_some_and(|cargo| cargo.0 > 0);
+                    let faction = ecs
+                        .entry_ref(*entity)
+                        .ok()
+                        .and_then(|entry| entry.get_component::<Faction>().ok().copied());
+                    let home = faction.and_then(|faction| bases.get(&faction)).copied();
+                    match (carrying, home) {
+                        (true, Some(base)) if within_reach(*pos, base, directions) => return,
+                        (true, Some(base)) => base,
+                        (false, _) if veins.contains(&node) => {
+                            if within_reach(*pos, node, directions) {
+                                return;
+                            }
+                            node
+                        }
+                        _ => *pos,
+                    }
+                }
+                // Formations move their own members
+                Objective::InFormation(_) => return,
+            };
+            let step = Some(target)
+                .filter(|target| target != pos)
+                .and_then(|target| next_step(map, rules, *pos, target, &immobile));
+            match step {
+                Some(destination) => {
+                    commands.push((
+                        (),
+                        WantsToMove {
+                            entity: *entity,
+                            destination,
+                        },
+                    ));
+                }
+                None => commands.remove_component::<Objective>(*entity),
+            }
+        });
+}
+
+/// Bases work on the first unit in their queue and put it next to them once
+/// it is trained, waiting if every neighbouring tile is taken
+#[system]
+#[read_component(Point)]
+#[read_component(Faction)]
+#[read_component(Health)]
+#[write_component(ProductionQueue)]
+pub fn produce(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &Map,
+    #[resource] index: &mut SpatialIndex,
+    #[resource] next_id: &mut NextUnitId,
+) {
+    let bases: Vec<(Entity, Point, Faction)> = <(Entity, &Point, &Faction)>::query()
+        .filter(component::<ProductionQueue>())
+        .iter(ecs)
+        .map(|(entity, pos, faction)| (*entity, *pos, *faction))
+        .collect();
+    let mut taken = HashSet::new();
+
+    for (base, pos, faction) in bases {
+        let kind = match ecs
+            .entry_ref(base)
+            .ok()
+            .and_then(|entry| entry.get_component::<ProductionQueue>().ok().cloned())
+        {
+            Some(production) => match production.queue.front() {
+                Some(kind) if production.progress + 1 >= kind.training_turns() => *kind,
+                Some(_) => {
+                    if let Ok(production) = ecs
+                        .entry_mut(base)
+                        .unwrap()
+                        .get_component_mut::<ProductionQueue>()
+                    {
+                        production.progress += 1;
+                    }
+                    continue;
+                }
+                None => continue,
+            },
+            None => continue,
+        };
+        let spot = Direction::ALL.iter().map(|d| pos + d.delta()).find(|spot| {
+            map.can_enter_tile(*spot)
+                && unit_at(ecs, index, *spot).is_none()
+                && !taken.contains(spot)
+        });
+        if let Some(spot) = spot {
+            let unit = spawn_unit(commands, kind, faction, next_id.take(), spot);
+            index.insert(unit, spot);
+            taken.insert(spot);
+            if let Ok(production) = ecs
+                .entry_mut(base)
+                .unwrap()
+                .get_component_mut::<ProductionQueue>()
+            {
+                production.queue.pop_front();
+                production.progress = 0;
+            }
+        }
+    }
+}
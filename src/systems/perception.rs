@@ -0,0 +1,140 @@
+use crate::prelude::*;
+
+/// How far an alert monster can see
+pub const SIGHT_RANGE: i32 = 8;
+
+/// How far an idle monster, not expecting trouble, notices an enemy
+const IDLE_SIGHT_RANGE: i32 = 5;
+
+/// How far the player's footsteps carry
+const FOOTSTEP_NOISE: i32 = 3;
+
+/// How far the sounds of a fight the player is in carry
+const COMBAT_NOISE: i32 = 8;
+
+/// Idle allies within this many tiles of a monster that spots an enemy become
+/// suspicious
+const ALERT_RADIUS: i32 = 5;
+
+/// Whether a unit at `from` can see `to` no further than `range` tiles away
+pub fn sees(map: &Map, from: Point, to: Point, range: i32) -> bool {
+    DistanceAlg::Pythagoras.distance2d(from, to) <= range as f32 && map.has_line_of_sight(from, to)
+}
+
+/// Keeps the noises the player made in this schedule's events until monsters
+/// next listen, so they hear fights monsters start with the player during
+/// their own turn as well as those the player starts
+#[system]
+#[read_component(Point)]
+#[read_component(Player)]
+pub fn record_noises(
+    ecs: &SubWorld,
+    #[resource] events: &EventChannel,
+    #[resource] noises: &mut Noises,
+) {
+    let players: Vec<(Entity, Point)> = <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .map(|(entity, pos)| (*entity, *pos))
+        .collect();
+    let player_at = |entity: Entity| {
+        players
+            .iter()
+            .find(|(player, _)| *player == entity)
+            .map(|(_, pos)| *pos)
+    };
+    noises.0.extend(events.iter().filter_map(|event| {
+        match event {
+            GameEvent::UnitMoved { entity, to, .. } => {
+                player_at(*entity).map(|_| (*to, FOOTSTEP_NOISE))
+            }
+            GameEvent::AttackResolved {
+                attacker, victim, ..
+            } => player_at(*attacker)
+                .or_else(|| player_at(*victim))
+                .map(|pos| (pos, COMBAT_NOISE)),
+            GameEvent::UnitDied {
+                killer: Some(killer),
+                at: Some(at),
+                ..
+            } => player_at(*killer).map(|_| (*at, COMBAT_NOISE)),
+            _ => None,
+        }
+    }));
+}
+
+/// Lets every monster look and listen for the player before it decides what to
+/// do. Seeing the player sets a monster hunting and hearing it makes it
+/// suspicious, and either way it remembers where the player was for a while
+/// before searching around there and finally settling back to idle. A monster
+/// that spots the player makes the idle allies around it suspicious.
+#[system]
+#[read_component(Point)]
+#[read_component(Player)]
+#[write_component(Awareness)]
+pub fn perception(
+    ecs: &mut SubWorld,
+    #[resource] map: &Map,
+    #[resource] index: &SpatialIndex,
+    #[resource] noises: &mut Noises,
+) {
+    let players: Vec<(Entity, Point)> = <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .map(|(entity, pos)| (*entity, *pos))
+        .collect();
+    // Everything heard since the last time, which is only heard once
+    let noises = std::mem::take(&mut noises.0);
+
+    let mut spotted: Vec<(Point, Point)> = Vec::new();
+    <(&Point, &mut Awareness)>::query().for_each_mut(ecs, |(pos, awareness)| {
+        awareness.in_sight = None;
+        let range = match awareness.state {
+            AlertState::Idle => IDLE_SIGHT_RANGE,
+            _ => SIGHT_RANGE,
+        };
+        let distance = |point: Point| DistanceAlg::Pythagoras.distance2d(*pos, point);
+        let seen = players
+            .iter()
+            .filter(|(_, player)| sees(map, *pos, *player, range))
+            .min_by(|a, b| distance(a.1).total_cmp(&distance(b.1)));
+        let heard = noises
+            .iter()
+            .filter(|(at, loudness)| distance(*at) <= *loudness as f32)
+            .min_by(|a, b| distance(a.0).total_cmp(&distance(b.0)));
+
+        match (seen, heard) {
+            (Some((player, enemy)), _) => {
+                if awareness.state != AlertState::Hunting {
+                    spotted.push((*pos, *enemy));
+                }
+                *awareness = Awareness::alert(AlertState::Hunting, *enemy);
+                awareness.in_sight = Some(*player);
+            }
+            (None, Some((noise, _))) => {
+                let state = match awareness.state {
+                    AlertState::Hunting => AlertState::Hunting,
+                    _ => AlertState::Suspicious,
+                };
+                *awareness = Awareness::alert(state, *noise);
+            }
+            (None, None) => awareness.forget(*pos),
+        }
+    });
+
+    for (pos, enemy) in spotted {
+        let allies: Vec<Entity> = index
+            .in_radius(pos, ALERT_RADIUS as f32)
+            .map(|(ally, _)| ally)
+            .collect();
+        for ally in allies {
+            if let Ok(mut entry) = ecs.entry_mut(ally) {
+                if let Ok(awareness) = entry.get_component_mut::<Awareness>() {
+                    if awareness.state == AlertState::Idle {
+                        *awareness = Awareness::alert(AlertState::Suspicious, enemy);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,53 +1,121 @@
 use crate::prelude::*;
 
+/// How many tiles one press of a pan key scrolls the camera
+const PAN_STEP: i32 = 5;
+
 #[system]
 #[read_component(Point)]
 #[read_component(Player)]
 #[read_component(Enemy)]
-#[write_component(Health)]
+#[read_component(Item)]
+#[read_component(Carried)]
+#[allow(clippy::too_many_arguments)]
 pub fn player_input(
     ecs: &mut SubWorld,
     commands: &mut CommandBuffer,
     #[resource] key: &Option<VirtualKeyCode>,
+    #[resource] modifiers: &KeyModifiers,
+    #[resource] keybindings: &Keybindings,
+    #[resource] rules: &MovementRules,
+    #[resource] map: &Map,
+    #[resource] camera: &mut Camera,
+    #[resource] index: &SpatialIndex,
     #[resource] turn_state: &mut TurnState,
 ) {
-    let key = match key {
+    let action = match keybindings.action(*key, *modifiers) {
         Some(it) => it,
         _ => return,
     };
     let mut players = <(Entity, &Point)>::query().filter(component::<Player>());
-    let delta = match key {
-        VirtualKeyCode::Left | VirtualKeyCode::H => Point::new(-1, 0),
-        VirtualKeyCode::Right | VirtualKeyCode::L => Point::new(1, 0),
-        VirtualKeyCode::Up | VirtualKeyCode::K => Point::new(0, -1),
-        VirtualKeyCode::Down | VirtualKeyCode::J => Point::new(0, 1),
-        _ => Point::new(0, 0),
+
+    if let Action::Pan(direction) = action {
+        // Panning only looks around, so it takes no turn
+        let corner = camera.top_left_corner() + direction.delta() * PAN_STEP;
+        camera.set_top_left_corner(Point::new(
+            corner.x.max(0).min(map.width - SCREEN_WIDTH),
+            corner.y.max(0).min(map.height - SCREEN_HEIGHT),
+        ));
+        return;
+    }
+
+    let stairs = match action {
+        Action::Descend => Some(StairDirection::Down),
+        Action::Ascend => Some(StairDirection::Up),
+        _ => None,
+    };
+    if let Action::CloseDoor = action {
+        let (entity, pos) = match players.iter(ecs).next() {
+            Some((entity, pos)) => (*entity, *pos),
+            None => return,
+        };
+        let door = Direction::allowed(rules)
+            .iter()
+            .map(|direction| pos + direction.delta())
+            .find(|door| map.get_tile(*door) == Some(TileType::DoorOpen));
+        if let Some(door) = door {
+            commands.push(((), WantsToCloseDoor { entity, door }));
+            *turn_state = TurnState::PlayerTurn;
+        }
+        return;
+    }
+
+    if let Action::PickUp = action {
+        let (player, pos) = match players.iter(ecs).next() {
+            Some((entity, pos)) => (*entity, *pos),
+            None => return,
+        };
+        let item = index.at(pos).iter().copied().find(|entity| {
+            ecs.entry_ref(*entity)
+                .is_ok_and(|entry| entry.get_component::<Item>().is_ok())
+        });
+        if let Some(item) = item {
+            commands.push((
+                (),
+                WantsToPickUp {
+                    entity: player,
+                    item,
+                },
+            ));
+            *turn_state = TurnState::PlayerTurn;
+        }
+        return;
+    }
+
+    if let Some(direction) = stairs {
+        if let Some(entity) = players.iter(ecs).map(|(entity, _)| *entity).next() {
+            commands.push(((), WantsToTakeStairs { entity, direction }));
+            *turn_state = TurnState::PlayerTurn;
+        }
+        return;
+    }
+
+    let delta = match action {
+        Action::Move(direction) if direction.is_diagonal() && !rules.diagonals => return,
+        Action::Move(direction) => direction.delta(),
+        Action::Wait => Point::new(0, 0),
+        _ => return,
     };
 
     let (player_entity, destination) = players
         .iter(ecs)
-        .find_map(|(entity, pos)| Some((*entity, *pos + delta)))
+        .map(|(entity, pos)| (*entity, *pos + delta))
+        .next()
         .unwrap();
 
-    let mut enemies = <(Entity, &Point)>::query().filter(component::<Enemy>());
-    let mut did_something = false;
     if delta.x != 0 || delta.y != 0 {
-        let mut hit_something = false;
-        did_something = true;
-        enemies
-            .iter(ecs)
-            .filter(|(_, pos)| **pos == destination)
-            .for_each(|(entity, _)| {
-                hit_something = true;
-                commands.push((
-                    (),
-                    WantsToAttack {
-                        attacker: player_entity,
-                        victim: *entity,
-                    },
-                ));
-            });
-        if !hit_something {
+        let enemy = index.at(destination).iter().copied().find(|entity| {
+            ecs.entry_ref(*entity)
+                .is_ok_and(|entry| entry.get_component::<Enemy>().is_ok())
+        });
+        if let Some(victim) = enemy {
+            commands.push((
+                (),
+                WantsToAttack {
+                    attacker: player_entity,
+                    victim,
+                },
+            ));
+        } else {
             commands.push((
                 (),
                 WantsToMove {
@@ -56,16 +124,8 @@ pub fn player_input(
                 },
             ));
         }
-    }
-
-    if !did_something {
-        if let Ok(mut health) = ecs
-            .entry_mut(player_entity)
-            .unwrap()
-            .get_component_mut::<Health>()
-        {
-            health.current = (health.current + 1).min(health.max);
-        }
+    } else {
+        commands.add_component(player_entity, Rested);
     }
 
     *turn_state = TurnState::PlayerTurn;
@@ -0,0 +1,297 @@
+use crate::prelude::*;
+use crate::systems::characters::{unit_at, TO_HIT};
+use crate::systems::{
+    apply_effect, attack_profile, award_experience, equipped_in, has_effect, inflicted_effect,
+    kill, total_defense,
+};
+
+/// Tiles a projectile covers each time `projectiles` runs
+const PROJECTILE_SPEED: usize = 4;
+
+/// How far `entity` can shoot: the range of its equipped ranged weapon, or its
+/// own range for units that shoot without one
+pub fn shooting_range(ecs: &SubWorld, entity: Entity) -> Option<i32> {
+    let ranged = |entity: Entity| {
+        ecs.entry_ref(entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<Ranged>().ok().copied())
+    };
+    equipped_in(ecs, entity, EquipmentSlot::Ranged)
+        .and_then(ranged)
+        .or_else(|| ranged(entity))
+        .map(|ranged| ranged.range)
+}
+
+/// Whether a unit at `from` with `range` could shoot at `target`
+pub fn can_shoot(map: &Map, from: Point, target: Point, range: i32) -> bool {
+    from != target
+        && DistanceAlg::Pythagoras.distance2d(from, target) <= range as f32
+        && map.has_line_of_sight(from, target)
+}
+
+/// The tiles a shot from `from` at `target` passes through, carrying on past the
+/// target until it has flown `range` tiles
+fn flight_path(from: Point, target: Point, range: i32) -> Vec<Point> {
+    let delta = target - from;
+    let scale = range / delta.x.abs().max(delta.y.abs()).max(1) + 1;
+    Bresenham::new(from, from + delta * scale)
+        .skip(1)
+        .take(range as usize)
+        .collect()
+}
+
+/// Drives targeting mode. F starts aiming at the closest enemy in range, the
+/// movement keys move the cursor, and F, Return or a mouse click fire.
+#[system]
+#[read_component(Player)]
+#[read_component(Enemy)]
+#[read_component(Point)]
+#[read_component(Equipped)]
+#[read_component(Ranged)]
+#[allow(clippy::too_many_arguments)]
+pub fn targeting(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] key: &mut Option<VirtualKeyCode>,
+    #[resource] modifiers: &KeyModifiers,
+    #[resource] keybindings: &Keybindings,
+    #[resource] targeting: &mut Targeting,
+    #[resource] map: &Map,
+    #[resource] camera: &Camera,
+    #[resource] click: &MouseClick,
+    #[resource] turn_state: &mut TurnState,
+) {
+    let (player, pos) = match <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some((player, pos)) => (*player, *pos),
+        None => return,
+    };
+    let action = keybindings.action(*key, *modifiers);
+    let range = match shooting_range(ecs, player) {
+        Some(range) => range,
+        None => {
+            if action == Some(Action::Target) {
+                *key = None;
+            }
+            return;
+        }
+    };
+
+    let cursor = match *targeting {
+        Targeting::Inactive => {
+            if action == Some(Action::Target) {
+                let closest = <&Point>::query()
+                    .filter(component::<Enemy>())
+                    .iter(ecs)
+                    .filter(|enemy| can_shoot(map, pos, **enemy, range))
+                    .min_by_key(|enemy| DistanceAlg::Manhattan.distance2d(pos, **enemy) as i32)
+                    .copied();
+                *targeting = Targeting::Aiming(closest.unwrap_or(pos));
+                *key = None;
+            }
+            return;
+        }
+        Targeting::Aiming(cursor) => cursor,
+    };
+
+    let fire_at = if let Some(clicked) = click.0 {
+        Some(clicked + camera.top_left_corner())
+    } else {
+        match action {
+            Some(Action::Target) | Some(Action::Confirm) => Some(cursor),
+            _ => None,
+        }
+    };
+    if let Some(target) = fire_at {
+        if can_shoot(map, pos, target, range) {
+            commands.push((
+                (),
+                WantsToShoot {
+                    shooter: player,
+                    target,
+                },
+            ));
+            *targeting = Targeting::Inactive;
+            *turn_state = TurnState::PlayerTurn;
+        }
+        *key = None;
+        return;
+    }
+
+    let delta = match action {
+        Some(Action::Move(direction)) => direction.delta(),
+        Some(Action::Cancel) => {
+            *targeting = Targeting::Inactive;
+            *key = None;
+            return;
+        }
+        _ => Point::zero(),
+    };
+    if map.get_tile(cursor + delta).is_some() {
+        *targeting = Targeting::Aiming(cursor + delta);
+    }
+    *key = None;
+}
+
+/// Turns shots into projectiles leaving the shooter's tile
+#[system]
+#[read_component(WantsToShoot)]
+#[read_component(Point)]
+#[read_component(CombatStats)]
+#[read_component(Equipped)]
+#[read_component(Weapon)]
+#[read_component(Ranged)]
+#[read_component(StatusEffects)]
+#[read_component(InflictsEffect)]
+pub fn shoot(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &Map,
+    #[resource] index: &mut SpatialIndex,
+) {
+    for (message, shot) in <(Entity, &WantsToShoot)>::query().iter(ecs) {
+        let origin = ecs
+            .entry_ref(shot.shooter)
+            .ok()
+            .and_then(|entry| entry.get_component::<Point>().ok().copied());
+        let range = shooting_range(ecs, shot.shooter)
+            .filter(|_| !has_effect(ecs, shot.shooter, EffectKind::Stun));
+        if let (Some(origin), Some(range)) = (origin, range) {
+            if can_shoot(map, origin, shot.target, range) {
+                let (attack, damage) = attack_profile(ecs, shot.shooter, EquipmentSlot::Ranged);
+                let projectile = commands.push((
+                    Projectile {
+                        shooter: shot.shooter,
+                        path: flight_path(origin, shot.target, range),
+                        next: 0,
+                        attack,
+                        damage,
+                        effect: inflicted_effect(ecs, shot.shooter, EquipmentSlot::Ranged),
+                    },
+                    origin,
+                    Render {
+                        color: ColorPair::new(YELLOW, BLACK),
+                        glyph: to_cp437('*'),
+                    },
+                ));
+                index.insert(projectile, origin);
+            }
+        }
+        commands.remove(*message);
+    }
+}
+
+/// Moves projectiles along their path, stopping them at walls, closed doors, the
+/// end of their range or the first unit they hit
+#[system]
+#[write_component(Projectile)]
+#[write_component(Point)]
+#[write_component(Health)]
+#[read_component(CombatStats)]
+#[read_component(Equipped)]
+#[read_component(Armour)]
+#[write_component(StatusEffects)]
+#[read_component(ExperienceValue)]
+#[write_component(Experience)]
+#[read_component(Player)]
+#[read_component(Name)]
+pub fn projectiles(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &Map,
+    #[resource] index: &mut SpatialIndex,
+    #[resource] events: &mut EventChannel,
+    #[resource] rng: &mut GameRng,
+) {
+    let flying: Vec<(Entity, Projectile)> = <(Entity, &Projectile)>::query()
+        .iter(ecs)
+        .map(|(entity, projectile)| (*entity, projectile.clone()))
+        .collect();
+
+    for (entity, mut projectile) in flying {
+        let start = ecs
+            .entry_ref(entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<Point>().ok().copied());
+        let mut position = None;
+        let mut stopped = false;
+        for _ in 0..PROJECTILE_SPEED {
+            let next = match projectile.path.get(projectile.next) {
+                // Shots fly over anything they could be aimed across, chasms included
+                Some(next) if map.get_tile(*next).is_some_and(|tile| !tile.is_opaque()) => *next,
+                _ => {
+                    stopped = true;
+                    break;
+                }
+            };
+            projectile.next += 1;
+            position = Some(next);
+
+            let victim = unit_at(ecs, index, next).filter(|victim| *victim != projectile.shooter);
+            if let Some(victim) = victim {
+                let defense = total_defense(ecs, victim);
+                if rng.roll_dice(1, 20) + projectile.attack < TO_HIT + defense {
+                    // A miss flies on past the target
+                    events.publish(GameEvent::AttackResolved {
+                        attacker: projectile.shooter,
+                        victim,
+                        hit: false,
+                        damage: 0,
+                    });
+                    continue;
+                }
+                let damage = projectile.damage.roll(rng).max(1);
+                let mut killed = false;
+                if let Ok(health) = ecs.entry_mut(victim).unwrap().get_component_mut::<Health>() {
+                    // A victim already killed this turn can't die again
+                    killed = health.current >= 1 && health.current - damage < 1;
+                    health.current -= damage;
+                }
+                events.publish(GameEvent::AttackResolved {
+                    attacker: projectile.shooter,
+                    victim,
+                    hit: true,
+                    damage,
+                });
+                if killed {
+                    kill(
+                        ecs,
+                        commands,
+                        events,
+                        index,
+                        victim,
+                        Some(projectile.shooter),
+                    );
+                    award_experience(ecs, projectile.shooter, victim);
+                }
+                if let Some(effect) = projectile.effect {
+                    apply_effect(ecs, victim, effect);
+                }
+                stopped = true;
+                break;
+            }
+        }
+
+        if stopped {
+            if let Some(start) = start {
+                index.remove(entity, start);
+            }
+            commands.remove(entity);
+            continue;
+        }
+        if let Ok(mut entry) = ecs.entry_mut(entity) {
+            if let (Some(position), Ok(pos)) = (position, entry.get_component_mut::<Point>()) {
+                if let Some(start) = start {
+                    index.move_entity(entity, start, position);
+                }
+                *pos = position;
+            }
+            if let Ok(flying) = entry.get_component_mut::<Projectile>() {
+                flying.next = projectile.next;
+            }
+        }
+    }
+}
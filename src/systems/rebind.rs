@@ -0,0 +1,65 @@
+use crate::prelude::*;
+
+/// The keybindings screen. While it is open every key is consumed: Up and Down
+/// pick an action, Return waits for a new key for it, Delete clears its keys,
+/// Tab toggles diagonal movement and Escape closes the screen and saves the
+/// bindings. The navigation keys are fixed so a bad binding can always be
+/// undone.
+#[system]
+pub fn rebind_keys(
+    #[resource] key: &mut Option<VirtualKeyCode>,
+    #[resource] modifiers: &KeyModifiers,
+    #[resource] keybindings: &mut Keybindings,
+    #[resource] menu: &mut RebindMenu,
+    #[resource] rules: &mut MovementRules,
+) {
+    let pressed = match *key {
+        Some(pressed) => pressed,
+        None => return,
+    };
+    let count = keybindings.bindings.len();
+
+    match *menu {
+        RebindMenu::Closed => {
+            if keybindings.action(*key, *modifiers) == Some(Action::Keybindings) {
+                *menu = RebindMenu::Browsing(0);
+                *key = None;
+            }
+            return;
+        }
+        RebindMenu::Browsing(selected) => match pressed {
+            VirtualKeyCode::Up => *menu = RebindMenu::Browsing((selected + count - 1) % count),
+            VirtualKeyCode::Down => *menu = RebindMenu::Browsing((selected + 1) % count),
+            VirtualKeyCode::Return => *menu = RebindMenu::Capturing(selected),
+            VirtualKeyCode::Delete | VirtualKeyCode::Back => {
+                let action = keybindings.bindings[selected].0;
+                keybindings.clear(action);
+            }
+            VirtualKeyCode::Tab => {
+                keybindings.diagonal_movement = !keybindings.diagonal_movement;
+                *rules = keybindings.movement_rules();
+            }
+            VirtualKeyCode::Escape => {
+                if let Err(error) = keybindings.save() {
+                    eprintln!("Could not save keybindings: {}", error);
+                }
+                *menu = RebindMenu::Closed;
+            }
+            _ => (),
+        },
+        RebindMenu::Capturing(selected) => {
+            // Shift and friends arrive as key presses of their own before the
+            // key they modify, so wait for the real key
+            if is_modifier(pressed) {
+                *key = None;
+                return;
+            }
+            if pressed != VirtualKeyCode::Escape {
+                let action = keybindings.bindings[selected].0;
+                keybindings.bind(action, KeyChord::new(pressed, *modifiers));
+            }
+            *menu = RebindMenu::Browsing(selected);
+        }
+    }
+    *key = None;
+}
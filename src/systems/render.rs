@@ -1,30 +1,75 @@
 use crate::prelude::*;
+use crate::systems::{can_shoot, carried_items, equipped_in, shooting_range, LevelUpReward};
 
+/// Draws every unit and item, tinting monsters by how alert they are
 #[system]
 #[read_component(Point)]
 #[read_component(Render)]
+#[read_component(Item)]
+#[read_component(Awareness)]
 pub fn characters(ecs: &SubWorld, #[resource] camera: &Camera) {
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(1);
     let offset = camera.top_left_corner();
 
-    <(&Point, &Render)>::query()
+    // Items go first so anything standing on them is drawn on top
+    <(&Point, &Render, Option<&Awareness>)>::query()
+        .filter(component::<Item>())
         .iter(ecs)
-        .for_each(|(pos, render)| {
-            draw_batch.set(*pos - offset, render.color, render.glyph);
+        .chain(
+            <(&Point, &Render, Option<&Awareness>)>::query()
+                .filter(!component::<Item>())
+                .iter(ecs),
+        )
+        .for_each(|(pos, render, awareness)| {
+            let color = match awareness.and_then(|awareness| awareness.state.tint()) {
+                Some(tint) => ColorPair::new(
+                    render.color.fg.lerp(RGBA::named(tint), 0.5),
+                    render.color.bg,
+                ),
+                None => render.color,
+            };
+            draw_batch.set(*pos - offset, color, render.glyph);
         });
 
     draw_batch.submit(5000).expect("Batch Error");
 }
 
+/// Labels each monster with its last decision while the AI debug overlay is on
 #[system]
-pub fn map(#[resource] map: &Map, #[resource] camera: &Camera) {
+#[read_component(Point)]
+#[read_component(Decision)]
+pub fn decisions(
+    ecs: &SubWorld,
+    #[resource] overlay: &AiDebugOverlay,
+    #[resource] camera: &Camera,
+) {
+    if !overlay.0 {
+        return;
+    }
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(1);
+    let offset = camera.top_left_corner();
+    <(&Point, &Decision)>::query()
+        .iter(ecs)
+        .for_each(|(pos, decision)| {
+            draw_batch.print_color(
+                *pos - offset + Point::new(1, 0),
+                decision.label(),
+                ColorPair::new(decision.color(), BLACK),
+            );
+        });
+    draw_batch.submit(5100).expect("Batch Error");
+}
+
+#[system]
+pub fn map(#[resource] map: &Map, #[resource] camera: &Camera, #[resource] theme: &TileTheme) {
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(0);
     let offset = camera.top_left_corner();
     camera.into_iter().for_each(|point| {
         if let Some(tile) = map.get_tile(point) {
-            draw_batch.set(point - offset, tile.into(), tile);
+            draw_batch.set(point - offset, theme.color(tile), theme.glyph(tile));
         }
     });
     draw_batch.submit(0).expect("Batch Error");
@@ -54,6 +99,43 @@ pub fn builder(#[resource] builder: &mut MapBuilder, #[resource] camera: &Camera
             );
         });
     }
+    for point in builder.sealed.iter() {
+        draw_batch.set(
+            *point - camera_offset,
+            ColorPair::new(DARK_GRAY, BLACK),
+            TileType::Wall,
+        );
+    }
+    for (point, tile) in builder.features.iter() {
+        draw_batch.set(*point - camera_offset, (*tile).into(), *tile);
+    }
+    let stairs = builder
+        .stairs_up
+        .iter()
+        .map(|pos| (*pos, TileType::StairsUp))
+        .chain(
+            builder
+                .stairs_down
+                .iter()
+                .map(|pos| (*pos, TileType::StairsDown)),
+        );
+    for (pos, tile) in stairs {
+        draw_batch.set(pos - camera_offset, ColorPair::new(WHITE, BLACK), tile);
+    }
+    for item in builder.items.iter() {
+        draw_batch.set(
+            *item - camera_offset,
+            ColorPair::new(MAGENTA, BLACK),
+            to_cp437('*'),
+        );
+    }
+    for key in builder.keys.iter() {
+        draw_batch.set(
+            *key - camera_offset,
+            ColorPair::new(GOLD, BLACK),
+            to_cp437('k'),
+        );
+    }
     for player in builder.player.iter() {
         draw_batch.set(
             *player - camera_offset,
@@ -65,7 +147,10 @@ pub fn builder(#[resource] builder: &mut MapBuilder, #[resource] camera: &Camera
 }
 
 #[system]
-pub fn progress_bar(#[resource] progress_bar: &Option<ProgressBar>) {
+pub fn progress_bar(
+    #[resource] progress_bar: &Option<ProgressBar>,
+    #[resource] speed: &GenerationSpeed,
+) {
     if let Some(ProgressBar {
         current,
         total,
@@ -90,32 +175,200 @@ pub fn progress_bar(#[resource] progress_bar: &Option<ProgressBar>) {
         if let Some(label) = label {
             draw_batch.print_color_centered(*y, label, ColorPair::new(WHITE, BLACK));
         }
+        draw_batch.print_color_centered(
+            y + 2,
+            format!("Speed: {} (+/- to change, End to skip)", speed.describe()),
+            ColorPair::new(YELLOW, BLACK),
+        );
 
         draw_batch.submit(UI_LAYER + 10).expect("Batch Error");
     }
 }
 
+#[system]
+pub fn generation_debugger(
+    #[resource] debugger: &Option<GenerationDebugger>,
+    #[resource] runner: &GeneraotrRunner,
+    #[resource] builder: &MapBuilder,
+) {
+    let debugger = match debugger {
+        Some(debugger) => debugger,
+        None => return,
+    };
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(1);
+
+    let state = if debugger.paused { "PAUSED" } else { "PLAYING" };
+    let command = runner.get_render_text().unwrap_or("Finished");
+    draw_batch.print_color(
+        Point::new(1, 1),
+        format!(
+            "Step {}/{} | Command {}/{}: {} | {}",
+            debugger.cursor,
+            debugger.history.len() - 1,
+            (runner.run_index + 1).min(runner.commands.len()),
+            runner.commands.len(),
+            command,
+            state
+        ),
+        ColorPair::new(YELLOW, BLACK),
+    );
+    draw_batch.print_color(
+        Point::new(1, 2),
+        "Space play/pause, Left/Right step, PgUp/PgDn command, Home/End",
+        ColorPair::new(WHITE, BLACK),
+    );
+    if builder.finished {
+        draw_batch.print_color(
+            Point::new(1, 3),
+            "Generation finished. Enter to start the game.",
+            ColorPair::new(GREEN, BLACK),
+        );
+    }
+    draw_batch.submit(UI_LAYER + 20).expect("Batch Error");
+}
+
+/// The actions listed in the help line, with the first key bound to each
+const HELP: [(Action, &str); 9] = [
+    (Action::Wait, "wait"),
+    (Action::Descend, "down"),
+    (Action::CloseDoor, "close"),
+    (Action::PickUp, "get"),
+    (Action::Inventory, "use"),
+    (Action::Drop, "drop"),
+    (Action::Target, "fire"),
+    (Action::Rest, "rest"),
+    (Action::Keybindings, "keys"),
+];
+
+fn help_line(keybindings: &Keybindings) -> String {
+    HELP.iter()
+        .filter_map(|(action, label)| {
+            keybindings
+                .keys_for(*action)
+                .first()
+                .map(|key| format!("{} {}", key, label))
+        })
+        .collect::<Vec<String>>()
+        .join("  ")
+}
+
 #[system]
 #[read_component(Health)]
 #[read_component(Player)]
-pub fn hud(ecs: &SubWorld) {
+#[read_component(StatusEffects)]
+#[read_component(Experience)]
+#[read_component(Hunger)]
+pub fn hud(
+    ecs: &SubWorld,
+    #[resource] depth: &Depth,
+    #[resource] resting: &Resting,
+    #[resource] keybindings: &Keybindings,
+) {
     let mut health_query = <&Health>::query().filter(component::<Player>());
-    let Health { current, max } = health_query.iter(ecs).next().unwrap();
+    let Health { current, max } = match health_query.iter(ecs).next() {
+        Some(health) => health,
+        None => return,
+    };
 
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(1);
-    draw_batch.print_color_centered(
-        1,
-        "Explore the Dungeon. Cursor keys to move.",
-        ColorPair::new(WHITE, BLACK),
+    draw_batch.print_color_centered(1, help_line(keybindings), ColorPair::new(WHITE, BLACK));
+    draw_batch.print_color_right(
+        Point::new(SCREEN_WIDTH, 2),
+        format!("Depth: {}", depth.0),
+        ColorPair::new(YELLOW, BLACK),
     );
+    let hunger = <&Hunger>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+        .map(|hunger| hunger.state());
+    let status = match (resting, hunger) {
+        (Resting::Active { .. }, _) => Some(("Resting...", CYAN)),
+        (_, Some(HungerState::Hungry)) => Some(("Hungry", ORANGE)),
+        (_, Some(HungerState::Starving)) => Some(("Starving!", RED)),
+        _ => None,
+    };
+    if let Some((status, color)) = status {
+        draw_batch.print_color_right(
+            Point::new(SCREEN_WIDTH - 10, 2),
+            status,
+            ColorPair::new(color, BLACK),
+        );
+    }
+    if let Some(effects) = <&StatusEffects>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        draw_batch.print_color(
+            Point::new(0, 2),
+            effects.describe(),
+            ColorPair::new(ORANGE, BLACK),
+        );
+    }
     draw_batch.bar_horizontal(
         Point::zero(),
-        SCREEN_WIDTH,
+        SCREEN_WIDTH / 2,
         *current,
         *max,
         ColorPair::new(RED, BLACK),
     );
+    draw_batch.print_color(
+        Point::new(1, 0),
+        format!("HP {}/{}", current, max),
+        ColorPair::new(WHITE, RED),
+    );
+    if let Some(experience) = <&Experience>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        draw_batch.bar_horizontal(
+            Point::new(SCREEN_WIDTH / 2, 0),
+            SCREEN_WIDTH / 2,
+            experience.current,
+            experience.needed(),
+            ColorPair::new(GOLD, BLACK),
+        );
+        draw_batch.print_color(
+            Point::new(SCREEN_WIDTH / 2 + 1, 0),
+            format!(
+                "Level {}  XP {}/{}",
+                experience.level,
+                experience.current,
+                experience.needed()
+            ),
+            ColorPair::new(BLACK, GOLD),
+        );
+    }
+    draw_batch.submit(UI_LAYER + 10).expect("Batch Error");
+}
+
+/// How many of the newest log messages are shown
+const LOG_LINES: usize = 4;
+
+#[system]
+pub fn message_log(#[resource] log: &MessageLog, #[resource] stats: &Statistics) {
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(1);
+    let shown = log.messages.len().min(LOG_LINES);
+    for (line, (text, color)) in log.messages[log.messages.len() - shown..]
+        .iter()
+        .enumerate()
+    {
+        draw_batch.print_color(
+            Point::new(0, SCREEN_HEIGHT - shown as i32 + line as i32),
+            text,
+            ColorPair::new(*color, BLACK),
+        );
+    }
+    draw_batch.print_color_right(
+        Point::new(SCREEN_WIDTH, SCREEN_HEIGHT - 1),
+        format!("Turn {}  Kills {}", stats.turns, stats.kills),
+        ColorPair::new(GREY, BLACK),
+    );
     draw_batch.submit(UI_LAYER + 10).expect("Batch Error");
 }
 
@@ -123,17 +376,28 @@ pub fn hud(ecs: &SubWorld) {
 #[read_component(Point)]
 #[read_component(Name)]
 #[read_component(Health)]
-pub fn tooltips(ecs: &SubWorld, #[resource] mouse_pos: &MousePoint, #[resource] camera: &Camera) {
+#[read_component(Equipped)]
+#[read_component(StatusEffects)]
+#[read_component(Awareness)]
+pub fn tooltips(
+    ecs: &SubWorld,
+    #[resource] mouse_pos: &MousePoint,
+    #[resource] camera: &Camera,
+    #[resource] index: &SpatialIndex,
+) {
     let offset = camera.top_left_corner();
     let map_pos = mouse_pos.0 + offset;
 
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(1);
 
-    for (entity, _, name) in <(Entity, &Point, &Name)>::query()
-        .iter(ecs)
-        .filter(|(_, pos, _)| **pos == map_pos)
-    {
+    let named = index.at(map_pos).iter().filter_map(|entity| {
+        ecs.entry_ref(*entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<Name>().ok().cloned())
+            .map(|name| (entity, name))
+    });
+    for (entity, name) in named {
         let mut screen_pos = mouse_pos.0;
         if screen_pos.y > SCREEN_HEIGHT / 2 {
             screen_pos.y -= 1;
@@ -141,14 +405,286 @@ pub fn tooltips(ecs: &SubWorld, #[resource] mouse_pos: &MousePoint, #[resource]
             screen_pos.y += 1;
         }
 
-        let display = if let Ok(health) = ecs.entry_ref(*entity).unwrap().get_component::<Health>()
-        {
-            format!("{} : {} hp", &name.0, health.current)
-        } else {
-            name.0.clone()
-        };
+        let mut display =
+            if let Ok(health) = ecs.entry_ref(*entity).unwrap().get_component::<Health>() {
+                format!("{} : {} hp", &name.0, health.current)
+            } else {
+                name.0.clone()
+            };
+        let weapon = equipped_in(ecs, *entity, EquipmentSlot::Melee)
+            .and_then(|weapon| ecs.entry_ref(weapon).ok())
+            .and_then(|entry| entry.get_component::<Name>().ok().cloned());
+        if let Some(weapon) = weapon {
+            display = format!("{}, wielding {}", display, weapon.0);
+        }
+        let effects = ecs
+            .entry_ref(*entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<StatusEffects>().ok().cloned())
+            .filter(|effects| !effects.0.is_empty());
+        if let Some(effects) = effects {
+            display = format!("{} [{}]", display, effects.describe());
+        }
+        let awareness = ecs
+            .entry_ref(*entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<Awareness>().ok().copied());
+        if let Some(awareness) = awareness {
+            display = format!("{} ({})", display, awareness.state.name());
+        }
         draw_batch.print_centered_at(screen_pos, &display);
     }
 
     draw_batch.submit(UI_LAYER).expect("Batch Error");
 }
+
+#[system]
+#[read_component(Player)]
+#[read_component(Item)]
+#[read_component(Carried)]
+#[read_component(Name)]
+#[read_component(Equipped)]
+pub fn inventory(ecs: &SubWorld, #[resource] menu: &InventoryMenu) {
+    let title = match menu {
+        InventoryMenu::Closed => return,
+        InventoryMenu::Use => "Use or equip which item? (Esc to close)",
+        InventoryMenu::Drop => "Drop which item? (Esc to close)",
+    };
+    let player = match <Entity>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(player) => *player,
+        None => return,
+    };
+    let items = carried_items(ecs, player);
+
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(1);
+    let panel = Rect::with_size(2, 4, 44, items.len().max(1) as i32 + 3);
+    draw_batch.draw_double_box(panel, ColorPair::new(WHITE, BLACK));
+    draw_batch.print_color(
+        Point::new(panel.x1 + 2, panel.y1),
+        title,
+        ColorPair::new(YELLOW, BLACK),
+    );
+    if items.is_empty() {
+        draw_batch.print(Point::new(panel.x1 + 2, panel.y1 + 2), "You carry nothing.");
+    }
+    for (index, (item, name)) in items.iter().take(9).enumerate() {
+        let equipped = ecs
+            .entry_ref(*item)
+            .is_ok_and(|entry| entry.get_component::<Equipped>().is_ok());
+        draw_batch.print(
+            Point::new(panel.x1 + 2, panel.y1 + 2 + index as i32),
+            format!(
+                "{}. {}{}",
+                index + 1,
+                name,
+                if equipped { " (equipped)" } else { "" }
+            ),
+        );
+    }
+    draw_batch.submit(UI_LAYER + 20).expect("Batch Error");
+}
+
+#[system]
+#[read_component(Player)]
+#[read_component(Point)]
+#[read_component(Equipped)]
+#[read_component(Ranged)]
+pub fn targeting(
+    ecs: &SubWorld,
+    #[resource] targeting: &Targeting,
+    #[resource] map: &Map,
+    #[resource] camera: &Camera,
+) {
+    let cursor = match targeting {
+        Targeting::Inactive => return,
+        Targeting::Aiming(cursor) => *cursor,
+    };
+    let (player, pos) = match <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some((player, pos)) => (*player, *pos),
+        None => return,
+    };
+    let range = shooting_range(ecs, player).unwrap_or(0);
+    let (color, line) = if can_shoot(map, pos, cursor, range) {
+        (CYAN, DARK_CYAN)
+    } else {
+        (RED, DARK_RED)
+    };
+    let offset = camera.top_left_corner();
+
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(1);
+    for point in Bresenham::new(pos, cursor).skip(1) {
+        draw_batch.set_bg(point - offset, line);
+    }
+    draw_batch.set_bg(cursor - offset, color);
+    draw_batch.print_color_centered(
+        3,
+        "Aim with cursor keys or mouse, F or Enter to fire, Esc to cancel",
+        ColorPair::new(color, BLACK),
+    );
+    draw_batch.submit(UI_LAYER + 5).expect("Batch Error");
+}
+
+#[system]
+#[read_component(Player)]
+#[read_component(Experience)]
+pub fn level_up(ecs: &SubWorld) {
+    let experience = match <&Experience>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .find(|experience| experience.unspent > 0)
+    {
+        Some(experience) => *experience,
+        None => return,
+    };
+
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(1);
+    let panel = Rect::with_size(
+        SCREEN_WIDTH / 2 - 24,
+        SCREEN_HEIGHT / 2 - 6,
+        48,
+        LevelUpReward::ALL.len() as i32 + 5,
+    );
+    draw_batch.draw_double_box(panel, ColorPair::new(GOLD, BLACK));
+    draw_batch.print_color(
+        Point::new(panel.x1 + 2, panel.y1),
+        format!(
+            "Welcome to level {}!",
+            experience.level - experience.unspent + 1
+        ),
+        ColorPair::new(GOLD, BLACK),
+    );
+    draw_batch.print(Point::new(panel.x1 + 2, panel.y1 + 2), "Choose a reward:");
+    for (index, reward) in LevelUpReward::ALL.iter().enumerate() {
+        draw_batch.print(
+            Point::new(panel.x1 + 2, panel.y1 + 3 + index as i32),
+            format!("{}. {}", index + 1, reward.describe()),
+        );
+    }
+    draw_batch.submit(UI_LAYER + 30).expect("Batch Error");
+}
+
+#[system]
+pub fn game_over(#[resource] stats: &Statistics, #[resource] keybindings: &Keybindings) {
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(1);
+    let panel = Rect::with_size(SCREEN_WIDTH / 2 - 20, SCREEN_HEIGHT / 2 - 5, 40, 7);
+    draw_batch.draw_double_box(panel, ColorPair::new(RED, BLACK));
+    draw_batch.print_color(
+        Point::new(panel.x1 + 2, panel.y1),
+        "You have died",
+        ColorPair::new(RED, BLACK),
+    );
+    draw_batch.print(
+        Point::new(panel.x1 + 2, panel.y1 + 2),
+        format!(
+            "{} turns, {} kills, depth {}",
+            stats.turns, stats.kills, stats.deepest_level
+        ),
+    );
+    if let Some(key) = keybindings.keys_for(Action::LoadGame).first() {
+        draw_batch.print(
+            Point::new(panel.x1 + 2, panel.y1 + 4),
+            format!("Press {} to load your saved game", key),
+        );
+    }
+    draw_batch.submit(UI_LAYER + 30).expect("Batch Error");
+}
+
+#[system]
+pub fn rebind_keys(#[resource] keybindings: &Keybindings, #[resource] menu: &RebindMenu) {
+    let (selected, capturing) = match *menu {
+        RebindMenu::Closed => return,
+        RebindMenu::Browsing(selected) => (selected, false),
+        RebindMenu::Capturing(selected) => (selected, true),
+    };
+
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(1);
+    let panel = Rect::with_size(
+        SCREEN_WIDTH / 2 - 30,
+        2,
+        60,
+        keybindings.bindings.len() as i32 + 5,
+    );
+    draw_batch.draw_double_box(panel, ColorPair::new(WHITE, BLACK));
+    draw_batch.print_color(
+        Point::new(panel.x1 + 2, panel.y1),
+        "Keybindings (Enter rebind, Del clear, Esc save)",
+        ColorPair::new(YELLOW, BLACK),
+    );
+    for (index, (action, keys)) in keybindings.bindings.iter().enumerate() {
+        let keys = if capturing && index == selected {
+            "press a key (Esc to cancel)".to_string()
+        } else {
+            keys.iter()
+                .map(|key| key.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let color = if index == selected {
+            ColorPair::new(BLACK, WHITE)
+        } else {
+            ColorPair::new(WHITE, BLACK)
+        };
+        let y = panel.y1 + 2 + index as i32;
+        draw_batch.print_color(
+            Point::new(panel.x1 + 2, y),
+            format!("{:<22}{}", action.describe(), keys),
+            color,
+        );
+    }
+    draw_batch.print_color(
+        Point::new(panel.x1 + 2, panel.y2 - 1),
+        if keybindings.diagonal_movement {
+            "Diagonal movement on (Tab to toggle)"
+        } else {
+            "Diagonal movement off (Tab to toggle)"
+        },
+        ColorPair::new(GREY, BLACK),
+    );
+    draw_batch.submit(UI_LAYER + 40).expect("Batch Error");
+}
+
+#[system]
+pub fn recipe_menu(#[resource] menu: &RecipeMenu) {
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(1);
+    let panel = Rect::with_size(SCREEN_WIDTH / 2 - 30, 2, 60, menu.recipes.len() as i32 + 6);
+    draw_batch.draw_double_box(panel, ColorPair::new(WHITE, BLACK));
+    draw_batch.print_color(
+        Point::new(panel.x1 + 2, panel.y1),
+        "Choose a level recipe (Enter to start)",
+        ColorPair::new(YELLOW, BLACK),
+    );
+    for (index, (name, recipe)) in menu.recipes.iter().enumerate() {
+        let color = match (index == menu.selected, recipe.is_ok()) {
+            (true, _) => ColorPair::new(BLACK, WHITE),
+            (false, true) => ColorPair::new(WHITE, BLACK),
+            (false, false) => ColorPair::new(GREY, BLACK),
+        };
+        draw_batch.print_color(
+            Point::new(panel.x1 + 2, panel.y1 + 2 + index as i32),
+            name,
+            color,
+        );
+    }
+    if let (_, Err(error)) = &menu.recipes[menu.selected] {
+        draw_batch.print_color(
+            Point::new(panel.x1 + 2, panel.y2 - 2),
+            error.chars().take(56).collect::<String>(),
+            ColorPair::new(RED, BLACK),
+        );
+    }
+    draw_batch.submit(UI_LAYER + 40).expect("Batch Error");
+}
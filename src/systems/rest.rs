@@ -0,0 +1,136 @@
+use crate::prelude::*;
+use crate::systems::kill;
+
+/// How far away an enemy with a clear line of sight stops the player resting
+const VIEW_RADIUS: f32 = 8.0;
+/// Turns of regeneration a turn spent waiting or resting is worth
+const REST_BONUS: i32 = 3;
+/// Turns between each point of health a starving unit loses
+const STARVATION_INTERVAL: i32 = 10;
+
+fn enemy_in_view(ecs: &SubWorld, map: &Map, index: &SpatialIndex, pos: Point) -> bool {
+    index.in_radius(pos, VIEW_RADIUS).any(|(entity, enemy)| {
+        ecs.entry_ref(entity)
+            .is_ok_and(|entry| entry.get_component::<Enemy>().is_ok())
+            && map.has_line_of_sight(pos, enemy)
+    })
+}
+
+/// The rest key starts resting until healed. While resting a turn is taken every frame
+/// until the player is healed, hungry, sees an enemy, gets hurt or presses a key.
+#[system]
+#[read_component(Player)]
+#[read_component(Enemy)]
+#[read_component(Point)]
+#[read_component(Health)]
+#[read_component(Hunger)]
+#[allow(clippy::too_many_arguments)]
+pub fn rest(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] key: &mut Option<VirtualKeyCode>,
+    #[resource] modifiers: &KeyModifiers,
+    #[resource] keybindings: &Keybindings,
+    #[resource] resting: &mut Resting,
+    #[resource] map: &Map,
+    #[resource] index: &SpatialIndex,
+    #[resource] turn_state: &mut TurnState,
+) {
+    let (player, pos, health, hunger) = match <(Entity, &Point, &Health, Option<&Hunger>)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some((player, pos, health, hunger)) => (*player, *pos, *health, hunger.copied()),
+        None => return,
+    };
+
+    let last_health = match *resting {
+        Resting::Inactive if keybindings.action(*key, *modifiers) == Some(Action::Rest) => {
+            *key = None;
+            health.current
+        }
+        Resting::Inactive => return,
+        Resting::Active { .. } if key.is_some() => {
+            *key = None;
+            *resting = Resting::Inactive;
+            return;
+        }
+        Resting::Active { last_health } => last_health,
+    };
+
+    let interrupted = health.current >= health.max
+        || health.current < last_health
+        || hunger.is_some_and(|hunger| hunger.state() != HungerState::Fed)
+        || enemy_in_view(ecs, map, index, pos);
+    if interrupted {
+        *resting = Resting::Inactive;
+        return;
+    }
+
+    *resting = Resting::Active {
+        last_health: health.current,
+    };
+    commands.add_component(player, Rested);
+    *turn_state = TurnState::PlayerTurn;
+}
+
+/// Runs once a round: hunger drops, starving units waste away, and everyone
+/// else regenerates, faster if they spent the turn resting.
+#[system]
+#[write_component(Health)]
+#[write_component(Hunger)]
+#[write_component(Regeneration)]
+#[read_component(Rested)]
+#[read_component(Player)]
+#[read_component(Name)]
+#[read_component(Point)]
+pub fn metabolism(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] events: &mut EventChannel,
+    #[resource] index: &mut SpatialIndex,
+) {
+    let mut starved = Vec::new();
+    <(Entity, &mut Health, &mut Hunger)>::query().for_each_mut(ecs, |(entity, health, hunger)| {
+        // Poison may already have killed it this turn
+        if health.current < 1 {
+            return;
+        }
+        // Below zero satiety counts the turns until the next point of starvation
+        hunger.satiety = (hunger.satiety - 1).max(-STARVATION_INTERVAL);
+        if hunger.satiety == -STARVATION_INTERVAL {
+            hunger.satiety = 0;
+            health.current -= 1;
+            if health.current < 1 {
+                starved.push(*entity);
+            }
+        }
+    });
+    for entity in starved {
+        kill(ecs, commands, events, index, entity, None);
+    }
+
+    <(
+        Entity,
+        &mut Health,
+        &mut Regeneration,
+        Option<&Hunger>,
+        Option<&Rested>,
+    )>::query()
+    .for_each_mut(ecs, |(entity, health, regeneration, hunger, rested)| {
+        if rested.is_some() {
+            commands.remove_component::<Rested>(*entity);
+        }
+        if health.current < 1 || hunger.is_some_and(|hunger| hunger.state() != HungerState::Fed) {
+            regeneration.elapsed = 0;
+            return;
+        }
+        regeneration.elapsed += if rested.is_some() { REST_BONUS } else { 1 };
+        let turns_per_hp = regeneration.turns_per_hp.max(1);
+        while regeneration.elapsed >= turns_per_hp {
+            regeneration.elapsed -= turns_per_hp;
+            health.current = (health.current + 1).min(health.max);
+        }
+    });
+}
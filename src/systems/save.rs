@@ -0,0 +1,43 @@
+use crate::prelude::*;
+
+/// Saves the game to `SAVE_FILE`, or replaces it with the one saved there.
+/// Saving is only allowed between turns, while loading also works once the
+/// player has died.
+#[system]
+pub fn save_game(
+    commands: &mut CommandBuffer,
+    #[resource] key: &Option<VirtualKeyCode>,
+    #[resource] modifiers: &KeyModifiers,
+    #[resource] keybindings: &Keybindings,
+    #[resource] turn_state: &TurnState,
+) {
+    match keybindings.action(*key, *modifiers) {
+        Some(Action::SaveGame) if *turn_state == TurnState::AwaitingInput => {
+            commands.exec_mut(|world, resources| {
+                let message = match SaveGame::capture(world, resources)
+                    .and_then(|save| save.save(SAVE_FILE))
+                {
+                    Ok(()) => ("Game saved.".to_string(), CYAN),
+                    Err(error) => (format!("Could not save: {}.", error), RED),
+                };
+                resources
+                    .get_mut_or_default::<MessageLog>()
+                    .add(message.0, message.1);
+            });
+        }
+        Some(Action::LoadGame) => {
+            commands.exec_mut(|world, resources| {
+                let message = match SaveGame::load(SAVE_FILE)
+                    .and_then(|save| save.restore(world, resources))
+                {
+                    Ok(()) => ("Game loaded.".to_string(), CYAN),
+                    Err(error) => (format!("Could not load: {}.", error), RED),
+                };
+                resources
+                    .get_mut_or_default::<MessageLog>()
+                    .add(message.0, message.1);
+            });
+        }
+        _ => {}
+    }
+}
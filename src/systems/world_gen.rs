@@ -6,34 +6,92 @@ pub fn world_gen(
     #[resource] generator: &mut GeneraotrRunner,
     #[resource] builder: &mut MapBuilder,
     #[resource] camera: &mut Camera,
+    #[resource] debugger: &mut Option<GenerationDebugger>,
+    #[resource] speed: &mut GenerationSpeed,
+    #[resource] key: &Option<VirtualKeyCode>,
 ) {
-    generator.next(builder, rng);
+    if let Some(key) = key {
+        speed.handle_key(*key);
+    }
+    match debugger {
+        Some(debugger) => {
+            if let Some(key) = key {
+                debugger.handle_key(*key, builder, generator, rng);
+            }
+            if !debugger.paused {
+                speed.run_frame(|| {
+                    debugger.step_forward(builder, generator, rng);
+                    !builder.finished
+                });
+            }
+        }
+        None => {
+            if let Some(VirtualKeyCode::End) = key {
+                generator.run_to_completion(builder, rng);
+            }
+            speed.run_frame(|| {
+                generator.next(builder, rng);
+                !builder.finished
+            });
+        }
+    }
     camera.update(builder.point);
 }
 
 #[system]
+#[read_component(Player)]
 pub fn finish_world_gen(
     #[resource] rng: &mut WorldGenRng,
     #[resource] builder: &mut MapBuilder,
+    #[resource] depth: &Depth,
+    #[resource] recipe: &GeneratorRecipe,
+    #[resource] debugger: &Option<GenerationDebugger>,
+    ecs: &SubWorld,
     commands: &mut CommandBuffer,
 ) {
     if !builder.finished {
         return;
     }
+    if let Some(GenerationDebugger {
+        confirmed: false, ..
+    }) = debugger
+    {
+        return;
+    }
     let MapResult { map, player } = builder.build_map();
     let player = player.expect("Failed to place player in worlds");
-    spawn_player(commands, player);
+    match <Entity>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(existing) => commands.add_component(*existing, player),
+        None => spawn_player(commands, player),
+    }
     builder
         .rooms
         .iter()
         .filter(|room| room.center() != player)
         .map(|r| r.center())
         .for_each(|pos| {
-            spawn_monster(commands, rng, pos);
+            spawn_monster(commands, rng, pos, depth.0);
         });
-    commands.exec_mut(move |_, resources| {
+    builder.items.iter().for_each(|pos| {
+        spawn_item(commands, rng, *pos);
+    });
+    builder.keys.iter().for_each(|pos| {
+        spawn_key(commands, *pos);
+    });
+    let theme = recipe.theme_for(depth.0);
+    let depth = depth.0;
+    commands.exec_mut(move |world, resources| {
+        resources.insert(theme);
+        resources.insert(SpatialIndex::build(&map, world));
         resources.insert(map.clone());
         resources.insert(Camera::new(player));
+        resources
+            .get_mut_or_default::<EventChannel>()
+            .publish(GameEvent::LevelEntered { depth });
     });
 }
 
@@ -28,3 +28,43 @@ impl Iterator for PointLine {
         Some(new_point)
     }
 }
+
+/// Walks a Bresenham line from `start` to `end` inclusive, stepping through an
+/// extra orthogonal tile whenever the line moves diagonally so every tile can be
+/// reached with cardinal moves.
+pub struct SteppedLine {
+    line: std::iter::Chain<Bresenham, std::iter::Once<Point>>,
+    previous: Option<Point>,
+    pending: Option<Point>,
+}
+
+impl SteppedLine {
+    pub fn new(start: Point, end: Point) -> Self {
+        Self {
+            line: Bresenham::new(start, end).chain(std::iter::once(end)),
+            previous: None,
+            pending: None,
+        }
+    }
+}
+
+impl Iterator for SteppedLine {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pending) = self.pending.take() {
+            self.previous = Some(pending);
+            return Some(pending);
+        }
+        let next = self.line.next()?;
+        let out = match self.previous {
+            Some(prev) if prev.x != next.x && prev.y != next.y => {
+                self.pending = Some(next);
+                Point::new(next.x, prev.y)
+            }
+            _ => next,
+        };
+        self.previous = Some(out);
+        Some(out)
+    }
+}
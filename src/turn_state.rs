@@ -3,4 +3,6 @@ pub enum TurnState {
     AwaitingInput,
     PlayerTurn,
     MonsterTurn,
+    /// The player has died. Only loading a saved game is left to do.
+    GameOver,
 }
@@ -0,0 +1,97 @@
+use ferros_rts::prelude::*;
+use ferros_rts::systems;
+
+#[test]
+fn a_hunter_searches_once_it_reaches_the_last_known_position() {
+    let last_known = Point::new(4, 4);
+    let mut awareness = Awareness::alert(AlertState::Hunting, last_known);
+
+    awareness.forget(Point::new(3, 4));
+    assert_eq!(awareness.state, AlertState::Hunting);
+    assert_eq!(awareness.memory, Awareness::MEMORY_TURNS - 1);
+
+    awareness.forget(last_known);
+    assert_eq!(awareness.state, AlertState::Searching);
+    assert_eq!(awareness.memory, Awareness::SEARCH_TURNS);
+    assert_eq!(awareness.last_known, Some(last_known));
+}
+
+#[test]
+fn a_hunter_searches_once_it_runs_out_of_memory() {
+    let mut awareness = Awareness::alert(AlertState::Hunting, Point::new(20, 20));
+    let away = Point::new(0, 0);
+    for _ in 1..Awareness::MEMORY_TURNS {
+        awareness.forget(away);
+        assert_eq!(awareness.state, AlertState::Hunting);
+    }
+    awareness.forget(away);
+    assert_eq!(awareness.state, AlertState::Searching);
+    assert_eq!(awareness.memory, Awareness::SEARCH_TURNS);
+}
+
+#[test]
+fn a_searcher_settles_back_to_idle() {
+    let mut awareness = Awareness::alert(AlertState::Suspicious, Point::new(4, 4));
+    awareness.forget(Point::new(4, 4));
+    assert_eq!(awareness.state, AlertState::Searching);
+
+    for turn in 1..Awareness::SEARCH_TURNS {
+        awareness.forget(Point::new(4, 4));
+        assert_eq!(awareness.state, AlertState::Searching, "turn {}", turn);
+        assert_eq!(awareness.memory, Awareness::SEARCH_TURNS - turn);
+    }
+    awareness.forget(Point::new(4, 4));
+    assert_eq!(awareness, Awareness::default());
+}
+
+#[test]
+fn an_idle_monster_has_nothing_to_forget() {
+    let mut awareness = Awareness::default();
+    awareness.forget(Point::new(1, 1));
+    assert_eq!(awareness, Awareness::default());
+}
+
+#[test]
+fn monsters_hear_fights_started_during_their_own_turn() {
+    let mut ecs = World::default();
+    let mut resources = Resources::default();
+    // A wall between the fight and a monster close enough to hear it
+    let mut map = Map::new(20, 10);
+    (0..10).for_each(|y| map.set_tile(Point::new(8, y), TileType::Wall));
+    let player = ecs.push((Player, Point::new(5, 5)));
+    let attacker = ecs.push((Enemy, Point::new(6, 5), Awareness::default()));
+    let listener = ecs.push((Enemy, Point::new(11, 5), Awareness::default()));
+    resources.insert(SpatialIndex::build(&map, &ecs));
+    resources.insert(map);
+    resources.insert(Noises::default());
+    let mut events = EventChannel::default();
+    events.publish(GameEvent::AttackResolved {
+        attacker,
+        victim: player,
+        hit: true,
+        damage: 1,
+    });
+    resources.insert(events);
+
+    // The input schedule reads the monster turn's events first, and the next
+    // monster turn only listens after that
+    let mut read_events = Schedule::builder()
+        .add_system(systems::record_noises_system())
+        .add_system(systems::clear_events_system())
+        .build();
+    read_events.execute(&mut ecs, &mut resources);
+    let mut monster_turn = Schedule::builder()
+        .add_system(systems::record_noises_system())
+        .add_system(systems::clear_events_system())
+        .add_system(systems::perception_system())
+        .build();
+    monster_turn.execute(&mut ecs, &mut resources);
+
+    let awareness = *ecs
+        .entry_ref(listener)
+        .unwrap()
+        .get_component::<Awareness>()
+        .unwrap();
+    assert_eq!(awareness.state, AlertState::Suspicious);
+    assert_eq!(awareness.last_known, Some(Point::new(5, 5)));
+}
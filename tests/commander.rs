@@ -0,0 +1,83 @@
+use ferros_rts::prelude::*;
+
+/// What each faction got up to while two commanders played each other
+#[derive(Default)]
+struct Record {
+    gathered: bool,
+    queued: bool,
+    /// The most of its soldiers near an enemy base at once
+    attackers: usize,
+}
+
+fn base_of(skirmish: &Skirmish, faction: Faction) -> Option<Point> {
+    skirmish
+        .units(faction)
+        .into_iter()
+        .find(|unit| unit.kind == UnitKind::Base)
+        .map(|unit| unit.pos)
+}
+
+/// Plays every faction with a `difficulty` commander until someone wins or
+/// `turns` have passed
+fn play(difficulty: Difficulty, turns: u32) -> (Skirmish, Vec<Record>) {
+    let mut skirmish = Skirmish::new(&SkirmishSettings::default());
+    let factions = skirmish.factions();
+    let mut commanders: Vec<_> = factions
+        .iter()
+        .map(|faction| Commander::new(*faction, difficulty))
+        .collect();
+    let bases: Vec<Point> = factions
+        .iter()
+        .map(|faction| base_of(&skirmish, *faction).expect("every faction starts with a base"))
+        .collect();
+    let mut records: Vec<Record> = factions.iter().map(|_| Record::default()).collect();
+
+    while skirmish.turn() < turns && skirmish.winner().is_none() {
+        let stockpiles: Vec<u32> = factions.iter().map(|f| skirmish.stockpile(*f)).collect();
+        let mut orders = Vec::new();
+        for commander in &mut commanders {
+            let given = commander.orders(&skirmish);
+            assert!(given
+                .iter()
+                .all(|order| order.faction == commander.faction()));
+            orders.extend(given);
+        }
+        skirmish.advance(orders);
+
+        for (index, faction) in factions.iter().enumerate() {
+            let record = &mut records[index];
+            record.gathered |= skirmish.stockpile(*faction) > stockpiles[index];
+            record.queued |= !skirmish.production(*faction).is_empty();
+            let attackers = skirmish
+                .units(*faction)
+                .iter()
+                .filter(|unit| {
+                    unit.kind == UnitKind::Soldier
+                        && bases.iter().enumerate().any(|(other, base)| {
+                            other != index
+                                && DistanceAlg::Pythagoras.distance2d(unit.pos, *base) < 12.0
+                        })
+                })
+                .count();
+            record.attackers = record.attackers.max(attackers);
+        }
+    }
+    (skirmish, records)
+}
+
+#[test]
+fn hard_commanders_gather_train_and_attack() {
+    let (skirmish, records) = play(Difficulty::Hard, 1100);
+
+    for (faction, record) in skirmish.factions().iter().zip(&records) {
+        assert!(record.gathered, "{:?} never gathered", faction);
+        assert!(record.queued, "{:?} never trained a unit", faction);
+    }
+    // Several at once, so an army was sent and not just a scout
+    let attackers = records.iter().map(|record| record.attackers).max();
+    assert!(
+        skirmish.winner().is_some() || attackers >= Some(3),
+        "nobody attacked in {} turns",
+        skirmish.turn()
+    );
+}
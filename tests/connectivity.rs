@@ -0,0 +1,96 @@
+use ferros_rts::prelude::*;
+
+const SEEDS: u64 = 1000;
+
+/// Generates `SEEDS` maps from the recipe and checks that the stairs and every
+/// item and key can be reached from the spawn, as can the centre of every room
+/// unless `rooms_may_be_sealed`
+fn assert_connected(name: &str, source: &str, rooms_may_be_sealed: bool) {
+    let recipe = GeneratorRecipe::parse(source).expect("recipe is invalid");
+    for seed in 0..SEEDS {
+        let mut builder = recipe.builder();
+        let mut rng = WorldGenRng::seeded(seed);
+        recipe.runner().run_to_completion(&mut builder, &mut rng);
+
+        let MapResult { map, player } = builder.build_map();
+        let spawn = player.expect("the recipe places the player");
+        let reachable = map.reachable_from(spawn);
+        let can_reach = |point: &Point| reachable[map.point2d_to_index(*point)];
+
+        for room in builder.rooms.iter() {
+            let center = room.center();
+            if rooms_may_be_sealed && builder.sealed.contains(&center) {
+                continue;
+            }
+            assert!(
+                !builder.sealed.contains(&center),
+                "{} seed {}: the room at {:?} was sealed",
+                name,
+                seed,
+                center
+            );
+            assert!(
+                can_reach(&center),
+                "{} seed {}: the room at {:?} can't be reached",
+                name,
+                seed,
+                center
+            );
+        }
+        let stairs = builder.stairs_up.iter().chain(builder.stairs_down.iter());
+        for point in stairs
+            .chain(builder.items.iter())
+            .chain(builder.keys.iter())
+        {
+            assert!(
+                can_reach(point),
+                "{} seed {}: {:?} can't be reached",
+                name,
+                seed,
+                point
+            );
+        }
+        assert!(
+            builder.connectivity_report().is_connected(),
+            "{} seed {}",
+            name,
+            seed
+        );
+    }
+}
+
+#[test]
+fn default_recipe_is_always_connected() {
+    assert_connected("default", include_str!("../recipes/default.ron"), false);
+}
+
+#[test]
+fn chain_recipe_is_always_connected() {
+    assert_connected("chain", include_str!("../recipes/chain.ron"), false);
+}
+
+#[test]
+fn winding_recipe_is_always_connected() {
+    assert_connected("winding", include_str!("../recipes/winding.ron"), true);
+}
+
+#[test]
+fn tunnels_are_bridged_across_chasms() {
+    assert_connected(
+        "chasms",
+        "(
+            width: 60,
+            height: 60,
+            commands: [
+                FillMap(Wall),
+                GenerateRooms(num_of_rooms: 10, max_room_size: 8),
+                ScatterTiles(tile: Chasm, per_room: 12),
+                PlacePlayerInRoom,
+                PlaceStairs,
+                PlaceItems(per_room: 2),
+                EnsureConnectivity(Tunnel),
+            ],
+        )",
+        false,
+    );
+}
@@ -0,0 +1,95 @@
+use ferros_rts::prelude::*;
+
+fn press(key: VirtualKeyCode, modifiers: KeyModifiers) -> StepInput {
+    StepInput {
+        key: Some(key),
+        modifiers,
+        click: None,
+    }
+}
+
+/// A click on the screen where `at` on the map is drawn
+fn click(skirmish: &Skirmish, at: Point) -> StepInput {
+    let camera = skirmish
+        .resources
+        .get::<Camera>()
+        .expect("Skirmish has a Camera");
+    StepInput {
+        click: Some(at - camera.top_left_corner()),
+        ..StepInput::default()
+    }
+}
+
+const CTRL: KeyModifiers = KeyModifiers {
+    shift: false,
+    control: true,
+    alt: false,
+};
+
+const ALT: KeyModifiers = KeyModifiers {
+    shift: false,
+    control: false,
+    alt: true,
+};
+
+#[test]
+fn recalled_groups_move_in_their_own_formation() {
+    let mut skirmish = Skirmish::new(&SkirmishSettings::default());
+    skirmish.take_command(Faction(0), Keybindings::default());
+    let soldiers: Vec<UnitInfo> = skirmish
+        .units(Faction(0))
+        .into_iter()
+        .filter(|unit| unit.kind == UnitKind::Soldier)
+        .collect();
+    let target = *skirmish.rooms().last().expect("the map has rooms");
+
+    // Pick out the first soldier, save them as group 1 and switch formation
+    assert!(skirmish
+        .command(click(&skirmish, soldiers[0].pos))
+        .is_empty());
+    assert!(skirmish
+        .command(press(VirtualKeyCode::Key1, CTRL))
+        .is_empty());
+    assert!(skirmish
+        .command(press(VirtualKeyCode::V, KeyModifiers::default()))
+        .is_empty());
+
+    // Recalling the group after picking out someone else brings back only the
+    // first soldier, in the formation the group was switched to
+    skirmish.command(click(&skirmish, soldiers[1].pos));
+    skirmish.command(press(VirtualKeyCode::Key1, ALT));
+    let orders = skirmish.command(click(&skirmish, target));
+
+    assert_eq!(
+        orders,
+        vec![Order::new(
+            Faction(0),
+            vec![soldiers[0].id],
+            Command::MoveTo {
+                x: target.x,
+                y: target.y,
+                formation: Formation::default().next(),
+            },
+        )]
+    );
+
+    skirmish.advance(orders);
+    let moved = skirmish
+        .units(Faction(0))
+        .into_iter()
+        .find(|unit| unit.id == soldiers[0].id)
+        .expect("the soldier is still standing");
+    assert!(!moved.idle);
+}
+
+#[test]
+fn units_that_are_not_yours_cannot_be_picked_out() {
+    let mut skirmish = Skirmish::new(&SkirmishSettings::default());
+    skirmish.take_command(Faction(0), Keybindings::default());
+    let theirs = skirmish.units(Faction(1))[0];
+
+    skirmish.command(click(&skirmish, theirs.pos));
+    assert!(skirmish
+        .command(click(&skirmish, Point::new(1, 1)))
+        .is_empty());
+}
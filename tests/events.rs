@@ -0,0 +1,124 @@
+use ferros_rts::prelude::*;
+use ferros_rts::systems;
+
+/// Runs every consumer over `events` the way a game schedule opens
+fn consume(ecs: &mut World, resources: &mut Resources, events: Vec<GameEvent>) {
+    let mut channel = EventChannel::default();
+    for event in events {
+        channel.publish(event);
+    }
+    resources.insert(channel);
+    Schedule::builder()
+        .add_system(systems::message_log_system())
+        .add_system(systems::statistics_system())
+        .add_system(systems::achievements_system())
+        .add_system(systems::replay_turns_system())
+        .add_system(systems::clear_events_system())
+        .build()
+        .execute(ecs, resources);
+}
+
+fn game() -> (World, Resources, Entity) {
+    let mut ecs = World::default();
+    let mut resources = Resources::default();
+    let player = ecs.push((Player, Point::new(1, 1), Name::from("Hero")));
+    resources.insert(MessageLog::default());
+    resources.insert(Statistics::default());
+    resources.insert(Achievements::default());
+    resources.insert(ReplayTurns::default());
+    (ecs, resources, player)
+}
+
+fn kill_by(killer: Entity, victim: Entity) -> GameEvent {
+    GameEvent::UnitDied {
+        entity: victim,
+        killer: Some(killer),
+        name: Some("Goblin".to_string()),
+        at: None,
+        was_player: false,
+    }
+}
+
+#[test]
+fn every_consumer_reads_the_same_events() {
+    let (mut ecs, mut resources, player) = game();
+    let goblin = ecs.push((Name::from("Goblin"),));
+    consume(
+        &mut ecs,
+        &mut resources,
+        vec![kill_by(player, goblin), GameEvent::TurnEnded],
+    );
+
+    assert_eq!(resources.get::<Statistics>().unwrap().kills, 1);
+    assert_eq!(resources.get::<Statistics>().unwrap().turns, 1);
+    assert!(resources
+        .get::<Achievements>()
+        .unwrap()
+        .has(Achievement::FirstBlood));
+    assert_eq!(*resources.get::<ReplayTurns>().unwrap(), ReplayTurns(1));
+    assert!(resources.get::<EventChannel>().unwrap().is_empty());
+}
+
+#[test]
+fn achievements_are_unlocked_and_announced_once() {
+    let (mut ecs, mut resources, player) = game();
+    let goblins: Vec<_> = (0..Achievements::SLAYER_KILLS)
+        .map(|_| ecs.push((Name::from("Goblin"),)))
+        .collect();
+    for goblin in goblins {
+        consume(&mut ecs, &mut resources, vec![kill_by(player, goblin)]);
+    }
+
+    let achievements = resources.get::<Achievements>().unwrap();
+    assert_eq!(
+        achievements.unlocked,
+        [Achievement::FirstBlood, Achievement::Slayer]
+    );
+    let announced = resources
+        .get::<MessageLog>()
+        .unwrap()
+        .messages
+        .iter()
+        .filter(|(text, _)| text.starts_with("Achievement unlocked"))
+        .count();
+    assert_eq!(announced, 2);
+}
+
+#[test]
+fn only_the_player_earns_achievements() {
+    let (mut ecs, mut resources, _) = game();
+    let orc = ecs.push((Name::from("Orc"),));
+    let goblin = ecs.push((Name::from("Goblin"),));
+    let item = ecs.push((Item,));
+    consume(
+        &mut ecs,
+        &mut resources,
+        vec![
+            kill_by(orc, goblin),
+            GameEvent::ItemPickedUp { entity: orc, item },
+            GameEvent::LevelEntered {
+                depth: Achievements::DEEP_DELVER_DEPTH - 1,
+            },
+        ],
+    );
+    assert_eq!(
+        *resources.get::<Achievements>().unwrap(),
+        Achievements {
+            unlocked: Vec::new(),
+            kills: 0,
+            items_picked_up: 0,
+        }
+    );
+
+    consume(
+        &mut ecs,
+        &mut resources,
+        vec![GameEvent::LevelEntered {
+            depth: Achievements::DEEP_DELVER_DEPTH,
+        }],
+    );
+    assert!(resources
+        .get::<Achievements>()
+        .unwrap()
+        .has(Achievement::DeepDelver));
+}
@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use ferros_rts::prelude::*;
+
+/// A skirmish on `map` with only the given units in it, numbered in order
+fn skirmish_on(map: Map, units: &[(UnitKind, Point)]) -> (Skirmish, Vec<UnitId>) {
+    let mut skirmish = Skirmish::new(&SkirmishSettings::default());
+    skirmish.ecs.clear();
+    let mut commands = CommandBuffer::new(&skirmish.ecs);
+    let ids: Vec<UnitId> = units
+        .iter()
+        .enumerate()
+        .map(|(n, (kind, pos))| {
+            let id = UnitId(n as u32);
+            spawn_unit(&mut commands, *kind, Faction(0), id, *pos);
+            id
+        })
+        .collect();
+    commands.flush(&mut skirmish.ecs, &mut skirmish.resources);
+    skirmish
+        .resources
+        .insert(SpatialIndex::build(&map, &skirmish.ecs));
+    skirmish.resources.insert(map);
+    (skirmish, ids)
+}
+
+fn move_to(ids: &[UnitId], target: Point, formation: Formation) -> Vec<Order> {
+    vec![Order::new(
+        Faction(0),
+        ids.to_vec(),
+        Command::MoveTo {
+            x: target.x,
+            y: target.y,
+            formation,
+        },
+    )]
+}
+
+fn formation(skirmish: &Skirmish) -> Option<FormationMove> {
+    <&FormationMove>::query()
+        .iter(&skirmish.ecs)
+        .next()
+        .cloned()
+}
+
+fn position(skirmish: &Skirmish, id: UnitId) -> Point {
+    skirmish
+        .units(Faction(0))
+        .into_iter()
+        .find(|unit| unit.id == id)
+        .expect("the unit is still standing")
+        .pos
+}
+
+fn steps_between(a: Point, b: Point) -> i32 {
+    (a.x - b.x).abs().max((a.y - b.y).abs())
+}
+
+#[test]
+fn a_line_files_through_a_doorway_past_a_base() {
+    // A wall down the middle with a one tile gap, and a base just past it
+    let mut map = Map::new(24, 11);
+    (0..11)
+        .filter(|y| *y != 5)
+        .for_each(|y| map.set_tile(Point::new(10, y), TileType::Wall));
+    let base = Point::new(11, 4);
+    let soldiers = [
+        Point::new(3, 4),
+        Point::new(3, 5),
+        Point::new(3, 6),
+        Point::new(2, 5),
+    ];
+    let mut units = vec![(UnitKind::Base, base)];
+    units.extend(soldiers.iter().map(|pos| (UnitKind::Soldier, *pos)));
+    let (mut skirmish, ids) = skirmish_on(map.clone(), &units);
+    let soldiers = &ids[1..];
+    let destination = Point::new(18, 5);
+
+    skirmish.advance(move_to(soldiers, destination, Formation::Line));
+    let mut progress = formation(&skirmish).expect("the soldiers form up").progress;
+    for turn in 0..80 {
+        let standing: Vec<Point> = soldiers.iter().map(|id| position(&skirmish, *id)).collect();
+        let tiles: HashSet<Point> = standing.iter().copied().collect();
+        assert_eq!(tiles.len(), standing.len(), "turn {}: {:?}", turn, standing);
+        assert!(!tiles.contains(&base), "turn {}: on the base", turn);
+
+        let group = match formation(&skirmish) {
+            Some(group) => group,
+            None => break,
+        };
+        for member in &group.members {
+            assert!(
+                map.can_enter_tile(member.target) && member.target != base,
+                "turn {}: a place at {:?}",
+                turn,
+                member.target
+            );
+        }
+        assert!(group.progress <= progress + 1, "turn {}", turn);
+        progress = group.progress;
+        assert!(
+            standing
+                .iter()
+                .any(|pos| steps_between(*pos, group.front()) <= 1),
+            "turn {}: the front at {:?} left everyone behind at {:?}",
+            turn,
+            group.front(),
+            standing
+        );
+        skirmish.advance(Vec::new());
+    }
+
+    assert!(
+        formation(&skirmish).is_none(),
+        "the formation never arrived"
+    );
+    for id in soldiers {
+        assert!(position(&skirmish, *id).x > 10, "{:?} was left behind", id);
+    }
+}
+
+#[test]
+fn the_front_waits_for_stragglers() {
+    let straggler = Point::new(0, 5);
+    let units = [
+        (UnitKind::Soldier, Point::new(5, 4)),
+        (UnitKind::Soldier, Point::new(5, 5)),
+        (UnitKind::Soldier, Point::new(5, 6)),
+        (UnitKind::Soldier, straggler),
+    ];
+    // The straggler has to go around a wall before it can follow
+    let mut map = Map::new(30, 11);
+    (3..=7).for_each(|y| map.set_tile(Point::new(1, y), TileType::Wall));
+    let (mut skirmish, ids) = skirmish_on(map, &units);
+
+    skirmish.advance(move_to(&ids, Point::new(25, 5), Formation::Box));
+    let start = formation(&skirmish).expect("the soldiers form up").front();
+    let behind = steps_between(start, straggler);
+    let mut turns = 0;
+    while let Some(group) = formation(&skirmish) {
+        let gap = steps_between(group.front(), position(&skirmish, ids[3]));
+        assert!(
+            gap <= behind,
+            "turn {}: the front got {} steps ahead",
+            turns,
+            gap
+        );
+        skirmish.advance(Vec::new());
+        turns += 1;
+        assert!(turns < 100, "the formation never arrived");
+    }
+}
@@ -0,0 +1,103 @@
+use ferros_rts::prelude::*;
+use ferros_rts::systems;
+
+fn run(ecs: &mut World, system: impl legion::systems::ParallelRunnable + 'static) -> Resources {
+    let mut resources = Resources::default();
+    resources.insert(SpatialIndex::build(&Map::new(10, 10), ecs));
+    resources.insert(EventChannel::default());
+    let mut schedule = Schedule::builder().add_system(system).build();
+    schedule.execute(ecs, &mut resources);
+    resources
+}
+
+fn hurt_player(ecs: &mut World) -> Entity {
+    ecs.push((
+        Player,
+        Point::new(2, 2),
+        Health {
+            current: 1,
+            max: 10,
+        },
+    ))
+}
+
+fn health(ecs: &World, entity: Entity) -> i32 {
+    ecs.entry_ref(entity)
+        .unwrap()
+        .get_component::<Health>()
+        .unwrap()
+        .current
+}
+
+#[test]
+fn a_blast_does_not_kill_the_dead_again() {
+    let mut ecs = World::default();
+    let player = hurt_player(&mut ecs);
+    let scroll = ecs.push((
+        Item,
+        Carried { owner: player },
+        DamagesNearby {
+            damage: 5,
+            radius: 3,
+        },
+    ));
+    ecs.push((Enemy, Point::new(3, 2), Health { current: 0, max: 5 }));
+    ecs.push((
+        (),
+        WantsToUseItem {
+            user: player,
+            item: scroll,
+        },
+    ));
+
+    let resources = run(&mut ecs, systems::use_items_system());
+    let died = resources
+        .get::<EventChannel>()
+        .unwrap()
+        .iter()
+        .any(|event| matches!(event, GameEvent::UnitDied { .. }));
+    assert!(!died);
+}
+
+#[test]
+fn items_on_the_floor_cannot_be_used() {
+    let mut ecs = World::default();
+    let player = hurt_player(&mut ecs);
+    let potion = ecs.push((
+        Item,
+        Consumable,
+        Point::new(2, 2),
+        ProvidesHealing { amount: 5 },
+    ));
+    ecs.push((
+        (),
+        WantsToUseItem {
+            user: player,
+            item: potion,
+        },
+    ));
+
+    run(&mut ecs, systems::use_items_system());
+    assert_eq!(health(&ecs, player), 1);
+    assert!(ecs.entry_ref(potion).is_ok());
+}
+
+#[test]
+fn items_held_by_someone_else_cannot_be_dropped() {
+    let mut ecs = World::default();
+    let player = hurt_player(&mut ecs);
+    let goblin = ecs.push((Enemy, Point::new(5, 5)));
+    let sword = ecs.push((Item, Carried { owner: goblin }));
+    ecs.push((
+        (),
+        WantsToDrop {
+            entity: player,
+            item: sword,
+        },
+    ));
+
+    run(&mut ecs, systems::drop_items_system());
+    let entry = ecs.entry_ref(sword).unwrap();
+    assert_eq!(entry.get_component::<Carried>().unwrap().owner, goblin);
+    assert!(entry.get_component::<Point>().is_err());
+}
@@ -0,0 +1,42 @@
+use ferros_rts::prelude::*;
+
+#[test]
+fn default_keys_are_each_bound_once() {
+    let defaults = Keybindings::default();
+    let config = ron::ser::to_string(&defaults).expect("defaults serialize");
+    assert!(Keybindings::parse(&config).is_ok());
+}
+
+#[test]
+fn a_key_bound_to_two_actions_is_rejected() {
+    let error = Keybindings::parse(r#"(bindings: [(Wait, ["Space"]), (Rest, ["R", "Space"])])"#)
+        .expect_err("Space is bound twice");
+    let message = error.to_string();
+    assert!(message.contains("Wait"), "{}", message);
+    assert!(message.contains("Rest"), "{}", message);
+}
+
+#[test]
+fn merged_defaults_leave_out_keys_already_taken() {
+    let keybindings = Keybindings::parse(r#"(bindings: [(Wait, ["R"])])"#)
+        .expect("R is only bound once by the config");
+    assert_eq!(
+        keybindings.action(Some(VirtualKeyCode::R), KeyModifiers::default()),
+        Some(Action::Wait)
+    );
+    assert!(keybindings.keys_for(Action::Rest).is_empty());
+}
+
+#[test]
+fn plain_number_keys_are_left_to_the_inventory_and_level_ups() {
+    let defaults = Keybindings::default();
+    let digits = [
+        VirtualKeyCode::Key1,
+        VirtualKeyCode::Key2,
+        VirtualKeyCode::Key3,
+        VirtualKeyCode::Key4,
+    ];
+    for key in digits {
+        assert_eq!(defaults.action(Some(key), KeyModifiers::default()), None);
+    }
+}
@@ -0,0 +1,29 @@
+use ferros_rts::prelude::*;
+
+#[test]
+fn every_player_ends_with_the_same_world() {
+    let outcomes = play_local(&SkirmishSettings::default(), 200, Difficulty::Normal, None)
+        .expect("the local match should finish");
+
+    assert_eq!(outcomes.len(), 2);
+    assert_eq!(outcomes[0].turns, outcomes[1].turns);
+    assert_eq!(outcomes[0].checksum, outcomes[1].checksum);
+}
+
+#[test]
+fn the_host_catches_a_player_out_of_step() {
+    let result = play_local(
+        &SkirmishSettings::default(),
+        50,
+        Difficulty::Normal,
+        Some(10),
+    );
+
+    match result {
+        Err(LockstepError::Desync { turn, faction }) => {
+            assert_eq!(turn, 10);
+            assert_eq!(faction, Faction(1));
+        }
+        other => panic!("expected a desync, got {:?}", other),
+    }
+}
@@ -0,0 +1,58 @@
+use ferros_rts::prelude::*;
+use ferros_rts::systems;
+
+fn run_metabolism(ecs: &mut World) -> Resources {
+    let mut resources = Resources::default();
+    resources.insert(EventChannel::default());
+    resources.insert(SpatialIndex::default());
+    resources.insert(TurnState::MonsterTurn);
+    let mut schedule = Schedule::builder()
+        .add_system(systems::metabolism_system())
+        .flush()
+        .add_system(systems::end_turn_system())
+        .build();
+    schedule.execute(ecs, &mut resources);
+    resources
+}
+
+#[test]
+fn starving_to_death_ends_the_game() {
+    let mut ecs = World::default();
+    let player = ecs.push((
+        Player,
+        Point::new(1, 1),
+        Health {
+            current: 1,
+            max: 10,
+        },
+        Hunger {
+            // Far enough below zero that the next turn costs a point of health
+            satiety: -1000,
+            max: 1000,
+        },
+    ));
+
+    let resources = run_metabolism(&mut ecs);
+
+    assert!(ecs.entry(player).is_some());
+    assert_eq!(*resources.get::<TurnState>().unwrap(), TurnState::GameOver);
+}
+
+#[test]
+fn regeneration_never_takes_less_than_a_turn() {
+    assert_eq!(Regeneration::new(0).turns_per_hp, 1);
+
+    let mut ecs = World::default();
+    let unit = ecs.push((
+        Health { current: 1, max: 5 },
+        Regeneration {
+            turns_per_hp: 0,
+            elapsed: 0,
+        },
+    ));
+
+    run_metabolism(&mut ecs);
+
+    let health = *ecs.entry(unit).unwrap().get_component::<Health>().unwrap();
+    assert_eq!(health.current, 2);
+}
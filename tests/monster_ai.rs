@@ -0,0 +1,230 @@
+use ferros_rts::prelude::*;
+use ferros_rts::systems;
+
+const PLAYER: Point = Point { x: 11, y: 10 };
+const MONSTER: Point = Point { x: 10, y: 10 };
+
+/// An open map with the player on it, moving on cardinal steps only
+fn arena() -> (World, Resources, Entity) {
+    let mut ecs = World::default();
+    let mut resources = Resources::default();
+    let player = ecs.push((Player, PLAYER, Health::new(10)));
+    resources.insert(Map::new(20, 20));
+    resources.insert(MovementRules { diagonals: false });
+    resources.insert(GameRng::seeded(0));
+    (ecs, resources, player)
+}
+
+/// A monster with `nodes` at `pos` that can see `enemy`, if given
+fn monster(
+    ecs: &mut World,
+    pos: Point,
+    nodes: Vec<BehaviourNode>,
+    health: Health,
+    enemy: Option<Entity>,
+) -> Entity {
+    let awareness = Awareness {
+        in_sight: enemy,
+        ..Awareness::default()
+    };
+    ecs.push((Enemy, pos, Behaviour(nodes), health, awareness))
+}
+
+fn think(ecs: &mut World, resources: &mut Resources) {
+    let map = resources.get::<Map>().unwrap().clone();
+    resources.insert(SpatialIndex::build(&map, ecs));
+    let mut schedule = Schedule::builder()
+        .add_system(systems::monster_ai_system())
+        .build();
+    schedule.execute(ecs, resources);
+}
+
+fn decision(ecs: &World, entity: Entity) -> Decision {
+    *ecs.entry_ref(entity)
+        .unwrap()
+        .get_component::<Decision>()
+        .unwrap()
+}
+
+fn moves(ecs: &World) -> Vec<Point> {
+    <&WantsToMove>::query()
+        .iter(ecs)
+        .map(|wants| wants.destination)
+        .collect()
+}
+
+fn state(ecs: &World, entity: Entity) -> AlertState {
+    ecs.entry_ref(entity)
+        .unwrap()
+        .get_component::<Awareness>()
+        .unwrap()
+        .state
+}
+
+#[test]
+fn hurt_monsters_flee_from_the_enemy() {
+    let (mut ecs, mut resources, player) = arena();
+    let nodes = vec![BehaviourNode::Flee { below_percent: 34 }];
+    let hurt = Health {
+        current: 3,
+        max: 10,
+    };
+    let fleeing = monster(&mut ecs, MONSTER, nodes, hurt, Some(player));
+    think(&mut ecs, &mut resources);
+
+    assert_eq!(decision(&ecs, fleeing), Decision::Flee);
+    assert_eq!(moves(&ecs), vec![Point::new(9, 10)]);
+}
+
+#[test]
+fn healthy_monsters_stand_their_ground() {
+    let (mut ecs, mut resources, player) = arena();
+    let nodes = vec![BehaviourNode::Flee { below_percent: 34 }];
+    let healthy = Health {
+        current: 4,
+        max: 10,
+    };
+    let standing = monster(&mut ecs, MONSTER, nodes, healthy, Some(player));
+    think(&mut ecs, &mut resources);
+
+    assert_eq!(decision(&ecs, standing), Decision::Idle);
+    assert!(moves(&ecs).is_empty());
+}
+
+#[test]
+fn keeping_range_backs_off_and_closes_in() {
+    let nodes = vec![BehaviourNode::KeepRange { min: 3, max: 6 }];
+    let cases = [
+        (MONSTER, Decision::BackOff, Some(Point::new(9, 10))),
+        (
+            Point::new(3, 10),
+            Decision::Approach,
+            Some(Point::new(4, 10)),
+        ),
+        (Point::new(7, 10), Decision::Idle, None),
+    ];
+    for (pos, expected, step) in cases {
+        let (mut ecs, mut resources, player) = arena();
+        let archer = monster(&mut ecs, pos, nodes.clone(), Health::new(10), Some(player));
+        think(&mut ecs, &mut resources);
+
+        assert_eq!(decision(&ecs, archer), expected, "from {:?}", pos);
+        assert_eq!(moves(&ecs), step.into_iter().collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn chasers_attack_an_enemy_next_to_them() {
+    let (mut ecs, mut resources, player) = arena();
+    let chaser = monster(
+        &mut ecs,
+        MONSTER,
+        vec![BehaviourNode::Chase],
+        Health::new(10),
+        Some(player),
+    );
+    think(&mut ecs, &mut resources);
+
+    let attacks: Vec<WantsToAttack> = <&WantsToAttack>::query().iter(&ecs).copied().collect();
+    assert_eq!(
+        attacks,
+        vec![WantsToAttack {
+            attacker: chaser,
+            victim: player,
+        }]
+    );
+}
+
+#[test]
+fn only_allies_that_cannot_see_the_enemy_are_called() {
+    let (mut ecs, mut resources, player) = arena();
+    // A wall hides the player from everything below it
+    let mut map = Map::new(20, 20);
+    (0..20).for_each(|x| map.set_tile(Point::new(x, 13), TileType::Wall));
+    resources.insert(map);
+
+    let nodes = vec![BehaviourNode::CallAllies { radius: 8 }];
+    monster(&mut ecs, MONSTER, nodes, Health::new(10), Some(player));
+    let hidden = ecs.push((
+        Enemy,
+        Point::new(10, 16),
+        Behaviour::brute(),
+        Awareness::default(),
+    ));
+    let watching = ecs.push((
+        Enemy,
+        Point::new(12, 12),
+        Behaviour::brute(),
+        Awareness::default(),
+    ));
+    let far = ecs.push((
+        Enemy,
+        Point::new(10, 19),
+        Behaviour::brute(),
+        Awareness::default(),
+    ));
+    let mindless = ecs.push((Enemy, Point::new(9, 16), Awareness::default()));
+    think(&mut ecs, &mut resources);
+
+    assert_eq!(state(&ecs, hidden), AlertState::Hunting);
+    let last_known = ecs
+        .entry_ref(hidden)
+        .unwrap()
+        .get_component::<Awareness>()
+        .unwrap()
+        .last_known;
+    assert_eq!(last_known, Some(PLAYER));
+    assert_eq!(state(&ecs, watching), AlertState::Idle);
+    assert_eq!(state(&ecs, far), AlertState::Idle);
+    assert_eq!(state(&ecs, mindless), AlertState::Idle);
+}
+
+#[test]
+fn guards_walk_back_to_their_post() {
+    let post = Point::new(5, 10);
+    let nodes = vec![BehaviourNode::GuardRoom { post, radius: 2 }];
+    let cases = [
+        (MONSTER, Decision::ReturnToPost, Some(Point::new(9, 10))),
+        (Point::new(6, 11), Decision::Idle, None),
+    ];
+    for (pos, expected, step) in cases {
+        let (mut ecs, mut resources, _) = arena();
+        let guard = monster(&mut ecs, pos, nodes.clone(), Health::new(10), None);
+        think(&mut ecs, &mut resources);
+
+        assert_eq!(decision(&ecs, guard), expected, "from {:?}", pos);
+        assert_eq!(moves(&ecs), step.into_iter().collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn looters_fetch_and_pick_up_items_but_leave_keys() {
+    let nodes = vec![BehaviourNode::PickUpItems { radius: 5 }];
+
+    let (mut ecs, mut resources, _) = arena();
+    let looter = monster(&mut ecs, MONSTER, nodes.clone(), Health::new(10), None);
+    let underfoot = ecs.push((Item, MONSTER));
+    think(&mut ecs, &mut resources);
+    assert_eq!(decision(&ecs, looter), Decision::PickUp);
+    let pickups: Vec<WantsToPickUp> = <&WantsToPickUp>::query().iter(&ecs).copied().collect();
+    assert_eq!(
+        pickups,
+        vec![WantsToPickUp {
+            entity: looter,
+            item: underfoot,
+        }]
+    );
+
+    let (mut ecs, mut resources, _) = arena();
+    let looter = monster(&mut ecs, MONSTER, nodes.clone(), Health::new(10), None);
+    ecs.push((Item, Point::new(10, 13)));
+    think(&mut ecs, &mut resources);
+    assert_eq!(decision(&ecs, looter), Decision::FetchItem);
+    assert_eq!(moves(&ecs), vec![Point::new(10, 11)]);
+
+    let (mut ecs, mut resources, _) = arena();
+    let looter = monster(&mut ecs, MONSTER, nodes, Health::new(10), None);
+    ecs.push((Item, Key, Point::new(10, 13)));
+    think(&mut ecs, &mut resources);
+    assert_eq!(decision(&ecs, looter), Decision::Idle);
+}
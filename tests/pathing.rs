@@ -0,0 +1,40 @@
+use ferros_rts::prelude::*;
+use ferros_rts::systems::first_step;
+
+#[test]
+fn paths_cut_corners_only_when_diagonals_are_allowed() {
+    let map = Map::new(10, 10);
+    let (from, to) = (Point::new(1, 1), Point::new(5, 5));
+
+    let diagonal = first_step(
+        &map.pathing(false, &MovementRules { diagonals: true }),
+        from,
+        to,
+    );
+    assert_eq!(diagonal, Some(Point::new(2, 2)));
+
+    let step = first_step(
+        &map.pathing(false, &MovementRules { diagonals: false }),
+        from,
+        to,
+    )
+    .expect("there is a way");
+    assert_eq!((step - from).x.abs() + (step - from).y.abs(), 1);
+}
+
+#[test]
+fn only_key_holders_path_through_locked_doors() {
+    let mut map = Map::new(5, 3);
+    for y in 0..3 {
+        map.set_tile(Point::new(2, y), TileType::Wall);
+    }
+    map.set_tile(Point::new(2, 1), TileType::DoorLocked);
+    let rules = MovementRules { diagonals: true };
+    let (from, to) = (Point::new(1, 1), Point::new(3, 1));
+
+    assert_eq!(first_step(&map.pathing(false, &rules), from, to), None);
+    assert_eq!(
+        first_step(&map.pathing(true, &rules), from, to),
+        Some(Point::new(2, 1))
+    );
+}
@@ -0,0 +1,50 @@
+use ferros_rts::prelude::*;
+use ferros_rts::systems;
+
+#[test]
+fn poison_ends_the_game_without_removing_the_player() {
+    let mut ecs = World::default();
+    let mut resources = Resources::default();
+    let mut effects = StatusEffects::default();
+    effects.apply(StatusEffect::new(EffectKind::Poison, 3, 2));
+    let player = ecs.push((
+        Player,
+        Point::new(1, 1),
+        Health {
+            current: 1,
+            max: 10,
+        },
+        effects,
+    ));
+    resources.insert(EventChannel::default());
+    resources.insert(SpatialIndex::default());
+    resources.insert(TurnState::MonsterTurn);
+
+    let mut schedule = Schedule::builder()
+        .add_system(systems::tick_status_effects_system())
+        .flush()
+        .add_system(systems::end_turn_system())
+        .build();
+    schedule.execute(&mut ecs, &mut resources);
+
+    assert!(ecs.entry(player).is_some());
+    assert_eq!(*resources.get::<TurnState>().unwrap(), TurnState::GameOver);
+    let deaths = resources
+        .get::<EventChannel>()
+        .unwrap()
+        .iter()
+        .filter(|event| matches!(event, GameEvent::UnitDied { .. }))
+        .count();
+    assert_eq!(deaths, 1);
+
+    // Nothing more happens to a dead player
+    resources.insert(TurnState::MonsterTurn);
+    resources.insert(EventChannel::default());
+    schedule.execute(&mut ecs, &mut resources);
+    let died_again = resources
+        .get::<EventChannel>()
+        .unwrap()
+        .iter()
+        .any(|event| matches!(event, GameEvent::UnitDied { .. }));
+    assert!(!died_again);
+}
@@ -0,0 +1,88 @@
+use ferros_rts::prelude::*;
+use ferros_rts::systems;
+
+/// A sure hit from `shooter` flying east from `from`
+fn arrow(shooter: Entity, from: Point) -> (Projectile, Point) {
+    let path = (1..=6).map(|step| from + Point::new(step, 0)).collect();
+    let projectile = Projectile {
+        shooter,
+        path,
+        next: 0,
+        attack: 100,
+        damage: Dice::new(0, 0, 5),
+        effect: None,
+    };
+    (projectile, from)
+}
+
+fn fire(map: Map, victim_at: Point, health: i32, arrows: usize) -> (World, Resources, Entity) {
+    let mut ecs = World::default();
+    let mut resources = Resources::default();
+    let shooter = ecs.push((Point::new(0, 2),));
+    let victim = ecs.push((
+        Enemy,
+        victim_at,
+        Health {
+            current: health,
+            max: 1,
+        },
+    ));
+    for _ in 0..arrows {
+        ecs.push(arrow(shooter, Point::new(0, 2)));
+    }
+    resources.insert(SpatialIndex::build(&map, &ecs));
+    resources.insert(map);
+    resources.insert(EventChannel::default());
+    resources.insert(GameRng::seeded(0));
+
+    let mut schedule = Schedule::builder()
+        .add_system(systems::projectiles_system())
+        .build();
+    schedule.execute(&mut ecs, &mut resources);
+    (ecs, resources, victim)
+}
+
+fn deaths(resources: &Resources) -> usize {
+    resources
+        .get::<EventChannel>()
+        .unwrap()
+        .iter()
+        .filter(|event| matches!(event, GameEvent::UnitDied { .. }))
+        .count()
+}
+
+#[test]
+fn a_unit_hit_by_two_arrows_dies_once() {
+    let (_, resources, _) = fire(Map::new(10, 5), Point::new(2, 2), 1, 2);
+    assert_eq!(deaths(&resources), 1);
+}
+
+#[test]
+fn a_unit_already_dead_does_not_die_again() {
+    let (_, resources, _) = fire(Map::new(10, 5), Point::new(2, 2), 0, 1);
+    assert_eq!(deaths(&resources), 0);
+}
+
+#[test]
+fn arrows_fly_over_chasms() {
+    let mut map = Map::new(10, 5);
+    map.set_tile(Point::new(1, 2), TileType::Chasm);
+    map.set_tile(Point::new(2, 2), TileType::Chasm);
+    let (_, resources, _) = fire(map, Point::new(3, 2), 1, 1);
+    assert_eq!(deaths(&resources), 1);
+}
+
+#[test]
+fn arrows_stop_at_walls() {
+    let mut map = Map::new(10, 5);
+    map.set_tile(Point::new(2, 2), TileType::Wall);
+    let (ecs, resources, victim) = fire(map, Point::new(3, 2), 1, 1);
+    assert_eq!(deaths(&resources), 0);
+    let health = ecs
+        .entry_ref(victim)
+        .unwrap()
+        .get_component::<Health>()
+        .unwrap()
+        .current;
+    assert_eq!(health, 1);
+}
@@ -0,0 +1,72 @@
+use ferros_rts::prelude::*;
+use ferros_rts::systems;
+
+fn press(menu: RecipeMenu, keys: &[VirtualKeyCode]) -> RecipeMenu {
+    let mut ecs = World::default();
+    let mut resources = Resources::default();
+    resources.insert(menu);
+    let mut schedule = Schedule::builder()
+        .add_system(systems::choose_recipe_system())
+        .build();
+    for key in keys {
+        resources.insert(Some(*key));
+        schedule.execute(&mut ecs, &mut resources);
+    }
+    resources
+        .remove::<RecipeMenu>()
+        .expect("the menu stays open")
+}
+
+#[test]
+fn the_shipped_recipes_are_listed_by_name() {
+    let recipes = GeneratorRecipe::list(RECIPES_DIR);
+    let names: Vec<&str> = recipes.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["chain", "default", "winding"]);
+    for (name, recipe) in &recipes {
+        assert!(recipe.is_ok(), "{} does not load", name);
+    }
+}
+
+#[test]
+fn the_menu_starts_on_the_default_recipe() {
+    let menu = RecipeMenu::new(RECIPES_DIR, None);
+    assert_eq!(menu.recipes[menu.selected].0, "default");
+
+    let empty = std::env::temp_dir().join(format!("ferros-no-recipes-{}", std::process::id()));
+    let menu = RecipeMenu::new(&empty, None);
+    assert_eq!(menu.recipes.len(), 1);
+    assert_eq!(menu.recipes[0].0, "default");
+}
+
+#[test]
+fn return_chooses_the_highlighted_recipe_with_the_given_seed() {
+    let menu = press(
+        RecipeMenu::new(RECIPES_DIR, Some(77)),
+        &[VirtualKeyCode::Down, VirtualKeyCode::Return],
+    );
+    assert_eq!(menu.recipes[menu.selected].0, "winding");
+    let chosen = menu.chosen.expect("a recipe was chosen");
+    assert_eq!(chosen.seed, Some(77));
+    assert_eq!(
+        chosen.commands.len(),
+        GeneratorRecipe::load("recipes/winding.ron")
+            .expect("winding loads")
+            .commands
+            .len()
+    );
+}
+
+#[test]
+fn a_broken_recipe_cannot_be_chosen() {
+    let menu = RecipeMenu {
+        recipes: vec![(
+            "broken".to_string(),
+            Err("map size 1x1 is too small".into()),
+        )],
+        selected: 0,
+        seed: None,
+        chosen: None,
+    };
+    let menu = press(menu, &[VirtualKeyCode::Return]);
+    assert!(menu.chosen.is_none());
+}
@@ -0,0 +1,38 @@
+use ferros_rts::prelude::*;
+
+fn recording(name: &str) -> (ReplayMode, std::path::PathBuf) {
+    let path = std::env::temp_dir().join(format!("ferros-{}-{}.ron", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let replay = Replay::new(7, GeneratorRecipe::default(), Keybindings::default());
+    (
+        ReplayMode::Recording {
+            replay,
+            path: path.clone(),
+        },
+        path,
+    )
+}
+
+#[test]
+fn a_recording_is_only_written_on_checksum_turns() {
+    let (mut mode, path) = recording("checksum-turns");
+    let (ecs, resources) = (World::default(), Resources::default());
+
+    for turn in 1..Replay::CHECKSUM_INTERVAL {
+        mode.turn_ended(turn, &ecs, &resources);
+        assert!(!path.exists(), "written on turn {}", turn);
+    }
+    mode.turn_ended(Replay::CHECKSUM_INTERVAL, &ecs, &resources);
+    let saved = Replay::load(&path).expect("written on the checksum turn");
+    assert_eq!(saved.checksums.len(), 1);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn saving_writes_a_recording_between_checksum_turns() {
+    let (mut mode, path) = recording("between-checksums");
+    mode.turn_ended(1, &World::default(), &Resources::default());
+    mode.save();
+    assert!(Replay::load(&path).is_ok());
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,77 @@
+use ferros_rts::prelude::*;
+
+fn level_with_player(effects: StatusEffects) -> (World, Resources, Entity) {
+    let mut ecs = World::default();
+    let mut resources = Resources::default();
+    let player = ecs.push((
+        Player,
+        Point::new(3, 4),
+        Health::new(20),
+        Name::from("Hero"),
+        effects,
+    ));
+    ecs.push((Item, Name::from("Key"), Key, Carried { owner: player }));
+    ecs.push((
+        Enemy,
+        Point::new(6, 4),
+        Health::new(2),
+        StatusEffects::default(),
+    ));
+    resources.insert(Map::new(10, 10));
+    resources.insert(Depth(2));
+    (ecs, resources, player)
+}
+
+fn save_and_load(ecs: &World, resources: &Resources, name: &str) -> (World, Resources) {
+    let path = std::env::temp_dir().join(format!("ferros-{}-{}.json", name, std::process::id()));
+    SaveGame::capture(ecs, resources)
+        .and_then(|save| save.save(&path))
+        .expect("could not save");
+    let save = SaveGame::load(&path).expect("could not load");
+    std::fs::remove_file(&path).ok();
+
+    let mut ecs = World::default();
+    let mut resources = Resources::default();
+    save.restore(&mut ecs, &mut resources)
+        .expect("could not restore");
+    (ecs, resources)
+}
+
+#[test]
+fn status_effects_survive_save_and_load() {
+    let mut effects = StatusEffects::default();
+    effects.apply(StatusEffect::new(EffectKind::Poison, 4, 2));
+    effects.apply(StatusEffect::new(EffectKind::Haste, 7, 1));
+    let (ecs, resources, _) = level_with_player(effects.clone());
+
+    let (ecs, resources) = save_and_load(&ecs, &resources, "effects");
+
+    let loaded = <&StatusEffects>::query()
+        .filter(component::<Player>())
+        .iter(&ecs)
+        .next()
+        .cloned();
+    assert_eq!(loaded, Some(effects));
+    assert_eq!(resources.get::<Depth>().map(|depth| depth.0), Some(2));
+}
+
+#[test]
+fn carried_items_keep_their_owner() {
+    let (ecs, resources, _) = level_with_player(StatusEffects::default());
+
+    let (ecs, _) = save_and_load(&ecs, &resources, "carried");
+
+    let player = <Entity>::query()
+        .filter(component::<Player>())
+        .iter(&ecs)
+        .next()
+        .copied()
+        .expect("the player was not loaded");
+    let owners: Vec<Entity> = <&Carried>::query()
+        .filter(component::<Key>())
+        .iter(&ecs)
+        .map(|carried| carried.owner)
+        .collect();
+    assert_eq!(owners, vec![player]);
+    assert_eq!(<&Enemy>::query().iter(&ecs).count(), 1);
+}
@@ -0,0 +1,41 @@
+use std::collections::HashSet;
+
+use ferros_rts::prelude::*;
+
+/// Whether `index` lists exactly the entities standing on each tile
+fn matches_world(index: &SpatialIndex, ecs: &World, map: &Map) -> bool {
+    let rebuilt = SpatialIndex::build(map, ecs);
+    (0..map.tiles.len())
+        .map(|idx| map.point_at(idx))
+        .all(|point| {
+            let kept: HashSet<Entity> = index.at(point).iter().copied().collect();
+            let expected: HashSet<Entity> = rebuilt.at(point).iter().copied().collect();
+            kept == expected
+        })
+}
+
+#[test]
+fn the_index_keeps_up_with_a_skirmish() {
+    let mut skirmish = Skirmish::new(&SkirmishSettings::default());
+    let mut commanders: Vec<Commander> = skirmish
+        .factions()
+        .into_iter()
+        .map(|faction| Commander::new(faction, Difficulty::Hard))
+        .collect();
+
+    for _ in 0..300 {
+        let orders = commanders
+            .iter_mut()
+            .flat_map(|commander| commander.orders(&skirmish))
+            .collect();
+        skirmish.advance(orders);
+
+        let map = skirmish.resources.get::<Map>().unwrap();
+        let index = skirmish.resources.get::<SpatialIndex>().unwrap();
+        assert!(
+            matches_world(&index, &skirmish.ecs, &map),
+            "the index fell behind on turn {}",
+            skirmish.turn()
+        );
+    }
+}